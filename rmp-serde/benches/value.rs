@@ -0,0 +1,234 @@
+#![feature(test)]
+
+extern crate test;
+extern crate serde;
+extern crate rmp;
+extern crate rmp_serde;
+
+use std::collections::BTreeMap;
+
+use test::Bencher;
+
+use rmp_serde::value::{from_value, to_value};
+
+/// Flat struct, implemented the way `#[derive]` does.
+struct Record {
+    id: u64,
+    name: String,
+    score: f64,
+    active: bool,
+}
+
+impl serde::Serialize for Record {
+    fn serialize<S>(&self, ser: &mut S) -> Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        struct Visitor<'a>(&'a Record, usize);
+
+        impl<'a> serde::ser::MapVisitor for Visitor<'a> {
+            fn visit<S>(&mut self, ser: &mut S) -> Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                self.1 += 1;
+                match self.1 {
+                    1 => ser.serialize_struct_elt("id", self.0.id).map(Some),
+                    2 => ser.serialize_struct_elt("name", &self.0.name).map(Some),
+                    3 => ser.serialize_struct_elt("score", self.0.score).map(Some),
+                    4 => ser.serialize_struct_elt("active", self.0.active).map(Some),
+                    _ => Ok(None),
+                }
+            }
+
+            fn len(&self) -> Option<usize> {
+                Some(4)
+            }
+        }
+
+        ser.serialize_struct("Record", Visitor(self, 0))
+    }
+}
+
+enum Field {
+    Id,
+    Name,
+    Score,
+    Active,
+    Ignore,
+}
+
+impl serde::Deserialize for Field {
+    fn deserialize<D>(de: &mut D) -> Result<Field, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = Field;
+
+            fn visit_str<E>(&mut self, value: &str) -> Result<Field, E>
+                where E: serde::de::Error
+            {
+                Ok(match value {
+                    "id" => Field::Id,
+                    "name" => Field::Name,
+                    "score" => Field::Score,
+                    "active" => Field::Active,
+                    _ => Field::Ignore,
+                })
+            }
+        }
+
+        de.deserialize_struct_field(Visitor)
+    }
+}
+
+impl serde::Deserialize for Record {
+    fn deserialize<D>(de: &mut D) -> Result<Record, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = Record;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> Result<Record, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                let (mut id, mut name, mut score, mut active) = (None, None, None, None);
+
+                while let Some(field) = try!(visitor.visit_key()) {
+                    match field {
+                        Field::Id => id = Some(try!(visitor.visit_value())),
+                        Field::Name => name = Some(try!(visitor.visit_value())),
+                        Field::Score => score = Some(try!(visitor.visit_value())),
+                        Field::Active => active = Some(try!(visitor.visit_value())),
+                        Field::Ignore => { try!(visitor.visit_value::<serde::de::impls::IgnoredAny>()); }
+                    }
+                }
+
+                try!(visitor.end());
+
+                match (id, name, score, active) {
+                    (Some(id), Some(name), Some(score), Some(active)) => {
+                        Ok(Record { id: id, name: name, score: score, active: active })
+                    }
+                    _ => Err(serde::de::Error::missing_field("id")),
+                }
+            }
+        }
+
+        de.deserialize_struct("Record", &["id", "name", "score", "active"], Visitor)
+    }
+}
+
+/// Newtype struct, implemented the way `#[derive]` does.
+struct Wrapper<T>(T);
+
+impl<T: serde::Serialize> serde::Serialize for Wrapper<T> {
+    fn serialize<S>(&self, ser: &mut S) -> Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        ser.serialize_newtype_struct("Wrapper", &self.0)
+    }
+}
+
+impl<T: serde::Deserialize> serde::Deserialize for Wrapper<T> {
+    fn deserialize<D>(de: &mut D) -> Result<Wrapper<T>, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor<T>(::std::marker::PhantomData<T>);
+
+        impl<T: serde::Deserialize> serde::de::Visitor for Visitor<T> {
+            type Value = Wrapper<T>;
+
+            fn visit_newtype_struct<D>(&mut self, de: &mut D) -> Result<Wrapper<T>, D::Error>
+                where D: serde::Deserializer
+            {
+                Ok(Wrapper(try!(serde::Deserialize::deserialize(de))))
+            }
+
+            fn visit_seq<V>(&mut self, mut visitor: V) -> Result<Wrapper<T>, V::Error>
+                where V: serde::de::SeqVisitor
+            {
+                let value = match try!(visitor.visit()) {
+                    Some(value) => value,
+                    None => return Err(serde::de::Error::end_of_stream()),
+                };
+                try!(visitor.end());
+
+                Ok(Wrapper(value))
+            }
+        }
+
+        de.deserialize_newtype_struct("Wrapper", Visitor(::std::marker::PhantomData))
+    }
+}
+
+fn record(id: u64) -> Record {
+    Record {
+        id: id,
+        name: "Lorem ipsum dolor sit amet".to_string(),
+        score: 0.5,
+        active: true,
+    }
+}
+
+#[bench]
+fn to_value_flat_struct(b: &mut Bencher) {
+    let record = record(42);
+
+    b.iter(|| {
+        test::black_box(to_value(&record));
+    });
+}
+
+#[bench]
+fn from_value_flat_struct(b: &mut Bencher) {
+    let value = to_value(&record(42));
+
+    b.iter(|| {
+        let record: Record = from_value(value.clone()).unwrap();
+        test::black_box(record);
+    });
+}
+
+#[bench]
+fn from_value_flat_struct_seq(b: &mut Bencher) {
+    let value = to_value(&(0..100).map(record).collect::<Vec<_>>());
+
+    b.iter(|| {
+        let records: Vec<Record> = from_value(value.clone()).unwrap();
+        test::black_box(records);
+    });
+}
+
+#[bench]
+fn from_value_int_map(b: &mut Bencher) {
+    let map: BTreeMap<String, i64> = (0..100).map(|i| (i.to_string(), i)).collect();
+    let value = to_value(&map);
+
+    b.iter(|| {
+        let map: BTreeMap<String, i64> = from_value(value.clone()).unwrap();
+        test::black_box(map);
+    });
+}
+
+#[bench]
+fn from_value_nested_newtype(b: &mut Bencher) {
+    let value = to_value(&Wrapper(Wrapper(Wrapper(Wrapper(record(42))))));
+
+    b.iter(|| {
+        let record: Wrapper<Wrapper<Wrapper<Wrapper<Record>>>> = from_value(value.clone()).unwrap();
+        test::black_box(record);
+    });
+}
+
+#[bench]
+fn clone_flat_struct_seq(b: &mut Bencher) {
+    // Baseline for the clones done by the decoding benchmarks.
+    let value = to_value(&(0..100).map(record).collect::<Vec<_>>());
+
+    b.iter(|| {
+        test::black_box(value.clone());
+    });
+}
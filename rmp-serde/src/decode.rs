@@ -1,11 +1,20 @@
 use std::convert::From;
 use std::fmt;
-use std::io::Read;
+use std::io::{self, Cursor, Read};
+use std::marker::PhantomData;
+use std::mem;
 use std::result;
+use std::str;
 
+#[cfg(feature = "tokio")]
+use futures::{Async, Future, Poll};
 use serde;
+use serde::bytes::ByteBuf;
+use serde::de::value::{SeqDeserializer, ValueDeserializer};
+#[cfg(feature = "tokio")]
+use tokio_io::AsyncRead;
 
-use rmp::Marker;
+use rmp::{Marker, Value};
 use rmp::decode::{
     DecodeStringError,
     FixedValueReadError,
@@ -17,7 +26,9 @@ use rmp::decode::{
     read_str_data,
     read_marker,
     read_full,
+    read_value,
 };
+use rmp::decode::value::Error as ValueError;
 
 /// Unstable: docs; incomplete
 #[derive(Debug)]
@@ -31,6 +42,12 @@ pub enum Error {
     Uncategorized(String),
     Syntax(String),
     DepthLimitExceeded,
+    /// The total size claimed by strings, binaries and containers exceeds the configured limit.
+    SizeLimitExceeded,
+    /// The input continues with the given number of bytes after the decoded value.
+    TrailingBytes(usize),
+    /// A string is not valid UTF-8.
+    InvalidUtf8(str::Utf8Error),
 }
 
 impl ::std::error::Error for Error {
@@ -46,6 +63,9 @@ impl ::std::error::Error for Error {
             Uncategorized(_) => None,
             Syntax(_) => None,
             DepthLimitExceeded => None,
+            SizeLimitExceeded => None,
+            TrailingBytes(_) => None,
+            InvalidUtf8(ref err) => Some(err),
         }
     }
 }
@@ -153,6 +173,22 @@ impl<'a> From<DecodeStringError<'a>> for Error {
     }
 }
 
+impl From<ValueError> for Error {
+    fn from(err: ValueError) -> Error {
+        match err {
+            ValueError::InvalidMarkerRead(err) => Error::InvalidMarkerRead(err),
+            ValueError::InvalidDataRead(err) => Error::InvalidDataRead(err),
+            ValueError::TypeMismatch(marker) => Error::TypeMismatch(marker),
+            ValueError::BufferSizeTooSmall(..) => Error::Uncategorized("BufferSizeTooSmall".to_string()),
+            ValueError::InvalidDataCopy(_, err) => Error::InvalidDataRead(err),
+            ValueError::InvalidUtf8(_, err) => Error::InvalidUtf8(err),
+            ValueError::InvalidArrayRead(err) => From::from(*err),
+            ValueError::InvalidMapKeyRead(err) => From::from(*err),
+            ValueError::InvalidMapValueRead(err) => From::from(*err),
+        }
+    }
+}
+
 impl From<MarkerReadError> for Error {
     fn from(err: MarkerReadError) -> Error {
         Error::InvalidMarkerRead(From::from(err))
@@ -203,6 +239,13 @@ pub struct Deserializer<R: Read> {
     buf: Vec<u8>,
     decoding_option: bool,
     depth: usize,
+    bytes_left: Option<usize>,
+    validate_utf8: bool,
+    bin_as_seq: bool,
+    ext_as_map: bool,
+    max_str_len: Option<u32>,
+    max_bin_len: Option<u32>,
+    max_seq_len: Option<u32>,
 }
 
 macro_rules! depth_count(
@@ -227,6 +270,13 @@ impl<R: Read> Deserializer<R> {
             buf: Vec::new(),
             decoding_option: false,
             depth: 1000,
+            bytes_left: None,
+            validate_utf8: true,
+            bin_as_seq: false,
+            ext_as_map: false,
+            max_str_len: None,
+            max_bin_len: None,
+            max_seq_len: None,
         }
     }
 
@@ -235,6 +285,70 @@ impl<R: Read> Deserializer<R> {
         self.depth = depth;
     }
 
+    /// Limits the total size of strings and binaries in bytes plus the number of array elements
+    /// and map entries that may be decoded, protecting against payloads claiming huge lengths.
+    ///
+    /// The lengths are checked before anything is allocated. The limit applies to everything
+    /// decoded from now on, so it should be set again before each value to limit them separately.
+    pub fn set_max_total_bytes(&mut self, limit: usize) {
+        self.bytes_left = Some(limit);
+    }
+
+    /// Rejects strings longer than `len` bytes with `SizeLimitExceeded`, before reading them.
+    pub fn set_max_str_len(&mut self, len: u32) {
+        self.max_str_len = Some(len);
+    }
+
+    /// Rejects binaries and ext payloads longer than `len` bytes with `SizeLimitExceeded`, before
+    /// reading them.
+    pub fn set_max_bin_len(&mut self, len: u32) {
+        self.max_bin_len = Some(len);
+    }
+
+    /// Rejects arrays with more than `len` elements with `SizeLimitExceeded`.
+    ///
+    /// The count is checked as elements are decoded, so an array claiming a huge length fails
+    /// after decoding `len` elements at most.
+    pub fn set_max_seq_len(&mut self, len: u32) {
+        self.max_seq_len = Some(len);
+    }
+
+    /// Controls whether strings are checked to be valid UTF-8 before being passed on as `&str`,
+    /// which is the default.
+    ///
+    /// With validation disabled, strings are passed on unchecked with `visit_bytes`, so types that
+    /// accept raw bytes skip a pass over every string when the input comes from a trusted encoder.
+    /// Types that need a `str` still validate the bytes themselves.
+    pub fn set_validate_utf8(&mut self, enabled: bool) {
+        self.validate_utf8 = enabled;
+    }
+
+    /// Passes binaries to visitors as sequences of bytes instead of with `visit_bytes`.
+    ///
+    /// This allows decoding into types that only accept sequences, like `serde_json::Value`, where
+    /// binaries become arrays of integers.
+    pub fn set_bin_as_seq(&mut self, enabled: bool) {
+        self.bin_as_seq = enabled;
+    }
+
+    /// Passes ext values to visitors as maps `{"type": ty, "data": data}` instead of failing.
+    ///
+    /// The data is a binary, which is affected by `set_bin_as_seq`.
+    pub fn set_ext_as_map(&mut self, enabled: bool) {
+        self.ext_as_map = enabled;
+    }
+
+    fn charge(&mut self, len: u32) -> Result<()> {
+        if let Some(ref mut left) = self.bytes_left {
+            if len as usize > *left {
+                return Err(Error::SizeLimitExceeded);
+            }
+            *left -= len as usize;
+        }
+
+        Ok(())
+    }
+
     /// Gets a reference to the underlying reader in this decoder.
     pub fn get_ref(&self) -> &R {
         &self.rd
@@ -253,14 +367,28 @@ impl<R: Read> Deserializer<R> {
     fn read_str<V>(&mut self, len: u32, mut visitor: V) -> Result<V::Value>
         where V: serde::de::Visitor
     {
+        try!(check_len(len, self.max_str_len));
+        try!(self.charge(len));
         self.buf.clear();
         self.buf.extend((0..len).map(|_| 0));
-        visitor.visit_str(try!(read_str_data(&mut self.rd, len, &mut self.buf[..])))
+
+        if self.validate_utf8 {
+            return visitor.visit_str(try!(read_str_data(&mut self.rd, len, &mut self.buf[..])));
+        }
+
+        match read_full(&mut self.rd, &mut self.buf[..]) {
+            Ok(n) if n == self.buf.len() => (),
+            Ok(..)   => return Err(Error::InvalidDataRead(ReadError::UnexpectedEOF)),
+            Err(err) => return Err(Error::InvalidDataRead(ReadError::Io(err))),
+        }
+
+        visitor.visit_bytes(&self.buf[..])
     }
 
     fn read_array<V>(&mut self, len: u32, mut visitor: V) -> Result<V::Value>
         where V: serde::de::Visitor
     {
+        try!(self.charge(len));
         depth_count!(self.depth, visitor.visit_seq(SeqVisitor {
             deserializer: self,
             len: len,
@@ -271,6 +399,7 @@ impl<R: Read> Deserializer<R> {
     fn read_map<V>(&mut self, len: u32, mut visitor: V) -> Result<V::Value>
         where V: serde::de::Visitor
     {
+        try!(self.charge(len));
         depth_count!(self.depth, visitor.visit_map(MapVisitor {
             deserializer: self,
             len: len,
@@ -278,9 +407,13 @@ impl<R: Read> Deserializer<R> {
         }))
     }
 
+    /// Binaries are read into the internal buffer and lent to the visitor with `visit_bytes`,
+    /// sparing an allocation. Visitors implementing only `visit_byte_buf` are not supported.
     fn read_bin_data<V>(&mut self, len: usize, mut visitor: V) -> Result<V::Value>
         where V: serde::de::Visitor
     {
+        try!(check_len(len as u32, self.max_bin_len));
+        try!(self.charge(len as u32));
         self.buf.clear();
         self.buf.extend((0..len).map(|_| 0));
 
@@ -290,8 +423,95 @@ impl<R: Read> Deserializer<R> {
             Err(err) => return Err(Error::InvalidDataRead(ReadError::Io(err))),
         }
 
+        if self.bin_as_seq {
+            let len = self.buf.len();
+            return visitor.visit_seq(SeqDeserializer::new(self.buf.drain(..), len));
+        }
+
         visitor.visit_bytes(&mut self.buf[..])
     }
+
+    fn read_ext<V>(&mut self, len: u32, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        try!(check_len(len, self.max_bin_len));
+        let ty: i8 = try!(read_numeric_data(&mut self.rd));
+
+        try!(self.charge(len));
+        let mut data = vec![0; len as usize];
+        match read_full(&mut self.rd, &mut data[..]) {
+            Ok(n) if n == data.len() => (),
+            Ok(..)   => return Err(Error::InvalidDataRead(ReadError::UnexpectedEOF)),
+            Err(err) => return Err(Error::InvalidDataRead(ReadError::Io(err))),
+        }
+
+        visitor.visit_map(ExtMapVisitor {
+            ty: ty,
+            data: Some(data),
+            bin_as_seq: self.bin_as_seq,
+            state: 0,
+        })
+    }
+}
+
+fn check_len(len: u32, max: Option<u32>) -> Result<()> {
+    match max {
+        Some(max) if len > max => Err(Error::SizeLimitExceeded),
+        _ => Ok(()),
+    }
+}
+
+/// Visits an ext value as a map with its type and data.
+struct ExtMapVisitor {
+    ty: i8,
+    data: Option<Vec<u8>>,
+    bin_as_seq: bool,
+    state: u8,
+}
+
+impl serde::de::MapVisitor for ExtMapVisitor {
+    type Error = Error;
+
+    fn visit_key<K>(&mut self) -> Result<Option<K>>
+        where K: serde::de::Deserialize
+    {
+        let key = match self.state {
+            0 => "type",
+            1 => "data",
+            _ => return Ok(None),
+        };
+
+        self.state += 1;
+        serde::Deserialize::deserialize(&mut key.into_deserializer()).map(Some)
+    }
+
+    fn visit_value<V>(&mut self) -> Result<V>
+        where V: serde::de::Deserialize
+    {
+        match (self.state, self.data.take()) {
+            (1, data) => {
+                self.data = data;
+                serde::Deserialize::deserialize(&mut self.ty.into_deserializer())
+            }
+            (_, Some(data)) => {
+                if self.bin_as_seq {
+                    serde::Deserialize::deserialize(&mut data.into_deserializer())
+                } else {
+                    serde::Deserialize::deserialize(&mut ByteBuf::from(data).into_deserializer())
+                }
+            }
+            (_, None) => Err(serde::de::Error::end_of_stream()),
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = 2 - self.state as usize;
+        (len, Some(len))
+    }
 }
 
 /// Unstable: docs; examples; incomplete
@@ -302,10 +522,12 @@ impl<R: Read> serde::Deserializer for Deserializer<R> {
         where V: serde::de::Visitor
     {
         let marker = try!(read_marker(&mut self.rd));
+        // Only the first marker belongs to the option, nested nils are units.
+        let decoding_option = mem::replace(&mut self.decoding_option, false);
 
         match marker {
             Marker::Null => {
-                if self.decoding_option {
+                if decoding_option {
                     visitor.visit_none()
                 } else {
                     visitor.visit_unit()
@@ -372,8 +594,25 @@ impl<R: Read> serde::Deserializer for Deserializer<R> {
                 let len: u32 = try!(read_numeric_data(&mut self.rd));
                 self.read_bin_data(len as usize, visitor)
             }
+            Marker::FixExt1 if self.ext_as_map => self.read_ext(1, visitor),
+            Marker::FixExt2 if self.ext_as_map => self.read_ext(2, visitor),
+            Marker::FixExt4 if self.ext_as_map => self.read_ext(4, visitor),
+            Marker::FixExt8 if self.ext_as_map => self.read_ext(8, visitor),
+            Marker::FixExt16 if self.ext_as_map => self.read_ext(16, visitor),
+            Marker::Ext8 if self.ext_as_map => {
+                let len: u8 = try!(read_numeric_data(&mut self.rd));
+                self.read_ext(len as u32, visitor)
+            }
+            Marker::Ext16 if self.ext_as_map => {
+                let len: u16 = try!(read_numeric_data(&mut self.rd));
+                self.read_ext(len as u32, visitor)
+            }
+            Marker::Ext32 if self.ext_as_map => {
+                let len: u32 = try!(read_numeric_data(&mut self.rd));
+                self.read_ext(len, visitor)
+            }
             Marker::Reserved => Err(Error::TypeMismatch(Marker::Reserved)),
-            // TODO: Make something with exts.
+            // Ext values are only supported with `ext_as_map`.
             marker => Err(From::from(FixedValueReadError::TypeMismatch(marker))),
         }
     }
@@ -401,9 +640,26 @@ impl<R: Read> serde::Deserializer for Deserializer<R> {
         res
     }
 
+    /// Unit structs are accepted both from nil and from an empty array, which is how they are
+    /// encoded, like `PhantomData`.
+    fn deserialize_unit_struct<V>(&mut self, _name: &'static str, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        let marker = try!(read_marker(&mut self.rd));
+        let decoding_option = mem::replace(&mut self.decoding_option, false);
+
+        match marker {
+            Marker::Null if decoding_option => Err(Error::TypeMismatch(Marker::Null)),
+            Marker::Null | Marker::FixArray(0) => visitor.visit_unit(),
+            Marker::FixArray(len) => Err(Error::LengthMismatch(len as u32)),
+            marker => Err(Error::TypeMismatch(marker)),
+        }
+    }
+
     fn deserialize_enum<V>(&mut self, _enum: &str, _variants: &[&str], mut visitor: V) -> Result<V::Value>
         where V: serde::de::EnumVisitor
     {
+        self.decoding_option = false;
         let len = try!(read_array_size(&mut self.rd));
 
         match len {
@@ -426,6 +682,12 @@ impl<'a, R: Read + 'a> serde::de::SeqVisitor for SeqVisitor<'a, R> {
         where T: serde::de::Deserialize,
     {
         if self.len > 0 {
+            if let Some(max) = self.deserializer.max_seq_len {
+                if self.actual - self.len >= max {
+                    return Err(Error::SizeLimitExceeded);
+                }
+            }
+
             self.len -= 1;
             let value = try!(serde::Deserialize::deserialize(self.deserializer));
             Ok(Some(value))
@@ -471,6 +733,13 @@ impl<'a, R: Read + 'a> serde::de::MapVisitor for MapVisitor<'a, R> {
         Ok(value)
     }
 
+    /// Decodes omitted `Option` fields as `None`.
+    fn missing_field<V>(&mut self, field: &'static str) -> Result<V>
+        where V: serde::de::Deserialize,
+    {
+        serde::Deserialize::deserialize(&mut MissingFieldDeserializer::new(field))
+    }
+
     fn end(&mut self) -> Result<()> {
         if self.len == 0 {
             Ok(())
@@ -480,6 +749,40 @@ impl<'a, R: Read + 'a> serde::de::MapVisitor for MapVisitor<'a, R> {
     }
 }
 
+/// Deserializer standing in for a field missing from a map.
+///
+/// Only `Option` fields can be decoded from it, as `None`. Every other type fails with a
+/// `missing_field` error.
+pub struct MissingFieldDeserializer<E> {
+    field: &'static str,
+    marker: PhantomData<E>,
+}
+
+impl<E> MissingFieldDeserializer<E> {
+    pub fn new(field: &'static str) -> MissingFieldDeserializer<E> {
+        MissingFieldDeserializer {
+            field: field,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E: serde::de::Error> serde::Deserializer for MissingFieldDeserializer<E> {
+    type Error = E;
+
+    fn deserialize<V>(&mut self, _visitor: V) -> result::Result<V::Value, E>
+        where V: serde::de::Visitor
+    {
+        Err(serde::de::Error::missing_field(self.field))
+    }
+
+    fn deserialize_option<V>(&mut self, mut visitor: V) -> result::Result<V::Value, E>
+        where V: serde::de::Visitor
+    {
+        visitor.visit_none()
+    }
+}
+
 /// Default variant visitor.
 ///
 /// # Note
@@ -523,10 +826,17 @@ impl<'a, R: Read> serde::de::VariantVisitor for VariantVisitor<'a, R> {
         T::deserialize(self.de)
     }
 
+    /// Tuple variants are expected as an array of exactly as many arguments as the variant has.
     fn visit_tuple<V>(&mut self, len: usize, visitor: V) -> Result<V::Value>
         where V: serde::de::Visitor,
     {
-        serde::de::Deserializer::deserialize_tuple(self.de, len, visitor)
+        let actual = try!(read_array_size(&mut self.de.rd));
+
+        if actual as usize != len {
+            return Err(Error::LengthMismatch(actual));
+        }
+
+        self.de.read_array(actual, visitor)
     }
 
     fn visit_struct<V>(&mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
@@ -535,3 +845,322 @@ impl<'a, R: Read> serde::de::VariantVisitor for VariantVisitor<'a, R> {
         serde::de::Deserializer::deserialize_tuple(self.de, fields.len(), visitor)
     }
 }
+
+/// Incremental decoder for values arriving in arbitrary chunks, like from a socket.
+///
+/// Bytes are accumulated with `feed` until a complete value is available, so a value split across
+/// several chunks is never lost.
+///
+/// # Examples
+/// ```
+/// use rmp_serde::decode::StreamDecoder;
+///
+/// let mut decoder = StreamDecoder::new();
+///
+/// decoder.feed(&[0x92, 0x01]);
+/// assert!(decoder.next_value().unwrap().is_none());
+///
+/// decoder.feed(&[0x02, 0xc0]);
+/// assert!(decoder.next_value().unwrap().is_some());
+/// assert!(decoder.next_value().unwrap().is_some());
+/// assert!(decoder.next_value().unwrap().is_none());
+/// ```
+pub struct StreamDecoder {
+    buf: Vec<u8>,
+    scanner: Scanner,
+}
+
+impl StreamDecoder {
+    pub fn new() -> StreamDecoder {
+        StreamDecoder {
+            buf: Vec::new(),
+            scanner: Scanner::new(),
+        }
+    }
+
+    /// Appends the given bytes to the internal buffer.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Changes the maximum nesting depth that is allowed, like `Deserializer::set_max_depth`.
+    ///
+    /// Values nested deeper fail with `DepthLimitExceeded` as soon as their header is scanned,
+    /// before anything is decoded.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.scanner.max_depth = depth;
+    }
+
+    /// Returns the number of buffered bytes not yet consumed by a decoded value.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Attempts to decode the next complete value from the buffered bytes.
+    ///
+    /// Returns `None` if more bytes are required, in which case the buffer is left untouched.
+    /// Otherwise the decoded bytes are removed from the buffer.
+    ///
+    /// The bytes already scanned are remembered between calls, so a value arriving in many small
+    /// chunks is still decoded in linear time.
+    pub fn next_value(&mut self) -> Result<Option<Value>> {
+        let len = match try!(self.scanner.scan(&self.buf)) {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+
+        let value = try!(read_value(&mut Cursor::new(&self.buf[..len])));
+        self.buf.drain(..len);
+
+        Ok(Some(value))
+    }
+}
+
+/// Finds where the first value of a growing buffer ends, without decoding it.
+///
+/// The scan resumes where the previous one stopped, so every byte is looked at only once no matter
+/// how many chunks the value arrives in.
+struct Scanner {
+    /// Offset of the first element not scanned yet.
+    pos: usize,
+    /// Number of elements still expected by each enclosing array or map, innermost last.
+    pending: Vec<u64>,
+    /// Nesting depth at which arrays and maps are rejected, counted as in `Deserializer`.
+    max_depth: usize,
+}
+
+impl Scanner {
+    fn new() -> Scanner {
+        Scanner {
+            pos: 0,
+            pending: Vec::new(),
+            max_depth: 1000,
+        }
+    }
+
+    /// Returns the length of the first value of the buffer once it is complete, after which the
+    /// scanner starts over for the next value.
+    fn scan(&mut self, buf: &[u8]) -> Result<Option<usize>> {
+        loop {
+            let (size, children) = match try!(element_size(&buf[self.pos..])) {
+                Some(element) => element,
+                None => return Ok(None),
+            };
+
+            if buf.len() - self.pos < size {
+                return Ok(None);
+            }
+            self.pos += size;
+
+            if children > 0 {
+                if self.pending.len() + 1 >= self.max_depth {
+                    return Err(Error::DepthLimitExceeded);
+                }
+
+                self.pending.push(children);
+                continue;
+            }
+
+            // The element is complete, and so are the containers it was the last element of.
+            loop {
+                match self.pending.last_mut() {
+                    Some(left) if *left > 1 => {
+                        *left -= 1;
+                        break;
+                    }
+                    Some(..) => {}
+                    None => {
+                        let len = self.pos;
+                        self.pos = 0;
+                        return Ok(Some(len));
+                    }
+                }
+
+                self.pending.pop();
+            }
+        }
+    }
+}
+
+/// Returns the size of the element at the start of the given buffer, excluding the elements it
+/// contains, along with their number, or `None` if the buffer ends before its header does.
+fn element_size(buf: &[u8]) -> Result<Option<(usize, u64)>> {
+    let marker = match buf.first() {
+        Some(&byte) => Marker::from_u8(byte),
+        None => return Ok(None),
+    };
+
+    // Length following the marker, stored in the given number of bytes.
+    let len = |n: usize| {
+        if buf.len() < 1 + n {
+            None
+        } else {
+            Some(buf[1..1 + n].iter().fold(0u64, |len, &byte| len << 8 | byte as u64))
+        }
+    };
+
+    let (header, n) = match marker {
+        Marker::FixPos(..) | Marker::FixNeg(..) | Marker::Null | Marker::True | Marker::False => {
+            return Ok(Some((1, 0)));
+        }
+        Marker::U8 | Marker::I8 => return Ok(Some((2, 0))),
+        Marker::U16 | Marker::I16 => return Ok(Some((3, 0))),
+        Marker::U32 | Marker::I32 | Marker::F32 => return Ok(Some((5, 0))),
+        Marker::U64 | Marker::I64 | Marker::F64 => return Ok(Some((9, 0))),
+        Marker::FixStr(len) => return Ok(Some((1 + len as usize, 0))),
+        Marker::FixArray(len) => return Ok(Some((1, len as u64))),
+        Marker::FixMap(len) => return Ok(Some((1, 2 * len as u64))),
+        Marker::FixExt1 => return Ok(Some((3, 0))),
+        Marker::FixExt2 => return Ok(Some((4, 0))),
+        Marker::FixExt4 => return Ok(Some((6, 0))),
+        Marker::FixExt8 => return Ok(Some((10, 0))),
+        Marker::FixExt16 => return Ok(Some((18, 0))),
+        Marker::Str8 | Marker::Bin8 => (2, 1),
+        Marker::Str16 | Marker::Bin16 => (3, 2),
+        Marker::Str32 | Marker::Bin32 => (5, 4),
+        Marker::Ext8 => (3, 1),
+        Marker::Ext16 => (4, 2),
+        Marker::Ext32 => (6, 4),
+        Marker::Array16 => return Ok(len(2).map(|len| (3, len))),
+        Marker::Array32 => return Ok(len(4).map(|len| (5, len))),
+        Marker::Map16 => return Ok(len(2).map(|len| (3, 2 * len))),
+        Marker::Map32 => return Ok(len(4).map(|len| (5, 2 * len))),
+        Marker::Reserved => return Err(Error::TypeMismatch(Marker::Reserved)),
+    };
+
+    // Strings, binaries and ext values, whose header is followed by the given number of bytes.
+    Ok(len(n).map(|len| (header + len as usize, 0)))
+}
+
+/// Future decoding a single value from an asynchronous reader, see `from_async_read`.
+#[cfg(feature = "tokio")]
+pub struct FromAsyncRead<'a, R: 'a, T> {
+    rd: &'a mut R,
+    buf: Vec<u8>,
+    scanner: Scanner,
+    _marker: PhantomData<T>,
+}
+
+#[cfg(feature = "tokio")]
+impl<'a, R, T> Future for FromAsyncRead<'a, R, T>
+    where R: AsyncRead,
+          T: serde::Deserialize
+{
+    type Item = T;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<T, Error> {
+        loop {
+            if let Some(len) = try!(self.scanner.scan(&self.buf)) {
+                let mut de = Deserializer::new(&self.buf[..len]);
+                let value = try!(serde::Deserialize::deserialize(&mut de));
+                return Ok(Async::Ready(value));
+            }
+
+            let mut chunk = [0; 4096];
+            match self.rd.read(&mut chunk) {
+                Ok(0) if self.buf.is_empty() => return Err(Error::InvalidMarkerRead(ReadError::UnexpectedEOF)),
+                Ok(0) => return Err(Error::InvalidDataRead(ReadError::UnexpectedEOF)),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(Async::NotReady),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(Error::InvalidDataRead(ReadError::Io(err))),
+            }
+        }
+    }
+}
+
+/// Asynchronously reads a complete value from the given reader and deserializes it.
+///
+/// Incoming bytes are buffered until the whole value is available, so it can arrive in any
+/// number of chunks. The reader is read ahead in chunks, so any bytes following the value are
+/// consumed and discarded; use `StreamDecoder` to decode several values from the same stream.
+#[cfg(feature = "tokio")]
+pub fn from_async_read<'a, R, T>(rd: &'a mut R) -> FromAsyncRead<'a, R, T>
+    where R: AsyncRead,
+          T: serde::Deserialize
+{
+    FromAsyncRead {
+        rd: rd,
+        buf: Vec::new(),
+        scanner: Scanner::new(),
+        _marker: PhantomData,
+    }
+}
+
+/// Deserializes an instance of type `T` from the start of the given bytes.
+///
+/// Any bytes following the value are ignored, see `from_slice_exact` to reject them.
+pub fn from_slice<T>(bytes: &[u8]) -> Result<T>
+    where T: serde::Deserialize
+{
+    serde::Deserialize::deserialize(&mut Deserializer::new(bytes))
+}
+
+/// Deserializes an instance of type `T` from the given bytes, which must hold exactly one value.
+///
+/// # Errors
+///
+/// Returns `TrailingBytes` if any bytes are left after the value, which usually means that the
+/// input is corrupted or was not meant to be decoded as `T`.
+pub fn from_slice_exact<T>(bytes: &[u8]) -> Result<T>
+    where T: serde::Deserialize
+{
+    let mut de = Deserializer::new(Cursor::new(bytes));
+    let value = try!(serde::Deserialize::deserialize(&mut de));
+
+    let pos = de.get_ref().position() as usize;
+    if pos < bytes.len() {
+        return Err(Error::TrailingBytes(bytes.len() - pos));
+    }
+
+    Ok(value)
+}
+
+/// Maximum frame length accepted by `read_framed`, in bytes.
+pub const DEFAULT_MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+/// Reads a frame prefixed with its length as a 4-byte big-endian integer, as written by
+/// `encode::write_framed`, and deserializes its contents.
+///
+/// Frames longer than `DEFAULT_MAX_FRAME_LEN` are rejected, see `read_framed_with_max`.
+pub fn read_framed<R, T>(rd: &mut R) -> Result<T>
+    where R: Read,
+          T: serde::Deserialize
+{
+    read_framed_with_max(rd, DEFAULT_MAX_FRAME_LEN)
+}
+
+/// Reads a length-prefixed frame like `read_framed`, rejecting frames longer than `max_len` bytes.
+///
+/// # Errors
+///
+/// Returns `SizeLimitExceeded` without reading the frame contents if the length exceeds
+/// `max_len`. If the reader ends early, `InvalidMarkerRead` is returned for a truncated length
+/// prefix and `InvalidDataRead` for truncated contents.
+pub fn read_framed_with_max<R, T>(rd: &mut R, max_len: u32) -> Result<T>
+    where R: Read,
+          T: serde::Deserialize
+{
+    let mut prefix = [0; 4];
+    try!(read_exact(rd, &mut prefix).map_err(Error::InvalidMarkerRead));
+
+    let len = (prefix[0] as u32) << 24 | (prefix[1] as u32) << 16 | (prefix[2] as u32) << 8 | prefix[3] as u32;
+    if len > max_len {
+        return Err(Error::SizeLimitExceeded);
+    }
+
+    let mut buf = vec![0; len as usize];
+    try!(read_exact(rd, &mut buf).map_err(Error::InvalidDataRead));
+
+    serde::Deserialize::deserialize(&mut Deserializer::new(&buf[..]))
+}
+
+fn read_exact<R: Read>(rd: &mut R, buf: &mut [u8]) -> result::Result<(), ReadError> {
+    rd.read_exact(buf).map_err(|err| {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => ReadError::UnexpectedEOF,
+            _ => ReadError::Io(err),
+        }
+    })
+}
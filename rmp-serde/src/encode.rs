@@ -1,7 +1,11 @@
 use serde;
 
+use std::cmp;
 use std::fmt;
-use std::io::Write;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "bytes")]
+use bytes::BufMut;
 
 use rmp::Marker;
 use rmp::encode::{
@@ -12,6 +16,7 @@ use rmp::encode::{
     write_f32,
     write_f64,
     write_str,
+    write_str_len,
     write_array_len,
     write_map_len,
     write_bin_len,
@@ -31,6 +36,16 @@ pub enum Error {
 
     /// Depth limit exceeded
     DepthLimitExceeded,
+
+    /// Failed to read the binary payload being written, or it ended before the declared length.
+    InvalidDataRead(io::Error),
+
+    /// The encoded value is larger than the limit.
+    SizeLimitExceeded,
+
+    /// Failed to serialize string, binary, struct, sequence or map, because its length doesn't fit
+    /// into the 32 bits MessagePack allows.
+    LengthOverflow(usize),
     Custom(String)
 }
 
@@ -41,6 +56,9 @@ impl ::std::error::Error for Error {
             Error::InvalidValueWrite(..) => "invalid value write",
             Error::UnknownLength => "attempt to serialize struct, sequence or map with unknown length",
             Error::DepthLimitExceeded => "depth limit exceeded",
+            Error::InvalidDataRead(..) => "invalid binary data read",
+            Error::SizeLimitExceeded => "size limit exceeded",
+            Error::LengthOverflow(..) => "length exceeds the MessagePack limit of 2^32 - 1",
             Error::Custom(_) => "custom message",
         }
     }
@@ -51,6 +69,9 @@ impl ::std::error::Error for Error {
             Error::InvalidValueWrite(ref err) => Some(err),
             Error::UnknownLength => None,
             Error::DepthLimitExceeded => None,
+            Error::InvalidDataRead(ref err) => Some(err),
+            Error::SizeLimitExceeded => None,
+            Error::LengthOverflow(..) => None,
             Error::Custom(_) => None,
         }
     }
@@ -131,6 +152,52 @@ impl<'a, W: VariantWriter> Serializer<'a, W> {
     pub fn set_max_depth(&mut self, depth: usize) {
         self.depth = depth;
     }
+
+    /// Writes `len` bytes taken from the given reader as a single binary value.
+    ///
+    /// The payload is copied in fixed-size chunks, so it never needs to be held in memory as a
+    /// whole, unlike with `serialize_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDataRead` if the reader fails or ends before `len` bytes, in which case the
+    /// output is left truncated after the binary header.
+    pub fn serialize_bin_from_read<R>(&mut self, rd: &mut R, len: u32) -> Result<(), Error>
+        where R: Read
+    {
+        try!(write_bin_len(&mut self.wr, len));
+
+        let mut buf = [0; 8192];
+        let mut remaining = len as usize;
+
+        while remaining > 0 {
+            let chunk = cmp::min(remaining, buf.len());
+            let n = match rd.read(&mut buf[..chunk]) {
+                Ok(0) => {
+                    let err = io::Error::new(io::ErrorKind::UnexpectedEof, "binary payload ended early");
+                    return Err(Error::InvalidDataRead(err));
+                }
+                Ok(n) => n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::InvalidDataRead(err)),
+            };
+
+            try!(self.wr.write_all(&buf[..n])
+                .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(WriteError(err)))));
+            remaining -= n;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that the given length can be written into a MessagePack length field.
+fn check_len(len: usize) -> Result<u32, Error> {
+    if len > u32::max_value() as usize {
+        return Err(Error::LengthOverflow(len));
+    }
+
+    Ok(len as u32)
 }
 
 macro_rules! depth_count(
@@ -240,7 +307,8 @@ impl<'a, W: VariantWriter> serde::Serializer for Serializer<'a, W> {
     }
 
     fn serialize_str(&mut self, val: &str) -> Result<(), Error> {
-        write_str(&mut self.wr, val).map_err(From::from)
+        try!(write_str_len(&mut self.wr, try!(check_len(val.len()))));
+        self.wr.write_all(val.as_bytes()).map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(WriteError(err))))
     }
 
     fn serialize_unit_variant(&mut self,
@@ -282,21 +350,45 @@ impl<'a, W: VariantWriter> serde::Serializer for Serializer<'a, W> {
         };
 
         // ... and its arguments length.
-        try!(write_array_len(&mut self.wr, len as u32));
+        try!(write_array_len(&mut self.wr, try!(check_len(len))));
 
         while let Some(()) = try!(depth_count!(self.depth, visitor.visit(self))) { }
 
         Ok(())
     }
 
+    /// Encodes struct variants as a tuple of id with a map of fields, like: [id, {field: value...}]
     fn serialize_struct_variant<V>(&mut self,
                                _name: &str,
-                               _variant_index: usize,
+                               variant_index: usize,
                                _variant: &str,
-                               _visitor: V) -> Result<(), Error>
+                               mut visitor: V) -> Result<(), Error>
         where V: serde::ser::MapVisitor,
     {
-        unimplemented!()
+        // Mark that we want to encode a variant type.
+        try!(write_array_len(&mut self.wr, 2));
+
+        // Encode a value position...
+        try!(self.serialize_usize(variant_index));
+
+        let len = match visitor.len() {
+            Some(len) => len,
+            None => return Err(Error::UnknownLength),
+        };
+
+        // ... and its fields.
+        try!(write_map_len(&mut self.wr, try!(check_len(len))));
+
+        while let Some(()) = try!(depth_count!(self.depth, visitor.visit(self))) { }
+
+        Ok(())
+    }
+
+    fn serialize_struct_variant_elt<V>(&mut self, key: &str, value: V) -> Result<(), Error>
+        where V: serde::Serialize,
+    {
+        try!(write_str(&mut self.wr, key));
+        value.serialize(self)
     }
 
     fn serialize_none(&mut self) -> Result<(), Error> {
@@ -309,7 +401,6 @@ impl<'a, W: VariantWriter> serde::Serializer for Serializer<'a, W> {
         depth_count!(self.depth, v.serialize(self))
     }
 
-    // TODO: Check len, overflow is possible.
     fn serialize_seq<V>(&mut self, mut visitor: V) -> Result<(), Error>
         where V: serde::ser::SeqVisitor,
     {
@@ -318,7 +409,7 @@ impl<'a, W: VariantWriter> serde::Serializer for Serializer<'a, W> {
             None => return Err(Error::UnknownLength),
         };
 
-        try!(write_array_len(&mut self.wr, len as u32));
+        try!(write_array_len(&mut self.wr, try!(check_len(len))));
 
         while let Some(()) = try!(depth_count!(self.depth, visitor.visit(self))) { }
 
@@ -339,7 +430,7 @@ impl<'a, W: VariantWriter> serde::Serializer for Serializer<'a, W> {
             None => return Err(Error::UnknownLength),
         };
 
-        try!(write_map_len(&mut self.wr, len as u32));
+        try!(write_map_len(&mut self.wr, try!(check_len(len))));
 
         while let Some(()) = try!(depth_count!(self.depth, visitor.visit(self))) { }
 
@@ -368,7 +459,7 @@ impl<'a, W: VariantWriter> serde::Serializer for Serializer<'a, W> {
             None => return Err(Error::UnknownLength),
         };
 
-        try!(self.vw.write_struct_len(&mut self.wr, len as u32));
+        try!(self.vw.write_struct_len(&mut self.wr, try!(check_len(len))));
 
         while let Some(()) = try!(depth_count!(self.depth, visitor.visit(self))) { }
 
@@ -383,7 +474,84 @@ impl<'a, W: VariantWriter> serde::Serializer for Serializer<'a, W> {
     }
 
     fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), Error> {
-        try!(write_bin_len(&mut self.wr, value.len() as u32));
+        try!(write_bin_len(&mut self.wr, try!(check_len(value.len()))));
         self.wr.write_all(value).map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(WriteError(err))))
     }
 }
+
+/// Serializes the given value directly into a caller-provided buffer.
+///
+/// This allows to reuse the same buffer across multiple calls without extra allocations. The
+/// buffer must have enough remaining capacity to hold the encoded value.
+#[cfg(feature = "bytes")]
+pub fn to_buf<T: ?Sized, B>(buf: &mut B, value: &T) -> Result<(), Error>
+    where T: serde::Serialize,
+          B: BufMut
+{
+    let mut wr = buf.writer();
+    value.serialize(&mut Serializer::new(&mut wr))
+}
+
+/// Serializes the given value and writes it prefixed with its length as a 4-byte big-endian
+/// integer, to be read back with `decode::read_framed`.
+///
+/// The value is encoded into a buffer first, since its length must be known upfront.
+pub fn write_framed<W: ?Sized, T: ?Sized>(wr: &mut W, value: &T) -> Result<(), Error>
+    where W: Write,
+          T: serde::Serialize
+{
+    let mut buf = Vec::new();
+    try!(value.serialize(&mut Serializer::new(&mut buf)));
+
+    if buf.len() > u32::max_value() as usize {
+        return Err(Error::LengthOverflow(buf.len()));
+    }
+
+    let len = buf.len() as u32;
+    let prefix = [(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8];
+
+    wr.write_all(&prefix)
+        .and_then(|()| wr.write_all(&buf))
+        .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(WriteError(err))))
+}
+
+/// Serializes the given value into a vector, failing with `SizeLimitExceeded` as soon as the
+/// encoding grows beyond `max` bytes, so that oversized values are never fully encoded.
+pub fn to_vec_bounded<T: ?Sized>(value: &T, max: usize) -> Result<Vec<u8>, Error>
+    where T: serde::Serialize
+{
+    let mut wr = BoundedWriter {
+        buf: Vec::new(),
+        max: max,
+        exceeded: false,
+    };
+
+    match value.serialize(&mut Serializer::new(&mut wr)) {
+        Ok(()) => Ok(wr.buf),
+        Err(..) if wr.exceeded => Err(Error::SizeLimitExceeded),
+        Err(err) => Err(err),
+    }
+}
+
+/// Rejects writes that would grow the buffer beyond its limit, remembering that it did.
+struct BoundedWriter {
+    buf: Vec<u8>,
+    max: usize,
+    exceeded: bool,
+}
+
+impl Write for BoundedWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() > self.max - self.buf.len() {
+            self.exceeded = true;
+            return Err(io::Error::new(io::ErrorKind::Other, "size limit exceeded"));
+        }
+
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,6 +1,17 @@
 extern crate rmp;
 extern crate serde;
 
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "uuid")]
+extern crate uuid;
+#[cfg(feature = "half")]
+extern crate half;
+#[cfg(feature = "tokio")]
+extern crate futures;
+#[cfg(feature = "tokio")]
+extern crate tokio_io;
+
 pub mod decode;
 pub mod encode;
 pub mod value;
@@ -9,3 +20,4 @@ pub use decode::Deserializer;
 pub use encode::Serializer;
 pub use value::from_value;
 pub use value::to_value;
+pub use value::binary_str as as_binary_str;
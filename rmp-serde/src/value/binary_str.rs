@@ -0,0 +1,31 @@
+//! Binary encoding of strings for use with `serialize_with` and `deserialize_with`.
+//!
+//! Strings are encoded as binaries instead of strings, for peers expecting `bin` for some fields.
+//! They are decoded from either, as long as the content is valid UTF-8. The module is also
+//! available as `rmp_serde::as_binary_str`.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Message {
+//!     topic: String,
+//!     #[serde(serialize_with = "rmp_serde::as_binary_str::serialize",
+//!             deserialize_with = "rmp_serde::as_binary_str::deserialize")]
+//!     payload: String,
+//! }
+//! ```
+
+use std::result;
+
+use serde;
+
+pub fn serialize<S>(s: &String, ser: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    ser.serialize_bytes(s.as_bytes())
+}
+
+pub fn deserialize<D>(d: &mut D) -> result::Result<String, D::Error>
+    where D: serde::Deserializer
+{
+    serde::Deserialize::deserialize(d)
+}
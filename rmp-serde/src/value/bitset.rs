@@ -0,0 +1,100 @@
+//! Bit set encoding for use with `serialize_with` and `deserialize_with`.
+//!
+//! Sets of flags, like the ones generated by `bitflags!`, are encoded either as their integer
+//! bits with `serialize` and `deserialize`, or as an array of the names of the flags they contain
+//! with `serialize_names` and `deserialize_names`.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     #[serde(serialize_with = "rmp_serde::value::bitset::serialize_names",
+//!             deserialize_with = "rmp_serde::value::bitset::deserialize_names")]
+//!     permissions: Permissions,
+//! }
+//! ```
+
+use std::result;
+
+use serde;
+
+/// Conversion of a set of flags from and to its integer bits.
+pub trait Bits: Sized {
+    fn bits(&self) -> u64;
+
+    /// Returns `None` if some of the bits do not correspond to a flag.
+    fn from_bits(bits: u64) -> Option<Self>;
+}
+
+/// Names of the flags of a set, for the array form.
+pub trait NamedBits: Bits {
+    /// Returns the name and bits of each flag.
+    fn names() -> &'static [(&'static str, u64)];
+}
+
+pub fn serialize<T, S>(flags: &T, s: &mut S) -> result::Result<(), S::Error>
+    where T: Bits,
+          S: serde::Serializer
+{
+    s.serialize_u64(flags.bits())
+}
+
+pub fn deserialize<T, D>(d: &mut D) -> result::Result<T, D::Error>
+    where T: Bits,
+          D: serde::Deserializer
+{
+    let bits: u64 = try!(serde::Deserialize::deserialize(d));
+
+    from_bits(bits)
+}
+
+/// Serializes the names of the flags contained in the set, in the order of the name table.
+///
+/// Fails if some of the bits are not covered by the table.
+pub fn serialize_names<T, S>(flags: &T, s: &mut S) -> result::Result<(), S::Error>
+    where T: NamedBits,
+          S: serde::Serializer
+{
+    let bits = flags.bits();
+    let mut left = bits;
+    let mut names = Vec::new();
+
+    for &(name, flag) in T::names() {
+        if flag != 0 && bits & flag == flag {
+            names.push(name);
+            left &= !flag;
+        }
+    }
+
+    if left != 0 {
+        return Err(serde::ser::Error::custom(format!("bits {:#x} have no name", left)));
+    }
+
+    serde::Serialize::serialize(&names, s)
+}
+
+pub fn deserialize_names<T, D>(d: &mut D) -> result::Result<T, D::Error>
+    where T: NamedBits,
+          D: serde::Deserializer
+{
+    let names: Vec<String> = try!(serde::Deserialize::deserialize(d));
+
+    let mut bits = 0;
+    for name in names {
+        match T::names().iter().find(|&&(n, _)| n == name) {
+            Some(&(_, flag)) => bits |= flag,
+            None => return Err(serde::de::Error::invalid_value(&format!("unknown flag `{}`", name))),
+        }
+    }
+
+    from_bits(bits)
+}
+
+fn from_bits<T, E>(bits: u64) -> result::Result<T, E>
+    where T: Bits,
+          E: serde::de::Error
+{
+    match T::from_bits(bits) {
+        Some(flags) => Ok(flags),
+        None => Err(serde::de::Error::invalid_value(&format!("invalid bits {:#x}", bits))),
+    }
+}
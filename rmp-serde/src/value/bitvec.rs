@@ -0,0 +1,70 @@
+//! Packed encoding of boolean vectors for use with `serialize_with` and `deserialize_with`.
+//!
+//! Vectors are encoded as a binary holding their length as a 4-byte big-endian integer, followed
+//! by their elements packed eight per byte, first element in the most significant bit. The
+//! padding bits of the last byte are zero.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Bitmap {
+//!     #[serde(serialize_with = "rmp_serde::value::bitvec::serialize",
+//!             deserialize_with = "rmp_serde::value::bitvec::deserialize")]
+//!     bits: Vec<bool>,
+//! }
+//! ```
+
+use std::result;
+
+use serde;
+use serde::bytes::ByteBuf;
+
+/// Packs the given elements, prefixed with their count.
+///
+/// # Panics
+///
+/// Panics if there are more than `u32::MAX` elements.
+pub fn pack(bits: &[bool]) -> Vec<u8> {
+    assert!(bits.len() <= u32::max_value() as usize, "too many elements to pack");
+
+    let len = bits.len() as u32;
+    let mut buf = vec![(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8];
+    buf.extend(bits.chunks(8).map(|chunk| {
+        chunk.iter().enumerate().fold(0u8, |byte, (idx, &bit)| {
+            if bit { byte | 0x80 >> idx } else { byte }
+        })
+    }));
+
+    buf
+}
+
+/// Unpacks elements packed by `pack`, returning `None` if the data doesn't match the count.
+pub fn unpack(buf: &[u8]) -> Option<Vec<bool>> {
+    if buf.len() < 4 {
+        return None;
+    }
+
+    let len = buf[..4].iter().fold(0usize, |len, &byte| len << 8 | byte as usize);
+    let data = &buf[4..];
+    if data.len() != (len + 7) / 8 {
+        return None;
+    }
+
+    Some((0..len).map(|idx| data[idx / 8] & 0x80 >> (idx % 8) != 0).collect())
+}
+
+pub fn serialize<S>(bits: &Vec<bool>, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    s.serialize_bytes(&pack(bits))
+}
+
+pub fn deserialize<D>(d: &mut D) -> result::Result<Vec<bool>, D::Error>
+    where D: serde::Deserializer
+{
+    let buf: ByteBuf = try!(serde::Deserialize::deserialize(d));
+
+    match unpack(&buf) {
+        Some(bits) => Ok(bits),
+        None => Err(serde::de::Error::invalid_length(buf.len())),
+    }
+}
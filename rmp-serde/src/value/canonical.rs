@@ -0,0 +1,34 @@
+//! Deterministic encoding of values, for hashing and signing.
+
+use rmp::Value;
+use rmp::encode::write_value;
+use rmp::value::{Float, Integer};
+
+/// Encodes the given value so that equal values always produce the same bytes.
+///
+/// The value is rewritten with the rules of the `canonical`, `narrow_ints` and `compress_floats`
+/// serializer options, applied recursively: map entries are sorted by key, non-negative `I64`
+/// integers become `U64` and `F64` floats that survive the round-trip through `f32` exactly
+/// become `F32`. Integers and lengths are then written with the smallest marker that fits.
+pub fn to_canonical_vec(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    // Writing into a vector never fails.
+    write_value(&mut buf, &canonicalize(value)).unwrap();
+    buf
+}
+
+fn canonicalize(value: &Value) -> Value {
+    match *value {
+        Value::Integer(Integer::I64(v)) if v >= 0 => Value::Integer(Integer::U64(v as u64)),
+        Value::Float(Float::F64(v)) if v as f32 as f64 == v => Value::Float(Float::F32(v as f32)),
+        Value::Array(ref vec) => Value::Array(vec.iter().map(canonicalize).collect()),
+        Value::Map(ref vec) => {
+            let mut vec: Vec<(Value, Value)> = vec.iter()
+                .map(|&(ref k, ref v)| (canonicalize(k), canonicalize(v)))
+                .collect();
+            vec.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+            Value::Map(vec)
+        }
+        ref value => value.clone(),
+    }
+}
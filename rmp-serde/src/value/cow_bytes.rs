@@ -0,0 +1,33 @@
+//! Binary encoding of `Cow<[u8]>` for use with `serialize_with` and `deserialize_with`.
+//!
+//! Without it, byte slices are serialized as sequences of integers, like any other slice. Both
+//! borrowed and owned bytes are encoded as a single binary, and are always decoded as owned.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Chunk<'a> {
+//!     #[serde(serialize_with = "rmp_serde::value::cow_bytes::serialize",
+//!             deserialize_with = "rmp_serde::value::cow_bytes::deserialize")]
+//!     data: Cow<'a, [u8]>,
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::result;
+
+use serde;
+use serde::bytes::ByteBuf;
+
+pub fn serialize<'a, S>(bytes: &Cow<'a, [u8]>, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    s.serialize_bytes(bytes)
+}
+
+pub fn deserialize<'a, D>(d: &mut D) -> result::Result<Cow<'a, [u8]>, D::Error>
+    where D: serde::Deserializer
+{
+    let buf: ByteBuf = try!(serde::Deserialize::deserialize(d));
+
+    Ok(Cow::Owned(buf.into()))
+}
@@ -1,10 +1,17 @@
 use serde;
 use rmp::Value;
 use rmp::Marker;
+use rmp::value::{Float, Integer};
+use std::collections::HashMap;
 use std::fmt;
 use std::result;
 use std::vec;
 
+use decode::MissingFieldDeserializer;
+
+use super::encode::type_tag;
+use super::merge::merge;
+
 #[derive(Debug)]
 pub enum Error {
     TypeMismatch(Marker),
@@ -92,27 +99,246 @@ pub type Result<T> = result::Result<T, Error>;
 
 pub struct Deserializer {
     value: Option<Value>,
+    tuple_from_map: bool,
+    float_as_int: bool,
+    number_as_f64: bool,
+    nil_as_empty: bool,
+    enum_from_int: bool,
+    enum_from_index_map: bool,
+    enum_tag: Option<String>,
+    tagged_elements: bool,
+    array_as_bytes: bool,
+    field_aliases: HashMap<String, String>,
+    field_map: HashMap<u32, &'static str>,
+    ext_decoders: HashMap<i8, Box<Fn(&[u8]) -> Result<Value>>>,
 }
 
 impl Deserializer {
     pub fn new(value: Value) -> Deserializer {
         Deserializer {
             value: Some(value),
+            tuple_from_map: false,
+            float_as_int: false,
+            number_as_f64: false,
+            nil_as_empty: false,
+            enum_from_int: false,
+            enum_from_index_map: false,
+            enum_tag: None,
+            tagged_elements: false,
+            array_as_bytes: false,
+            field_aliases: HashMap::new(),
+            field_map: HashMap::new(),
+            ext_decoders: HashMap::new(),
         }
     }
-}
 
-impl serde::Deserializer for Deserializer {
-    type Error = Error;
+    /// Allows tuples to be decoded from maps with positional integer keys `0..N`, as some
+    /// encoders do, in addition to arrays.
+    pub fn set_tuple_from_map(&mut self, enabled: bool) {
+        self.tuple_from_map = enabled;
+    }
 
-    fn deserialize<V>(&mut self, mut visitor: V) -> Result<V::Value>
+    /// Decodes floats into integer fields only when they are whole-valued, like `3.0`.
+    ///
+    /// Floats with a fractional part or out of the 64-bit integer range are rejected instead of
+    /// being truncated.
+    pub fn set_float_as_int(&mut self, enabled: bool) {
+        self.float_as_int = enabled;
+    }
+
+    /// Passes every number on as `f64`, like JSON does, where the type to decode into is not known
+    /// upfront, so that generic consumers see integers and `F32` values the same way as `F64`.
+    ///
+    /// Integer fields are not affected. Integers beyond 2^53 lose precision.
+    pub fn set_number_as_f64(&mut self, enabled: bool) {
+        self.number_as_f64 = enabled;
+    }
+
+    /// Decodes nil as an empty string, sequence or map where one of those is expected, as written
+    /// by the value `Serializer` with `empty_as_nil`.
+    pub fn set_nil_as_empty(&mut self, enabled: bool) {
+        self.nil_as_empty = enabled;
+    }
+
+    /// Decodes unit variants of C-style enums from a bare integer, which is taken as the index of
+    /// the variant.
+    pub fn set_enum_from_int(&mut self, enabled: bool) {
+        self.enum_from_int = enabled;
+    }
+
+    /// Decodes enums from single-entry maps from the index of the variant to its content, like
+    /// `{1: [2, 3]}`, as written by the value `Serializer` with `enum_as_index_map`. Unit variants
+    /// are also accepted from their bare index, as they are written in map keys.
+    pub fn set_enum_from_index_map(&mut self, enabled: bool) {
+        self.enum_from_index_map = enabled;
+    }
+
+    /// Decodes enums from maps carrying the index of the variant under the given key, like
+    /// `{"kind": 2, "w": 3, "h": 4}`. The remaining entries make up the content of the variant,
+    /// either its fields or a newtype map.
+    ///
+    /// Discriminants other than an index in range are rejected.
+    pub fn set_enum_tag(&mut self, tag: &str) {
+        self.enum_tag = Some(tag.to_owned());
+    }
+
+    /// Decodes array elements from `[tag, element]` pairs, as written by the value `Serializer`
+    /// with `tagged_elements`.
+    ///
+    /// Elements whose tag doesn't match their type are rejected.
+    pub fn set_tagged_elements(&mut self, enabled: bool) {
+        self.tagged_elements = enabled;
+    }
+
+    /// Decodes binaries as sequences of their bytes where a sequence is expected, so that byte
+    /// vectors like `Vec<u8>` accept both binaries and the arrays of integers some older peers
+    /// write instead.
+    ///
+    /// Array elements out of range for the element type are rejected as usual.
+    pub fn set_array_as_bytes(&mut self, enabled: bool) {
+        self.array_as_bytes = enabled;
+    }
+
+    /// Renames string keys of every decoded map according to the given `alias => field` table
+    /// before they are matched against field names, like `ts => timestamp`.
+    ///
+    /// A map containing both an alias and the field it stands for, or two aliases of the same
+    /// field, is rejected.
+    pub fn set_field_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.field_aliases = aliases;
+    }
+
+    /// Renames integer keys of decoded structs according to the given `index => field` table, the
+    /// reverse of the one given to the `Serializer`, so that structs can be encoded with small
+    /// integer keys agreed upon upfront.
+    ///
+    /// Structs with integer keys absent from the table are rejected. Other maps are not affected.
+    pub fn set_field_map(&mut self, map: HashMap<u32, &'static str>) {
+        self.field_map = map;
+    }
+
+    /// Registers a decoder transforming the payload of ext values of the given type into a value,
+    /// which is then deserialized in place of the ext, like a UUID into its string form.
+    ///
+    /// Ext values of types without a registered decoder are rejected.
+    pub fn register_ext(&mut self, ty: i8, decoder: Box<Fn(&[u8]) -> Result<Value>>) {
+        self.ext_decoders.insert(ty, decoder);
+    }
+
+    /// Registers a resolver turning references into the values they stand for, which are then
+    /// deserialized in place of the references, like object ids into the objects of a graph.
+    ///
+    /// References are ext values of the given type whose payload is the id, as a big-endian
+    /// unsigned integer of up to 8 bytes. Ids for which the resolver returns `None` are rejected.
+    pub fn set_resolver(&mut self, ty: i8, resolver: Box<Fn(u64) -> Option<Value>>) {
+        self.register_ext(ty, Box::new(move |data: &[u8]| {
+            if data.is_empty() || data.len() > 8 {
+                return Err(serde::de::Error::invalid_length(data.len()));
+            }
+
+            let id = data.iter().fold(0u64, |id, &byte| id << 8 | byte as u64);
+
+            match resolver(id) {
+                Some(value) => Ok(value),
+                None => Err(serde::de::Error::invalid_value(&format!("unresolved reference {}", id))),
+            }
+        }));
+    }
+
+    fn resolve_aliases(&self, map: Vec<(Value, Value)>) -> Result<Vec<(Value, Value)>> {
+        if self.field_aliases.is_empty() {
+            return Ok(map);
+        }
+
+        let mut aliased = Vec::new();
+        let map: Vec<(Value, Value)> = map.into_iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    Value::String(ref key) if self.field_aliases.contains_key(key) => {
+                        let field = self.field_aliases[key].clone();
+                        aliased.push((key.clone(), field.clone()));
+                        Value::String(field)
+                    }
+                    key => key,
+                };
+                (key, value)
+            })
+            .collect();
+
+        for (alias, field) in aliased {
+            let key = Value::String(field);
+            if map.iter().filter(|&&(ref k, _)| *k == key).count() > 1 {
+                return Err(serde::de::Error::invalid_value(
+                    &format!("alias `{}` collides with an existing field {}", alias, key)));
+            }
+        }
+
+        Ok(map)
+    }
+
+    fn resolve_field_indices(&self, map: Vec<(Value, Value)>) -> Result<Vec<(Value, Value)>> {
+        map.into_iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    Value::Integer(Integer::U64(idx)) => {
+                        let field = if idx <= u32::max_value() as u64 {
+                            self.field_map.get(&(idx as u32))
+                        } else {
+                            None
+                        };
+
+                        match field {
+                            Some(field) => Value::String(field.to_string()),
+                            None => {
+                                return Err(serde::de::Error::invalid_value(
+                                    &format!("field index {} is missing from the field map", idx)));
+                            }
+                        }
+                    }
+                    key => key,
+                };
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    fn deserialize_int<V>(&mut self, value: Option<Value>, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match value {
+            Some(Value::Float(v)) if self.float_as_int => {
+                let v = match v {
+                    Float::F32(v) => v as f64,
+                    Float::F64(v) => v,
+                };
+
+                if v.fract() != 0.0 || !(-9223372036854775808.0..18446744073709551616.0).contains(&v) {
+                    return Err(serde::de::Error::invalid_value(&format!("expected whole number, found {}", v)));
+                }
+
+                if v < 0.0 {
+                    visitor.visit_i64(v as i64)
+                } else {
+                    visitor.visit_u64(v as u64)
+                }
+            }
+            value => self.visit_value(value, visitor),
+        }
+    }
+
+    /// Dispatches the given value to the visitor.
+    ///
+    /// Values are passed by move, so the `deserialize_*` hints falling back to the generic
+    /// behavior don't have to store the value back into the deserializer first. Binaries are handed
+    /// over with `visit_byte_buf`, which falls back to `visit_bytes` for visitors borrowing them.
+    fn visit_value<V>(&mut self, value: Option<Value>, mut visitor: V) -> Result<V::Value>
         where V: serde::de::Visitor
     {
         use rmp::Value::*;
         use rmp::value::Integer::*;
         use rmp::value::Float::*;
 
-        let value = match self.value.take() {
+        let value = match value {
             Some(value) => value,
             None => return Err(serde::de::Error::end_of_stream()),
         };
@@ -126,21 +352,130 @@ impl serde::Deserializer for Deserializer {
             Float(F32(v)) => visitor.visit_f32(v),
             Float(F64(v)) => visitor.visit_f64(v),
             Binary(v) => visitor.visit_byte_buf(v),
-            Array(v) => visitor.visit_seq(SeqVisitor {
-                de: self,
-                len: v.len(),
-                actual: v.len(),
-                iter: v.into_iter(),
-            }),
-            Map(v) => visitor.visit_map(MapVisitor {
-                de: self,
-                len: v.len(),
-                actual: v.len(),
-                iter: v.into_iter(),
-                value: None,
-            }),
-            Ext(_, _) => unimplemented!(),
+            Array(v) => {
+                let v = if self.tagged_elements { try!(untag_elements(v)) } else { v };
+                visitor.visit_seq(SeqVisitor {
+                    de: self,
+                    len: v.len(),
+                    actual: v.len(),
+                    iter: v.into_iter(),
+                })
+            }
+            Map(v) => {
+                let v = try!(self.resolve_aliases(v));
+                visitor.visit_map(MapVisitor {
+                    de: self,
+                    len: v.len(),
+                    actual: v.len(),
+                    iter: v.into_iter(),
+                    value: None,
+                })
+            }
+            Ext(ty, data) => {
+                let value = match self.ext_decoders.get(&ty) {
+                    Some(decoder) => try!(decoder(&data)),
+                    None => {
+                        return Err(serde::de::Error::invalid_value(
+                            &format!("no decoder registered for ext of type {}", ty)));
+                    }
+                };
+
+                self.visit_value(Some(value), visitor)
+            }
+        }
+    }
+}
+
+/// Unwraps elements from their `[tag, element]` pairs, checking the tags.
+fn untag_elements(vec: Vec<Value>) -> Result<Vec<Value>> {
+    vec.into_iter()
+        .map(|pair| {
+            let mut pair = match pair {
+                Value::Array(pair) if pair.len() == 2 => pair,
+                pair => {
+                    return Err(serde::de::Error::invalid_value(
+                        &format!("expected a [tag, element] pair, found {}", unexpected(&pair))));
+                }
+            };
+
+            let value = pair.pop().unwrap();
+            match pair.pop().unwrap() {
+                Value::Integer(Integer::U64(tag)) if tag == type_tag(&value) as u64 => Ok(value),
+                tag => {
+                    Err(serde::de::Error::invalid_value(
+                        &format!("tag {} does not match {}", tag, unexpected(&value))))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Describes the given value for error messages, like `string "yes"`.
+fn unexpected(value: &Value) -> String {
+    match *value {
+        Value::Nil => "nil".to_string(),
+        Value::Boolean(..) => format!("boolean {}", value),
+        Value::Integer(..) => format!("integer {}", value),
+        Value::Float(..) => format!("float {}", value),
+        Value::String(..) => format!("string {}", value),
+        Value::Binary(..) => "binary".to_string(),
+        Value::Array(..) => "array".to_string(),
+        Value::Map(..) => "map".to_string(),
+        Value::Ext(ty, ..) => format!("ext of type {}", ty),
+    }
+}
+
+/// Orders the values of a map with integer keys `0..len` by their keys.
+///
+/// Every index must be present exactly once.
+fn map_to_tuple(map: Vec<(Value, Value)>, len: usize) -> Result<Vec<Value>> {
+    let mut values: Vec<Option<Value>> = (0..len).map(|_| None).collect();
+
+    for (key, value) in map {
+        let idx = match key {
+            Value::Integer(Integer::U64(idx)) if idx < len as u64 => idx as usize,
+            key => return Err(serde::de::Error::invalid_value(&format!("unexpected tuple index {}", key))),
+        };
+
+        if values[idx].is_some() {
+            return Err(serde::de::Error::invalid_value(&format!("duplicate tuple index {}", idx)));
+        }
+
+        values[idx] = Some(value);
+    }
+
+    values.into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            value.ok_or_else(|| serde::de::Error::invalid_value(&format!("missing tuple index {}", idx)))
+        })
+        .collect()
+}
+
+impl serde::Deserializer for Deserializer {
+    type Error = Error;
+
+    /// Numbers are widened to `f64` if `number_as_f64` is set. Since `f32` and `f64` fields end up
+    /// here as well, they accept every number either way.
+    fn deserialize<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        let value = self.value.take();
+
+        if self.number_as_f64 {
+            let v = match value {
+                Some(Value::Integer(Integer::I64(v))) => Some(v as f64),
+                Some(Value::Integer(Integer::U64(v))) => Some(v as f64),
+                Some(Value::Float(Float::F32(v))) => Some(v as f64),
+                _ => None,
+            };
+
+            if let Some(v) = v {
+                return visitor.visit_f64(v);
+            }
         }
+
+        self.visit_value(value, visitor)
     }
 
     #[inline]
@@ -153,6 +488,360 @@ impl serde::Deserializer for Deserializer {
             None => Err(serde::de::Error::end_of_stream()),
         }
     }
+
+    /// Unsigned integers are accepted as long as they fit into `i64`.
+    fn deserialize_i64<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Integer(Integer::I64(v))) => visitor.visit_i64(v),
+            Some(Value::Integer(Integer::U64(v))) => {
+                if v > i64::max_value() as u64 {
+                    return Err(serde::de::Error::invalid_value(&format!("integer {} is out of range for i64", v)));
+                }
+
+                visitor.visit_i64(v as i64)
+            }
+            value => self.deserialize_int(value, visitor),
+        }
+    }
+
+    /// Signed integers are accepted as long as they are not negative.
+    fn deserialize_u64<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Integer(Integer::U64(v))) => visitor.visit_u64(v),
+            Some(Value::Integer(Integer::I64(v))) => {
+                if v < 0 {
+                    return Err(serde::de::Error::invalid_value(&format!("integer {} is out of range for u64", v)));
+                }
+
+                visitor.visit_u64(v as u64)
+            }
+            value => self.deserialize_int(value, visitor),
+        }
+    }
+
+    fn deserialize_bool<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Boolean(v)) => visitor.visit_bool(v),
+            Some(value) => {
+                Err(Error::Uncategorized(format!("invalid type: expected bool, found {}", unexpected(&value))))
+            }
+            None => Err(serde::de::Error::end_of_stream()),
+        }
+    }
+
+    /// Unit is accepted both from nil and from an empty array, as some peers encode it that way.
+    ///
+    /// Unit structs are decoded the same way, because `deserialize_unit_struct` forwards here.
+    fn deserialize_unit<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Nil) => visitor.visit_unit(),
+            Some(Value::Array(ref v)) if v.is_empty() => visitor.visit_unit(),
+            value => self.visit_value(value, visitor),
+        }
+    }
+
+    /// Maps are also accepted as a sequence of key-value pairs, like into `Vec<(K, V)>`, and
+    /// binaries as a sequence of bytes if `array_as_bytes` is set.
+    fn deserialize_seq<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Map(v)) => {
+                let v = v.into_iter().map(|(k, v)| Value::Array(vec![k, v])).collect();
+                visitor.visit_seq(SeqVisitor::new(self, v))
+            }
+            Some(Value::Binary(v)) if self.array_as_bytes => {
+                let v = v.into_iter().map(|b| Value::Integer(Integer::U64(b as u64))).collect();
+                visitor.visit_seq(SeqVisitor::new(self, v))
+            }
+            Some(Value::Nil) if self.nil_as_empty => visitor.visit_seq(SeqVisitor::new(self, Vec::new())),
+            value => self.visit_value(value, visitor),
+        }
+    }
+
+    fn deserialize_map<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Nil) if self.nil_as_empty => {
+                visitor.visit_map(MapVisitor {
+                    de: self,
+                    iter: Vec::new().into_iter(),
+                    value: None,
+                    len: 0,
+                    actual: 0,
+                })
+            }
+            value => self.visit_value(value, visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(&mut self, _name: &'static str, _fields: &'static [&'static str], visitor: V)
+        -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Map(map)) if !self.field_map.is_empty() => {
+                let map = try!(self.resolve_field_indices(map));
+                self.visit_value(Some(Value::Map(map)), visitor)
+            }
+            value => {
+                self.value = value;
+                self.deserialize_map(visitor)
+            }
+        }
+    }
+
+    /// Binaries are also accepted if they contain valid UTF-8, as some peers encode text that way.
+    fn deserialize_str<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Nil) if self.nil_as_empty => visitor.visit_str(""),
+            Some(Value::Binary(v)) => {
+                match String::from_utf8(v) {
+                    Ok(v) => visitor.visit_string(v),
+                    Err(err) => {
+                        Err(serde::de::Error::invalid_value(
+                            &format!("invalid UTF-8 in binary at byte {}", err.utf8_error().valid_up_to())))
+                    }
+                }
+            }
+            value => self.visit_value(value, visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(&mut self, len: usize, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Map(v)) if self.tuple_from_map => {
+                let v = try!(map_to_tuple(v, len));
+                visitor.visit_seq(SeqVisitor::new(self, v))
+            }
+            value => self.visit_value(value, visitor),
+        }
+    }
+
+    /// Newtype structs are handed over to `visit_newtype_struct` with the wrapped value, rather
+    /// than going through a single-element sequence. They are accepted both from a bare value and
+    /// from a single-element array, the way the `Serializer` writes them.
+    fn deserialize_newtype_struct<V>(&mut self, name: &'static str, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(Value::Array(mut v)) => {
+                if v.len() == 1 {
+                    self.value = v.pop();
+                    visitor.visit_newtype_struct(self)
+                } else {
+                    self.value = Some(Value::Array(v));
+                    self.deserialize_tuple_struct(name, 1, visitor)
+                }
+            }
+            Some(Value::Map(v)) if self.tuple_from_map => {
+                self.value = Some(Value::Map(v));
+                self.deserialize_tuple_struct(name, 1, visitor)
+            }
+            Some(value) => {
+                self.value = Some(value);
+                visitor.visit_newtype_struct(self)
+            }
+            None => Err(serde::de::Error::end_of_stream()),
+        }
+    }
+
+    /// Single-field tuple structs are also accepted from a bare value, not wrapped into an array.
+    fn deserialize_tuple_struct<V>(&mut self, _name: &'static str, len: usize, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        if len == 1 {
+            match self.value.take() {
+                Some(Value::Map(v)) if self.tuple_from_map => {
+                    self.value = Some(Value::Map(v));
+                }
+                Some(Value::Array(v)) => {
+                    self.value = Some(Value::Array(v));
+                }
+                Some(value) => {
+                    return visitor.visit_seq(SeqVisitor::new(self, vec![value]));
+                }
+                None => return Err(serde::de::Error::end_of_stream()),
+            }
+        }
+
+        self.deserialize_tuple(len, visitor)
+    }
+
+    /// Unit variants are accepted from their variant name, the way enum map keys are encoded, and
+    /// from their index if `enum_from_int` or `enum_from_index_map` is set. Any variant is
+    /// accepted from a map from its index to its content if `enum_from_index_map` is set, and from
+    /// a map carrying its index under the key set with `set_enum_tag`.
+    fn deserialize_enum<V>(&mut self, name: &str, variants: &'static [&'static str], mut visitor: V)
+        -> Result<V::Value>
+        where V: serde::de::EnumVisitor
+    {
+        match self.value.take() {
+            Some(Value::String(variant)) => visitor.visit(VariantVisitor { variant: Some(Value::String(variant)) }),
+            Some(Value::Integer(idx)) if self.enum_from_int || self.enum_from_index_map => {
+                let idx = match idx {
+                    Integer::U64(idx) if idx < variants.len() as u64 => idx,
+                    idx => {
+                        return Err(serde::de::Error::invalid_value(
+                            &format!("variant index {} is out of range for enum {} with {} variants",
+                                     Value::Integer(idx), name, variants.len())));
+                    }
+                };
+
+                visitor.visit(VariantVisitor { variant: Some(Value::Integer(Integer::U64(idx))) })
+            }
+            Some(Value::Map(mut map)) if self.enum_from_index_map && map.len() == 1 => {
+                let (idx, content) = map.pop().unwrap();
+                let idx = match idx {
+                    Value::Integer(Integer::U64(idx)) if idx < variants.len() as u64 => idx,
+                    idx => {
+                        return Err(serde::de::Error::invalid_value(
+                            &format!("variant index {} is out of range for enum {} with {} variants",
+                                     idx, name, variants.len())));
+                    }
+                };
+
+                visitor.visit(TaggedVariantVisitor {
+                    de: self,
+                    idx: idx as usize,
+                    content: Some(content),
+                })
+            }
+            Some(Value::Map(mut map)) if self.enum_tag.is_some() => {
+                let pos = {
+                    let tag = self.enum_tag.as_ref().unwrap();
+                    map.iter().position(|&(ref key, _)| match *key {
+                        Value::String(ref key) => key == tag,
+                        _ => false,
+                    })
+                };
+
+                let pos = match pos {
+                    Some(pos) => pos,
+                    None => {
+                        let msg = format!("missing discriminant field `{}` for enum {}",
+                                          self.enum_tag.as_ref().unwrap(), name);
+                        self.value = Some(Value::Map(map));
+                        return Err(serde::de::Error::invalid_value(&msg));
+                    }
+                };
+
+                let idx = match map.remove(pos).1 {
+                    Value::Integer(Integer::U64(idx)) if idx < variants.len() as u64 => idx,
+                    idx => {
+                        return Err(serde::de::Error::invalid_value(
+                            &format!("discriminant {} is out of range for enum {} with {} variants",
+                                     idx, name, variants.len())));
+                    }
+                };
+
+                visitor.visit(TaggedVariantVisitor {
+                    de: self,
+                    idx: idx as usize,
+                    content: Some(Value::Map(map)),
+                })
+            }
+            value => {
+                self.value = value;
+                Err(serde::de::Error::invalid_type(serde::de::Type::Enum))
+            }
+        }
+    }
+}
+
+/// Yields a unit variant from its name or index.
+struct VariantVisitor {
+    variant: Option<Value>,
+}
+
+/// Hands a variant index to the visitor as `usize`, the way variant identifiers expect it.
+struct IndexDeserializer(usize);
+
+impl serde::Deserializer for IndexDeserializer {
+    type Error = Error;
+
+    fn deserialize<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        visitor.visit_usize(self.0)
+    }
+}
+
+impl serde::de::VariantVisitor for VariantVisitor {
+    type Error = Error;
+
+    fn visit_variant<V>(&mut self) -> Result<V>
+        where V: serde::Deserialize
+    {
+        let variant = match self.variant.take() {
+            Some(variant) => variant,
+            None => return Err(serde::de::Error::end_of_stream()),
+        };
+
+        match variant {
+            Value::Integer(Integer::U64(idx)) => serde::Deserialize::deserialize(&mut IndexDeserializer(idx as usize)),
+            variant => serde::Deserialize::deserialize(&mut Deserializer::new(variant)),
+        }
+    }
+
+    fn visit_unit(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Yields a variant from its index, along with its content taken out of the map holding both.
+struct TaggedVariantVisitor<'a> {
+    de: &'a mut Deserializer,
+    idx: usize,
+    content: Option<Value>,
+}
+
+impl<'a> serde::de::VariantVisitor for TaggedVariantVisitor<'a> {
+    type Error = Error;
+
+    fn visit_variant<V>(&mut self) -> Result<V>
+        where V: serde::Deserialize
+    {
+        serde::Deserialize::deserialize(&mut IndexDeserializer(self.idx))
+    }
+
+    fn visit_unit(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn visit_newtype<T>(&mut self) -> Result<T>
+        where T: serde::Deserialize
+    {
+        self.de.value = self.content.take();
+        serde::Deserialize::deserialize(self.de)
+    }
+
+    fn visit_tuple<V>(&mut self, len: usize, visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        self.de.value = self.content.take();
+        serde::Deserializer::deserialize_tuple(self.de, len, visitor)
+    }
+
+    fn visit_struct<V>(&mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        self.de.value = self.content.take();
+        serde::Deserializer::deserialize_struct(self.de, "", fields, visitor)
+    }
 }
 
 struct SeqVisitor<'a> {
@@ -162,6 +851,17 @@ struct SeqVisitor<'a> {
     actual: usize,
 }
 
+impl<'a> SeqVisitor<'a> {
+    fn new(de: &'a mut Deserializer, values: Vec<Value>) -> SeqVisitor<'a> {
+        SeqVisitor {
+            de: de,
+            len: values.len(),
+            actual: values.len(),
+            iter: values.into_iter(),
+        }
+    }
+}
+
 impl <'a> serde::de::SeqVisitor for SeqVisitor<'a> {
     type Error = Error;
 
@@ -224,6 +924,13 @@ impl <'a> serde::de::MapVisitor for MapVisitor<'a> {
         Ok(try!(serde::Deserialize::deserialize(self.de)))
     }
 
+    /// Decodes omitted `Option` fields as `None`.
+    fn missing_field<T>(&mut self, field: &'static str) -> Result<T>
+        where T: serde::Deserialize
+    {
+        serde::Deserialize::deserialize(&mut MissingFieldDeserializer::new(field))
+    }
+
     fn end(&mut self) -> Result<()> {
         if self.len == 0 {
             Ok(())
@@ -237,7 +944,128 @@ impl <'a> serde::de::MapVisitor for MapVisitor<'a> {
     }
 }
 
+/// Allows values to be used wherever serde expects something convertible into a deserializer,
+/// producing a `Deserializer` with the default configuration.
+impl serde::de::value::ValueDeserializer<Error> for Value {
+    type Deserializer = Deserializer;
+
+    fn into_deserializer(self) -> Deserializer {
+        Deserializer::new(self)
+    }
+}
+
 pub fn from_value<T>(value: Value) -> Result<T>
     where T: serde::Deserialize {
     serde::Deserialize::deserialize(&mut Deserializer::new(value))
 }
+
+/// Deserializes an instance of type `T` from `user` merged over `defaults` with `merge`, so that
+/// fields missing from `user` are taken from `defaults`.
+pub fn from_value_with_defaults<T>(defaults: Value, user: Value) -> Result<T>
+    where T: serde::Deserialize
+{
+    from_value(merge(defaults, user))
+}
+
+/// Deserializes an instance of type `T` from an array after dropping its first `n` elements,
+/// like the version of a `[version, payload]` frame.
+///
+/// A single remaining element is deserialized on its own, otherwise the remaining elements are
+/// deserialized as an array.
+pub fn from_value_skipping_prefix<T>(value: Value, n: usize) -> Result<T>
+    where T: serde::Deserialize
+{
+    let mut vec = match value {
+        Value::Array(vec) => vec,
+        _ => return Err(Error::TypeMismatch(Marker::Array32)),
+    };
+
+    if vec.len() < n {
+        return Err(Error::LengthMismatch(vec.len() as u32));
+    }
+
+    let mut rest = vec.split_off(n);
+    let value = if rest.len() == 1 {
+        rest.pop().unwrap()
+    } else {
+        Value::Array(rest)
+    };
+
+    from_value(value)
+}
+
+/// Deserializes an instance of type `T` from the leading elements of an array, returning the
+/// elements it did not consume, if any, as an array.
+///
+/// Types reading a fixed number of elements, like tuples, leave the rest of the array alone
+/// instead of failing with `LengthMismatch`. Values other than arrays are deserialized as a whole.
+pub fn from_value_partial<T>(value: Value) -> Result<(T, Option<Value>)>
+    where T: serde::Deserialize
+{
+    let vec = match value {
+        Value::Array(vec) => vec,
+        value => return from_value(value).map(|value| (value, None)),
+    };
+
+    let mut iter = vec.into_iter();
+    let value = try!(serde::Deserialize::deserialize(&mut PrefixDeserializer {
+        de: Deserializer::new(Value::Nil),
+        iter: &mut iter,
+    }));
+
+    let rest: Vec<Value> = iter.collect();
+    if rest.is_empty() {
+        Ok((value, None))
+    } else {
+        Ok((value, Some(Value::Array(rest))))
+    }
+}
+
+/// Deserializes a sequence from the leading elements of an array.
+struct PrefixDeserializer<'a> {
+    de: Deserializer,
+    iter: &'a mut vec::IntoIter<Value>,
+}
+
+impl<'a> serde::Deserializer for PrefixDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize<V>(&mut self, mut visitor: V) -> Result<V::Value>
+        where V: serde::de::Visitor
+    {
+        visitor.visit_seq(PrefixSeqVisitor {
+            de: &mut self.de,
+            iter: &mut *self.iter,
+        })
+    }
+}
+
+struct PrefixSeqVisitor<'a> {
+    de: &'a mut Deserializer,
+    iter: &'a mut vec::IntoIter<Value>,
+}
+
+impl<'a> serde::de::SeqVisitor for PrefixSeqVisitor<'a> {
+    type Error = Error;
+
+    fn visit<T>(&mut self) -> Result<Option<T>>
+        where T: serde::Deserialize,
+    {
+        match self.iter.next() {
+            Some(value) => {
+                self.de.value = Some(value);
+                Ok(Some(try!(serde::Deserialize::deserialize(self.de))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Unconsumed elements are left for the caller.
+    fn end(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.iter.len(), Some(self.iter.len()))
+    }
+}
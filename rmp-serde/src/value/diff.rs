@@ -0,0 +1,137 @@
+//! Comparison of values for debugging round-trip mismatches.
+
+use rmp::Value;
+use rmp::value::{Float, Integer};
+
+/// Returns `None` if both values are equal, otherwise a description of the first difference
+/// found in depth-first order, like `.config.timeout: 5 != 7`.
+///
+/// Map entries are matched by key regardless of their order. String keys are shown as `.key`,
+/// other keys and array indices as `[key]`.
+pub fn diff(lhs: &Value, rhs: &Value) -> Option<String> {
+    diff_at(&mut String::new(), lhs, rhs)
+}
+
+/// Compares values semantically, unlike the derived `PartialEq`.
+///
+/// Integers are equal if their numeric values are, regardless of the representation, so
+/// `I64(5)` equals `U64(5)`. Floats of different widths are compared by value only if
+/// `mixed_floats` is set, otherwise `F32` and `F64` never compare equal. Map entries are matched
+/// one-to-one by key regardless of their order.
+///
+/// Floats are compared numerically with `==`, which differs from `Value` equality, where floats
+/// are equal only if their bits are: here NaN never equals itself and `0.0` equals `-0.0`.
+pub fn value_eq(lhs: &Value, rhs: &Value, mixed_floats: bool) -> bool {
+    match (lhs, rhs) {
+        (&Value::Integer(lhs), &Value::Integer(rhs)) => {
+            match (lhs, rhs) {
+                (Integer::I64(lhs), Integer::U64(rhs)) |
+                (Integer::U64(rhs), Integer::I64(lhs)) => lhs >= 0 && lhs as u64 == rhs,
+                (lhs, rhs) => lhs == rhs,
+            }
+        }
+        (&Value::Float(lhs), &Value::Float(rhs)) => {
+            match (lhs, rhs) {
+                (Float::F32(lhs), Float::F64(rhs)) |
+                (Float::F64(rhs), Float::F32(lhs)) => mixed_floats && lhs as f64 == rhs,
+                (Float::F32(lhs), Float::F32(rhs)) => lhs == rhs,
+                (Float::F64(lhs), Float::F64(rhs)) => lhs == rhs,
+            }
+        }
+        (&Value::Array(ref lhs), &Value::Array(ref rhs)) => {
+            lhs.len() == rhs.len() &&
+                lhs.iter().zip(rhs.iter()).all(|(lhs, rhs)| value_eq(lhs, rhs, mixed_floats))
+        }
+        (&Value::Map(ref lhs), &Value::Map(ref rhs)) => {
+            if lhs.len() != rhs.len() {
+                return false;
+            }
+
+            // Every entry must be matched by a distinct one, so duplicate keys are counted.
+            let mut used = vec![false; rhs.len()];
+            lhs.iter().all(|&(ref key, ref lhs)| {
+                let pos = rhs.iter().enumerate().position(|(idx, &(ref k, ref rhs))| {
+                    !used[idx] && value_eq(key, k, mixed_floats) && value_eq(lhs, rhs, mixed_floats)
+                });
+
+                match pos {
+                    Some(idx) => {
+                        used[idx] = true;
+                        true
+                    }
+                    None => false,
+                }
+            })
+        }
+        (lhs, rhs) => lhs == rhs,
+    }
+}
+
+fn diff_at(path: &mut String, lhs: &Value, rhs: &Value) -> Option<String> {
+    match (lhs, rhs) {
+        (&Value::Array(ref lhs), &Value::Array(ref rhs)) => {
+            for (idx, (lhs, rhs)) in lhs.iter().zip(rhs.iter()).enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", idx));
+                if let Some(diff) = diff_at(path, lhs, rhs) {
+                    return Some(diff);
+                }
+                path.truncate(len);
+            }
+
+            if lhs.len() != rhs.len() {
+                return Some(format!("{}: array length {} != {}", root(path), lhs.len(), rhs.len()));
+            }
+
+            None
+        }
+        (&Value::Map(ref lhs), &Value::Map(ref rhs)) => {
+            for &(ref key, ref lhs) in lhs {
+                let len = path.len();
+                push_key(path, key);
+                let diff = match find(rhs, key) {
+                    Some(rhs) => diff_at(path, lhs, rhs),
+                    None => Some(format!("{}: {} != <missing>", path, lhs)),
+                };
+                if diff.is_some() {
+                    return diff;
+                }
+                path.truncate(len);
+            }
+
+            for &(ref key, ref rhs) in rhs {
+                if find(lhs, key).is_none() {
+                    push_key(path, key);
+                    return Some(format!("{}: <missing> != {}", path, rhs));
+                }
+            }
+
+            None
+        }
+        (lhs, rhs) if lhs == rhs => None,
+        (lhs, rhs) => {
+            let (lhs_fmt, rhs_fmt) = (format!("{}", lhs), format!("{}", rhs));
+            if lhs_fmt == rhs_fmt {
+                // Same display, but different representations, like `I64(5)` and `U64(5)`.
+                Some(format!("{}: {:?} != {:?}", root(path), lhs, rhs))
+            } else {
+                Some(format!("{}: {} != {}", root(path), lhs_fmt, rhs_fmt))
+            }
+        }
+    }
+}
+
+fn root(path: &str) -> &str {
+    if path.is_empty() { "." } else { path }
+}
+
+fn push_key(path: &mut String, key: &Value) {
+    match *key {
+        Value::String(ref key) => path.push_str(&format!(".{}", key)),
+        ref key => path.push_str(&format!("[{}]", key)),
+    }
+}
+
+fn find<'a>(map: &'a [(Value, Value)], key: &Value) -> Option<&'a Value> {
+    map.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref v)| v)
+}
@@ -2,6 +2,7 @@ use serde;
 use rmp::Value;
 use rmp::value::Integer::{U64, I64};
 use rmp::value::Float::{F64, F32};
+use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Debug)]
@@ -34,15 +35,137 @@ enum State {
     Object(Vec<(Value, Value)>),
 }
 
+/// Configuration of the value `Serializer`, all options are disabled by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SerializerOptions {
+    /// Emits structs as arrays of field values, without field names.
+    pub compact_structs: bool,
+    /// Omits struct fields serialized as nil, like `None`. Has no effect with `compact_structs`,
+    /// where field positions matter.
+    pub skip_none: bool,
+    /// Emits non-negative signed integers as `U64`, the same way unsigned ones are.
+    pub narrow_ints: bool,
+    /// Sorts map entries and struct fields by key, so that equal values always produce the same
+    /// encoding.
+    pub canonical: bool,
+    /// Emits `f64` values as `F32` when they survive the round-trip through `f32` exactly.
+    pub compress_floats: bool,
+    /// Emits empty strings, sequences and maps as nil. Structs are not affected.
+    pub empty_as_nil: bool,
+    /// Emits unit variants as their index, including in map keys, like C-style enums.
+    pub enum_as_int: bool,
+    /// Emits variants as single-entry maps from their index to their content, like `{1: [2, 3]}`,
+    /// instead of their content alone. Unit variants have a nil content, except in map keys where
+    /// they are emitted as their index. Has no effect on unit variants with `enum_as_int`.
+    pub enum_as_index_map: bool,
+    /// Emits every element of sequences, tuples and compact structs as a `[tag, element]` pair,
+    /// where the tag tells the type of the element as given by `type_tag`.
+    pub tagged_elements: bool,
+}
+
+/// Returns the tag identifying the type of the given value with `tagged_elements`: 0 for nil,
+/// 1 for booleans, 2 for integers, 3 for floats, 4 for strings, 5 for binaries, 6 for arrays,
+/// 7 for maps and 8 for ext values.
+pub fn type_tag(value: &Value) -> u8 {
+    match *value {
+        Value::Nil => 0,
+        Value::Boolean(..) => 1,
+        Value::Integer(..) => 2,
+        Value::Float(..) => 3,
+        Value::String(..) => 4,
+        Value::Binary(..) => 5,
+        Value::Array(..) => 6,
+        Value::Map(..) => 7,
+        Value::Ext(..) => 8,
+    }
+}
+
 pub struct Serializer {
     state: Vec<State>,
+    opts: SerializerOptions,
+    in_key: bool,
+    field_map: HashMap<&'static str, u32>,
 }
 
 impl Serializer {
     pub fn new() -> Serializer {
+        Serializer::with_options(SerializerOptions::default())
+    }
+
+    pub fn with_options(opts: SerializerOptions) -> Serializer {
         Serializer {
             state: Vec::new(),
+            opts: opts,
+            in_key: false,
+            field_map: HashMap::new(),
+        }
+    }
+
+    /// Emits `f64` values as `F32` when they survive the round-trip through `f32` exactly, like
+    /// `1.5`. Values such as `0.1` are kept as `F64`.
+    pub fn set_compress_floats(&mut self, enabled: bool) {
+        self.opts.compress_floats = enabled;
+    }
+
+    /// Emits struct field names as integer keys according to the given `field => index` table, for
+    /// compactness with a schema agreed upon upfront. The `Deserializer` takes the reverse table.
+    ///
+    /// Serializing a struct field absent from the table fails. Has no effect with
+    /// `compact_structs`, which omits field names altogether.
+    pub fn set_field_map(&mut self, map: HashMap<&'static str, u32>) {
+        self.field_map = map;
+    }
+
+    /// Drops any state left behind by a failed nested serialization, so the stack stays
+    /// consistent with the point where it started.
+    fn restore<T>(&mut self, depth: usize, res: Result<T, Error>) -> Result<T, Error> {
+        if res.is_err() {
+            self.state.truncate(depth);
         }
+
+        res
+    }
+
+    /// Collects the entries produced by the visitor, sorted by key if canonical.
+    fn collect_map<V>(&mut self, mut visitor: V) -> Result<Vec<(Value, Value)>, Error>
+        where V: serde::ser::MapVisitor,
+    {
+        let len = visitor.len().unwrap_or(0);
+        let values = Vec::with_capacity(len);
+
+        let depth = self.state.len();
+        self.state.push(State::Object(values));
+
+        loop {
+            let res = visitor.visit(self);
+            if try!(self.restore(depth, res)).is_none() {
+                break;
+            }
+        }
+
+        let mut values = match self.state.pop().unwrap() {
+            State::Object(values) => values,
+            state => panic!("expected object, found {:?}", state),
+        };
+
+        if self.opts.canonical {
+            values.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+        }
+
+        Ok(values)
+    }
+
+    /// Replaces the value just serialized with a single-entry map from the variant index to it.
+    fn wrap_variant(&mut self, variant_index: usize) -> Result<(), Error> {
+        let value = match self.state.pop().unwrap() {
+            State::Value(value) => value,
+            state => panic!("expected value, found {:?}", state),
+        };
+
+        let key = Value::Integer(U64(variant_index as u64));
+        self.state.push(State::Value(Value::Map(vec![(key, value)])));
+
+        Ok(())
     }
 
     pub fn unwrap(mut self) -> Value {
@@ -70,6 +193,10 @@ impl serde::ser::Serializer for Serializer {
 
     #[inline]
     fn serialize_i64(&mut self, value: i64) -> Result<(), Error> {
+        if self.opts.narrow_ints && value >= 0 {
+            return self.serialize_u64(value as u64);
+        }
+
         self.state.push(State::Value(Value::Integer(I64(value))));
         Ok(())
     }
@@ -88,6 +215,10 @@ impl serde::ser::Serializer for Serializer {
 
     #[inline]
     fn serialize_f64(&mut self, value: f64) -> Result<(), Error> {
+        if self.opts.compress_floats && value as f32 as f64 == value {
+            return self.serialize_f32(value as f32);
+        }
+
         self.state.push(State::Value(Value::Float(F64(value))));
         Ok(())
     }
@@ -101,10 +232,21 @@ impl serde::ser::Serializer for Serializer {
 
     #[inline]
     fn serialize_str(&mut self, value: &str) -> Result<(), Error> {
+        // Map keys are kept, so that an empty key stays distinguishable from a nil one.
+        if self.opts.empty_as_nil && value.is_empty() && !self.in_key {
+            return self.serialize_unit();
+        }
+
         self.state.push(State::Value(Value::String(String::from(value))));
         Ok(())
     }
 
+    #[inline]
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.state.push(State::Value(Value::Binary(value.to_vec())));
+        Ok(())
+    }
+
     #[inline]
     fn serialize_none(&mut self) -> Result<(), Error> {
         self.serialize_unit()
@@ -124,15 +266,25 @@ impl serde::ser::Serializer for Serializer {
         let len = visitor.len().unwrap_or(0);
         let values = Vec::with_capacity(len);
 
+        let depth = self.state.len();
         self.state.push(State::Array(values));
 
-        while let Some(()) = try!(visitor.visit(self)) { }
+        loop {
+            let res = visitor.visit(self);
+            if try!(self.restore(depth, res)).is_none() {
+                break;
+            }
+        }
 
         let values = match self.state.pop().unwrap() {
             State::Array(values) => values,
             state => panic!("Expected array, found {:?}", state),
         };
 
+        if self.opts.empty_as_nil && values.is_empty() {
+            return self.serialize_unit();
+        }
+
         self.state.push(State::Value(Value::Array(values)));
 
         Ok(())
@@ -142,13 +294,21 @@ impl serde::ser::Serializer for Serializer {
     fn serialize_seq_elt<T>(&mut self, value: T) -> Result<(), Error>
         where T: serde::ser::Serialize,
     {
-        try!(value.serialize(self));
+        let depth = self.state.len();
+        let res = value.serialize(self);
+        try!(self.restore(depth, res));
 
         let value = match self.state.pop().unwrap() {
             State::Value(value) => value,
             state => panic!("expected value, found {:?}", state),
         };
 
+        let value = if self.opts.tagged_elements {
+            Value::Array(vec![Value::Integer(U64(type_tag(&value) as u64)), value])
+        } else {
+            value
+        };
+
         match *self.state.last_mut().unwrap() {
             State::Array(ref mut values) => { values.push(value); }
             ref state => panic!("expected array, found {:?}", state),
@@ -158,19 +318,14 @@ impl serde::ser::Serializer for Serializer {
     }
 
     #[inline]
-    fn serialize_map<V>(&mut self, mut visitor: V) -> Result<(), Error>
+    fn serialize_map<V>(&mut self, visitor: V) -> Result<(), Error>
         where V: serde::ser::MapVisitor,
     {
-        let values = Vec::new();
-
-        self.state.push(State::Object(values));
+        let values = try!(self.collect_map(visitor));
 
-        while let Some(()) = try!(visitor.visit(self)) { }
-
-        let values = match self.state.pop().unwrap() {
-            State::Object(values) => values,
-            state => panic!("expected object, found {:?}", state),
-        };
+        if self.opts.empty_as_nil && values.is_empty() {
+            return self.serialize_unit();
+        }
 
         self.state.push(State::Value(Value::Map(values)));
 
@@ -182,20 +337,98 @@ impl serde::ser::Serializer for Serializer {
         where K: serde::ser::Serialize,
               V: serde::ser::Serialize,
     {
-        try!(key.serialize(self));
+        // On error the object is left as it was, without a dangling key.
+        let depth = self.state.len();
+        let in_key = self.in_key;
+        self.in_key = true;
+        let res = key.serialize(self);
+        self.in_key = in_key;
+        try!(self.restore(depth, res));
 
         let key = match self.state.pop().unwrap() {
             State::Value(key) => key,
             state => panic!("expected key, found {:?}", state),
         };
 
-        try!(value.serialize(self));
+        let res = value.serialize(self);
+        try!(self.restore(depth, res));
+
+        let value = match self.state.pop().unwrap() {
+            State::Value(value) => value,
+            state => panic!("expected value, found {:?}", state),
+        };
+
+        match *self.state.last_mut().unwrap() {
+            State::Object(ref mut values) => { values.push((key, value)); }
+            ref state => panic!("expected object, found {:?}", state),
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_struct<V>(&mut self, _name: &'static str, mut visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor,
+    {
+        if !self.opts.compact_structs {
+            let values = try!(self.collect_map(visitor));
+            self.state.push(State::Value(Value::Map(values)));
+            return Ok(());
+        }
+
+        let len = visitor.len().unwrap_or(0);
+        let values = Vec::with_capacity(len);
+
+        let depth = self.state.len();
+        self.state.push(State::Array(values));
+
+        loop {
+            let res = visitor.visit(self);
+            if try!(self.restore(depth, res)).is_none() {
+                break;
+            }
+        }
+
+        let values = match self.state.pop().unwrap() {
+            State::Array(values) => values,
+            state => panic!("expected array, found {:?}", state),
+        };
+
+        self.state.push(State::Value(Value::Array(values)));
+
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_struct_elt<V>(&mut self, key: &'static str, value: V) -> Result<(), Error>
+        where V: serde::ser::Serialize,
+    {
+        if self.opts.compact_structs {
+            return self.serialize_seq_elt(value);
+        }
+
+        let key = if self.field_map.is_empty() {
+            Value::String(key.to_string())
+        } else {
+            match self.field_map.get(key) {
+                Some(&idx) => Value::Integer(U64(idx as u64)),
+                None => return Err(Error::Custom(format!("field `{}` is missing from the field map", key))),
+            }
+        };
+
+        let depth = self.state.len();
+        let res = value.serialize(self);
+        try!(self.restore(depth, res));
 
         let value = match self.state.pop().unwrap() {
             State::Value(value) => value,
             state => panic!("expected value, found {:?}", state),
         };
 
+        if self.opts.skip_none && value == Value::Nil {
+            return Ok(());
+        }
+
         match *self.state.last_mut().unwrap() {
             State::Object(ref mut values) => { values.push((key, value)); }
             ref state => panic!("expected object, found {:?}", state),
@@ -204,6 +437,78 @@ impl serde::ser::Serializer for Serializer {
         Ok(())
     }
 
+    /// Unit variants used as map keys, like in `HashMap<Color, u32>`, are emitted as their variant
+    /// name, so the key stays a plain string.
+    #[inline]
+    fn serialize_unit_variant(&mut self,
+                              _name: &'static str,
+                              variant_index: usize,
+                              variant: &'static str) -> Result<(), Error> {
+        if self.opts.enum_as_int || (self.in_key && self.opts.enum_as_index_map) {
+            self.serialize_u64(variant_index as u64)
+        } else if self.in_key {
+            self.serialize_str(variant)
+        } else if self.opts.enum_as_index_map {
+            try!(self.serialize_unit());
+            self.wrap_variant(variant_index)
+        } else {
+            self.serialize_unit()
+        }
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(&mut self,
+                                    name: &'static str,
+                                    variant_index: usize,
+                                    variant: &'static str,
+                                    value: T) -> Result<(), Error>
+        where T: serde::ser::Serialize,
+    {
+        if !self.opts.enum_as_index_map {
+            return self.serialize_tuple_variant(name, variant_index, variant, Some(value));
+        }
+
+        let depth = self.state.len();
+        let res = value.serialize(self);
+        try!(self.restore(depth, res));
+
+        self.wrap_variant(variant_index)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant<V>(&mut self,
+                                  _name: &'static str,
+                                  variant_index: usize,
+                                  variant: &'static str,
+                                  visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        try!(self.serialize_tuple_struct(variant, visitor));
+
+        if self.opts.enum_as_index_map {
+            self.wrap_variant(variant_index)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn serialize_struct_variant<V>(&mut self,
+                                   _name: &'static str,
+                                   variant_index: usize,
+                                   variant: &'static str,
+                                   visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor,
+    {
+        try!(self.serialize_struct(variant, visitor));
+
+        if self.opts.enum_as_index_map {
+            self.wrap_variant(variant_index)
+        } else {
+            Ok(())
+        }
+    }
+
     /*
     #[inline]
     fn serialize_unit_variant(&mut self,
@@ -284,6 +589,255 @@ impl serde::ser::Serializer for Serializer {
     */
 }
 
+/// Serializer handing every completed top-level value to a sink instead of accumulating them,
+/// so an unbounded stream of values can be serialized with bounded memory.
+///
+/// Values are built the same way as with the plain value `Serializer`.
+pub struct StreamingSerializer<F> {
+    ser: Serializer,
+    sink: F,
+}
+
+impl<F> StreamingSerializer<F>
+    where F: FnMut(Value)
+{
+    pub fn new(sink: F) -> StreamingSerializer<F> {
+        StreamingSerializer::with_options(sink, SerializerOptions::default())
+    }
+
+    pub fn with_options(sink: F, opts: SerializerOptions) -> StreamingSerializer<F> {
+        StreamingSerializer {
+            ser: Serializer::with_options(opts),
+            sink: sink,
+        }
+    }
+
+    /// Hands the value completed by a top-level call to the sink.
+    fn flush(&mut self, res: Result<(), Error>) -> Result<(), Error> {
+        if res.is_err() {
+            self.ser.state.clear();
+            return res;
+        }
+
+        match self.ser.state.pop() {
+            Some(State::Value(value)) => (self.sink)(value),
+            state => panic!("expected value, found {:?}", state),
+        }
+
+        Ok(())
+    }
+}
+
+macro_rules! stream_fn {
+    ($name:ident, $ty:ty) => {
+        #[inline]
+        fn $name(&mut self, value: $ty) -> Result<(), Error> {
+            let res = self.ser.$name(value);
+            self.flush(res)
+        }
+    }
+}
+
+impl<F> serde::ser::Serializer for StreamingSerializer<F>
+    where F: FnMut(Value)
+{
+    type Error = Error;
+
+    stream_fn!(serialize_bool, bool);
+    stream_fn!(serialize_isize, isize);
+    stream_fn!(serialize_i8, i8);
+    stream_fn!(serialize_i16, i16);
+    stream_fn!(serialize_i32, i32);
+    stream_fn!(serialize_i64, i64);
+    stream_fn!(serialize_usize, usize);
+    stream_fn!(serialize_u8, u8);
+    stream_fn!(serialize_u16, u16);
+    stream_fn!(serialize_u32, u32);
+    stream_fn!(serialize_u64, u64);
+    stream_fn!(serialize_f32, f32);
+    stream_fn!(serialize_f64, f64);
+    stream_fn!(serialize_char, char);
+    stream_fn!(serialize_str, &str);
+    stream_fn!(serialize_bytes, &[u8]);
+
+    #[inline]
+    fn serialize_unit(&mut self) -> Result<(), Error> {
+        let res = self.ser.serialize_unit();
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_unit_struct(&mut self, name: &'static str) -> Result<(), Error> {
+        let res = self.ser.serialize_unit_struct(name);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(&mut self,
+                              name: &'static str,
+                              variant_index: usize,
+                              variant: &'static str) -> Result<(), Error> {
+        let res = self.ser.serialize_unit_variant(name, variant_index, variant);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(&mut self, name: &'static str, value: T) -> Result<(), Error>
+        where T: serde::ser::Serialize,
+    {
+        let res = self.ser.serialize_newtype_struct(name, value);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(&mut self,
+                                    name: &'static str,
+                                    variant_index: usize,
+                                    variant: &'static str,
+                                    value: T) -> Result<(), Error>
+        where T: serde::ser::Serialize,
+    {
+        let res = self.ser.serialize_newtype_variant(name, variant_index, variant, value);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_none(&mut self) -> Result<(), Error> {
+        let res = self.ser.serialize_none();
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_some<V>(&mut self, value: V) -> Result<(), Error>
+        where V: serde::ser::Serialize,
+    {
+        let res = self.ser.serialize_some(value);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_seq<V>(&mut self, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        let res = self.ser.serialize_seq(visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_seq_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_seq_elt(value)
+    }
+
+    #[inline]
+    fn serialize_tuple<V>(&mut self, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        let res = self.ser.serialize_tuple(visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_tuple_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_tuple_elt(value)
+    }
+
+    #[inline]
+    fn serialize_fixed_size_array<V>(&mut self, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        let res = self.ser.serialize_fixed_size_array(visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_tuple_struct<V>(&mut self, name: &'static str, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        let res = self.ser.serialize_tuple_struct(name, visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_tuple_struct_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_tuple_struct_elt(value)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant<V>(&mut self,
+                                  name: &'static str,
+                                  variant_index: usize,
+                                  variant: &'static str,
+                                  visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        let res = self.ser.serialize_tuple_variant(name, variant_index, variant, visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_tuple_variant_elt(value)
+    }
+
+    #[inline]
+    fn serialize_map<V>(&mut self, visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor,
+    {
+        let res = self.ser.serialize_map(visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_map_elt<K, V>(&mut self, key: K, value: V) -> Result<(), Error>
+        where K: serde::ser::Serialize,
+              V: serde::ser::Serialize,
+    {
+        self.ser.serialize_map_elt(key, value)
+    }
+
+    #[inline]
+    fn serialize_struct<V>(&mut self, name: &'static str, visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor,
+    {
+        let res = self.ser.serialize_struct(name, visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_struct_elt<V>(&mut self, key: &'static str, value: V) -> Result<(), Error>
+        where V: serde::ser::Serialize,
+    {
+        self.ser.serialize_struct_elt(key, value)
+    }
+
+    #[inline]
+    fn serialize_struct_variant<V>(&mut self,
+                                   name: &'static str,
+                                   variant_index: usize,
+                                   variant: &'static str,
+                                   visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor,
+    {
+        let res = self.ser.serialize_struct_variant(name, variant_index, variant, visitor);
+        self.flush(res)
+    }
+
+    #[inline]
+    fn serialize_struct_variant_elt<V>(&mut self, key: &'static str, value: V) -> Result<(), Error>
+        where V: serde::ser::Serialize,
+    {
+        self.ser.serialize_struct_variant_elt(key, value)
+    }
+}
+
 pub fn to_value<T: ?Sized>(value: &T) -> Value
     where T: serde::Serialize
 {
@@ -291,3 +845,12 @@ pub fn to_value<T: ?Sized>(value: &T) -> Value
     value.serialize(&mut ser).ok().unwrap();
     ser.unwrap()
 }
+
+/// Serializes the given value using the specified options.
+pub fn to_value_with<T: ?Sized>(value: &T, opts: SerializerOptions) -> Result<Value, Error>
+    where T: serde::Serialize
+{
+    let mut ser = Serializer::with_options(opts);
+    try!(value.serialize(&mut ser));
+    Ok(ser.unwrap())
+}
@@ -0,0 +1,25 @@
+//! Error payloads for RPC responses.
+
+use std::error::Error;
+
+use rmp::Value;
+
+/// Builds an error payload `{"message": m, "source_chain": [c1, c2, ...]}` from the given error.
+///
+/// The message is the `Display` form of the error, and the chain holds those of its causes, from
+/// the closest to the root one, as returned by walking `Error::cause`.
+pub fn from_error<E: ?Sized>(err: &E) -> Value
+    where E: Error
+{
+    let mut chain = Vec::new();
+    let mut cause = err.cause();
+    while let Some(err) = cause {
+        chain.push(Value::String(format!("{}", err)));
+        cause = err.cause();
+    }
+
+    Value::Map(vec![
+        (Value::String("message".to_string()), Value::String(format!("{}", err))),
+        (Value::String("source_chain".to_string()), Value::Array(chain)),
+    ])
+}
@@ -0,0 +1,105 @@
+//! Convenience methods for working with decoded values.
+
+use std::collections::BTreeMap;
+use std::mem;
+
+use rmp::{Marker, Value};
+
+use super::decode::{Error, Result};
+
+/// Extends `Value` with accessors that avoid manual matching.
+pub trait ValueExt {
+    /// Unwraps an array into its elements.
+    ///
+    /// Returns `TypeMismatch` if the value is not an array.
+    fn into_array(self) -> Result<Vec<Value>>;
+
+    /// Unwraps a map into its key-value pairs, preserving their order.
+    ///
+    /// Returns `TypeMismatch` if the value is not a map.
+    fn into_map(self) -> Result<Vec<(Value, Value)>>;
+
+    /// Unwraps a map into an ordered map, keeping keys of any type. Of duplicate keys the last
+    /// one wins.
+    ///
+    /// Returns `TypeMismatch` if the value is not a map.
+    fn into_btree_map(self) -> Result<BTreeMap<Value, Value>>;
+
+    /// Replaces the value with nil, returning the previous one.
+    fn take(&mut self) -> Value;
+
+    /// Returns the value of a map entry with the given string key, if any.
+    ///
+    /// Returns `None` if the value is not a map.
+    fn get_mut(&mut self, key: &str) -> Option<&mut Value>;
+
+    /// Returns the elements of an array, or `None` if the value is not an array.
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Value>>;
+
+    /// Inserts an entry into a map, returning the previous value of the key, if any. New keys
+    /// are appended after the existing entries, while existing ones keep their position.
+    ///
+    /// Returns `TypeMismatch` if the value is not a map.
+    fn insert(&mut self, key: Value, value: Value) -> Result<Option<Value>>;
+}
+
+impl ValueExt for Value {
+    fn into_array(self) -> Result<Vec<Value>> {
+        match self {
+            Value::Array(vec) => Ok(vec),
+            _ => Err(Error::TypeMismatch(Marker::Array32)),
+        }
+    }
+
+    fn into_map(self) -> Result<Vec<(Value, Value)>> {
+        match self {
+            Value::Map(vec) => Ok(vec),
+            _ => Err(Error::TypeMismatch(Marker::Map32)),
+        }
+    }
+
+    fn into_btree_map(self) -> Result<BTreeMap<Value, Value>> {
+        self.into_map().map(|vec| vec.into_iter().collect())
+    }
+
+    fn take(&mut self) -> Value {
+        mem::replace(self, Value::Nil)
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match *self {
+            Value::Map(ref mut vec) => {
+                vec.iter_mut()
+                    .find(|&&mut (ref k, _)| match *k {
+                        Value::String(ref k) => k == key,
+                        _ => false,
+                    })
+                    .map(|&mut (_, ref mut v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match *self {
+            Value::Array(ref mut vec) => Some(vec),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, key: Value, value: Value) -> Result<Option<Value>> {
+        let vec = match *self {
+            Value::Map(ref mut vec) => vec,
+            _ => return Err(Error::TypeMismatch(Marker::Map32)),
+        };
+
+        for &mut (ref k, ref mut v) in vec.iter_mut() {
+            if *k == key {
+                return Ok(Some(mem::replace(v, value)));
+            }
+        }
+
+        vec.push((key, value));
+        Ok(None)
+    }
+}
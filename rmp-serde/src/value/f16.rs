@@ -0,0 +1,32 @@
+//! Half-precision float encoding for use with `serialize_with` and `deserialize_with`.
+//!
+//! Values are encoded as `f32`, which represents every `f16` exactly, including subnormals,
+//! infinities and NaN. Decoding accepts any float and rounds it to the nearest `f16`.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Layer {
+//!     #[serde(serialize_with = "rmp_serde::value::f16::serialize",
+//!             deserialize_with = "rmp_serde::value::f16::deserialize")]
+//!     weight: f16,
+//! }
+//! ```
+
+use std::result;
+
+use serde;
+
+use half::f16;
+
+pub fn serialize<S>(value: &f16, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    s.serialize_f32(value.to_f32())
+}
+
+pub fn deserialize<D>(d: &mut D) -> result::Result<f16, D::Error>
+    where D: serde::Deserializer
+{
+    let value: f32 = try!(serde::Deserialize::deserialize(d));
+    Ok(f16::from_f32(value))
+}
@@ -0,0 +1,272 @@
+//! Partial serialization of structs, like for PATCH requests.
+//!
+//! The `FieldFilterSerializer` decorates another serializer, usually the value `Serializer`, and
+//! drops every field of the outermost struct or map whose name is not in the given allowlist.
+//! Nested values are serialized unchanged.
+//!
+//! The number of emitted fields is not known upfront, so the length hint is dropped. Serializers
+//! that write the length before the fields, like the MessagePack `Serializer`, are not supported.
+//!
+//! # Examples
+//! ```
+//! use std::collections::BTreeMap;
+//! use serde::Serialize;
+//! use rmp_serde::value::encode::Serializer;
+//! use rmp_serde::value::filter::FieldFilterSerializer;
+//!
+//! # extern crate serde;
+//! # extern crate rmp;
+//! # extern crate rmp_serde;
+//! # fn main() {
+//! let mut map = BTreeMap::new();
+//! map.insert("id", 1);
+//! map.insert("name", 2);
+//!
+//! let mut ser = Serializer::new();
+//! map.serialize(&mut FieldFilterSerializer::new(&mut ser, &["name"])).unwrap();
+//!
+//! assert_eq!("{\"name\": 2}", format!("{}", ser.unwrap()));
+//! # }
+//! ```
+
+use std::result;
+
+use rmp::Value;
+use serde;
+
+use super::encode::{SerializerOptions, to_value_with};
+
+/// Serializer decorator emitting only the allowed fields of the outermost struct or map.
+pub struct FieldFilterSerializer<'a, S: 'a> {
+    ser: &'a mut S,
+    fields: &'a [&'a str],
+}
+
+impl<'a, S: 'a> FieldFilterSerializer<'a, S> {
+    pub fn new(ser: &'a mut S, fields: &'a [&'a str]) -> FieldFilterSerializer<'a, S> {
+        FieldFilterSerializer {
+            ser: ser,
+            fields: fields,
+        }
+    }
+
+    fn is_allowed(&self, field: &str) -> bool {
+        self.fields.iter().any(|f| *f == field)
+    }
+}
+
+macro_rules! forward_fn {
+    ($name:ident, $ty:ty) => {
+        #[inline]
+        fn $name(&mut self, v: $ty) -> result::Result<(), S::Error> {
+            self.ser.$name(v)
+        }
+    }
+}
+
+impl<'a, S> serde::Serializer for FieldFilterSerializer<'a, S>
+    where S: serde::Serializer
+{
+    type Error = S::Error;
+
+    forward_fn!(serialize_bool, bool);
+    forward_fn!(serialize_isize, isize);
+    forward_fn!(serialize_i8, i8);
+    forward_fn!(serialize_i16, i16);
+    forward_fn!(serialize_i32, i32);
+    forward_fn!(serialize_i64, i64);
+    forward_fn!(serialize_usize, usize);
+    forward_fn!(serialize_u8, u8);
+    forward_fn!(serialize_u16, u16);
+    forward_fn!(serialize_u32, u32);
+    forward_fn!(serialize_u64, u64);
+    forward_fn!(serialize_f32, f32);
+    forward_fn!(serialize_f64, f64);
+    forward_fn!(serialize_char, char);
+    forward_fn!(serialize_str, &str);
+    forward_fn!(serialize_bytes, &[u8]);
+
+    fn serialize_unit(&mut self) -> result::Result<(), S::Error> {
+        self.ser.serialize_unit()
+    }
+
+    fn serialize_unit_struct(&mut self, name: &'static str) -> result::Result<(), S::Error> {
+        self.ser.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(&mut self,
+                              name: &'static str,
+                              variant_index: usize,
+                              variant: &'static str) -> result::Result<(), S::Error>
+    {
+        self.ser.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(&mut self, name: &'static str, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.ser.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T>(&mut self,
+                                    name: &'static str,
+                                    variant_index: usize,
+                                    variant: &'static str,
+                                    value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.ser.serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    fn serialize_none(&mut self) -> result::Result<(), S::Error> {
+        self.ser.serialize_none()
+    }
+
+    fn serialize_some<V>(&mut self, value: V) -> result::Result<(), S::Error>
+        where V: serde::Serialize
+    {
+        self.ser.serialize_some(value)
+    }
+
+    fn serialize_seq<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.ser.serialize_seq(visitor)
+    }
+
+    fn serialize_seq_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.ser.serialize_seq_elt(value)
+    }
+
+    fn serialize_tuple<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.ser.serialize_tuple(visitor)
+    }
+
+    fn serialize_tuple_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.ser.serialize_tuple_elt(value)
+    }
+
+    fn serialize_fixed_size_array<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.ser.serialize_fixed_size_array(visitor)
+    }
+
+    fn serialize_tuple_struct<V>(&mut self, name: &'static str, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.ser.serialize_tuple_struct(name, visitor)
+    }
+
+    fn serialize_tuple_struct_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.ser.serialize_tuple_struct_elt(value)
+    }
+
+    fn serialize_tuple_variant<V>(&mut self,
+                                  name: &'static str,
+                                  variant_index: usize,
+                                  variant: &'static str,
+                                  visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.ser.serialize_tuple_variant(name, variant_index, variant, visitor)
+    }
+
+    fn serialize_tuple_variant_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.ser.serialize_tuple_variant_elt(value)
+    }
+
+    fn serialize_map<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::MapVisitor
+    {
+        self.ser.serialize_map(Filtered::new(visitor, self.fields))
+    }
+
+    /// Map keys are compared by their string value, entries with other keys are dropped.
+    fn serialize_map_elt<K, V>(&mut self, key: K, value: V) -> result::Result<(), S::Error>
+        where K: serde::Serialize,
+              V: serde::Serialize
+    {
+        let allowed = match to_value_with(&key, SerializerOptions::default()) {
+            Ok(Value::String(ref key)) => self.is_allowed(key),
+            _ => false,
+        };
+
+        if allowed {
+            self.ser.serialize_map_elt(key, value)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn serialize_struct<V>(&mut self, name: &'static str, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::MapVisitor
+    {
+        self.ser.serialize_struct(name, Filtered::new(visitor, self.fields))
+    }
+
+    fn serialize_struct_elt<V>(&mut self, key: &'static str, value: V) -> result::Result<(), S::Error>
+        where V: serde::Serialize
+    {
+        if self.is_allowed(key) {
+            self.ser.serialize_struct_elt(key, value)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn serialize_struct_variant<V>(&mut self,
+                                   name: &'static str,
+                                   variant_index: usize,
+                                   variant: &'static str,
+                                   visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::MapVisitor
+    {
+        self.ser.serialize_struct_variant(name, variant_index, variant, visitor)
+    }
+
+    fn serialize_struct_variant_elt<V>(&mut self, key: &'static str, value: V) -> result::Result<(), S::Error>
+        where V: serde::Serialize
+    {
+        self.ser.serialize_struct_variant_elt(key, value)
+    }
+}
+
+/// Wraps the visitor of the outermost struct or map, so that its fields are filtered.
+struct Filtered<'a, V> {
+    visitor: V,
+    fields: &'a [&'a str],
+}
+
+impl<'a, V> Filtered<'a, V> {
+    fn new(visitor: V, fields: &'a [&'a str]) -> Filtered<'a, V> {
+        Filtered {
+            visitor: visitor,
+            fields: fields,
+        }
+    }
+}
+
+impl<'a, V> serde::ser::MapVisitor for Filtered<'a, V>
+    where V: serde::ser::MapVisitor
+{
+    fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+        where S: serde::Serializer
+    {
+        self.visitor.visit(&mut FieldFilterSerializer::new(ser, self.fields))
+    }
+
+    fn len(&self) -> Option<usize> {
+        None
+    }
+}
@@ -0,0 +1,175 @@
+//! Conversion between nested values and flat maps of paths to leaf values, for key-value stores.
+
+use std::collections::BTreeMap;
+
+use rmp::Value;
+
+use super::walk::walk;
+
+/// Returns the leaf values found in the given value, keyed by their path, like `a.b[0]`.
+///
+/// Paths use the notation of `get_path`, without the leading dot. Empty arrays and maps are kept
+/// as leaves, so that they survive `unflatten`, and a value that is neither an array nor a map
+/// is keyed by the empty path. Keys are not escaped, so string keys containing `.` or `[` yield
+/// paths that do not round-trip.
+pub fn flatten(value: &Value) -> BTreeMap<String, Value> {
+    let mut leaves = BTreeMap::new();
+
+    walk(value, &mut |path, value| {
+        match *value {
+            Value::Array(ref vec) if !vec.is_empty() => (),
+            Value::Map(ref map) if !map.is_empty() => (),
+            ref value => {
+                leaves.insert(path.to_owned(), value.clone());
+            }
+        }
+    });
+
+    leaves
+}
+
+/// Rebuilds the value described by a map of paths to leaf values, as returned by `flatten`.
+///
+/// Returns `None` if a path is malformed or if two paths conflict, like `a` and `a.b`. Map
+/// entries are created in the order of their paths and array elements missing from the paths
+/// are filled with nil. Since `[n]` always denotes an array index, maps with integer keys come
+/// back as arrays.
+///
+/// Array indices must be lower than the number of leaves, which `flatten` output always satisfies,
+/// so that a path like `a[4000000000]` cannot make arrays grow far beyond the size of the input.
+/// Paths with larger indices are rejected as malformed.
+pub fn unflatten(leaves: BTreeMap<String, Value>) -> Option<Value> {
+    let mut root = Node::Empty;
+    let max_len = leaves.len();
+
+    for (path, leaf) in leaves {
+        let segments = match parse(&path, max_len) {
+            Some(segments) => segments,
+            None => return None,
+        };
+
+        if !root.insert(&segments, leaf) {
+            return None;
+        }
+    }
+
+    Some(root.into_value())
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Splits the path into segments, rejecting array indices not lower than `max_len`.
+fn parse(path: &str, max_len: usize) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+
+    while !rest.is_empty() {
+        if rest.starts_with('[') {
+            let end = match rest.find(']') {
+                Some(end) => end,
+                None => return None,
+            };
+
+            match rest[1..end].parse() {
+                Ok(idx) if idx < max_len => segments.push(Segment::Index(idx)),
+                _ => return None,
+            }
+            rest = &rest[end + 1..];
+        } else {
+            if rest.starts_with('.') {
+                rest = &rest[1..];
+            }
+
+            let end = rest.find(|c| c == '.' || c == '[').unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+
+            segments.push(Segment::Key(rest[..end].to_owned()));
+            rest = &rest[end..];
+        }
+    }
+
+    Some(segments)
+}
+
+/// A value under construction, where `Empty` marks the places no path has reached yet.
+enum Node {
+    Empty,
+    Leaf(Value),
+    Array(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+impl Node {
+    /// Places the leaf at the given path below this node, returning `false` on conflict.
+    fn insert(&mut self, segments: &[Segment], leaf: Value) -> bool {
+        let (segment, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => {
+                return match *self {
+                    Node::Empty => {
+                        *self = Node::Leaf(leaf);
+                        true
+                    }
+                    _ => false,
+                };
+            }
+        };
+
+        match *segment {
+            Segment::Key(ref key) => {
+                if let Node::Empty = *self {
+                    *self = Node::Map(Vec::new());
+                }
+
+                match *self {
+                    Node::Map(ref mut map) => {
+                        let pos = match map.iter().position(|&(ref k, _)| k == key) {
+                            Some(pos) => pos,
+                            None => {
+                                map.push((key.clone(), Node::Empty));
+                                map.len() - 1
+                            }
+                        };
+
+                        map[pos].1.insert(rest, leaf)
+                    }
+                    _ => false,
+                }
+            }
+            Segment::Index(idx) => {
+                if let Node::Empty = *self {
+                    *self = Node::Array(Vec::new());
+                }
+
+                match *self {
+                    Node::Array(ref mut vec) => {
+                        while vec.len() <= idx {
+                            vec.push(Node::Empty);
+                        }
+
+                        vec[idx].insert(rest, leaf)
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Node::Empty => Value::Nil,
+            Node::Leaf(value) => value,
+            Node::Array(vec) => Value::Array(vec.into_iter().map(Node::into_value).collect()),
+            Node::Map(map) => {
+                Value::Map(map.into_iter()
+                    .map(|(key, node)| (Value::String(key), node.into_value()))
+                    .collect())
+            }
+        }
+    }
+}
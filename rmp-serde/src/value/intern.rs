@@ -0,0 +1,432 @@
+//! String interning for payloads with many repeated strings.
+//!
+//! Every string that occurs more than once in a value is moved into a dictionary and replaced by
+//! an ext value of type `STRING_REF_EXT` whose payload is the MessagePack-encoded dictionary
+//! index, as long as this makes the encoding smaller. Short strings, whose references would take
+//! more room than their repeated copies, are kept in place. The result is an envelope of the form
+//! `[dictionary, data]`.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use serde;
+
+use rmp::Value;
+use rmp::decode::read_u64_loosely;
+use rmp::encode::write_uint;
+
+use super::decode::{self, Deserializer};
+use super::encode::{self, Serializer};
+
+/// Ext type used to reference an entry of the string dictionary.
+pub const STRING_REF_EXT: i8 = 0x49;
+
+/// Serializer that deduplicates repeated strings into a dictionary.
+///
+/// Values are accumulated the same way as with the plain value `Serializer`; interning happens
+/// once the whole value is known, in `unwrap`.
+pub struct StringInterningSerializer {
+    ser: Serializer,
+}
+
+impl StringInterningSerializer {
+    pub fn new() -> StringInterningSerializer {
+        StringInterningSerializer {
+            ser: Serializer::new(),
+        }
+    }
+
+    /// Returns the `[dictionary, data]` envelope.
+    pub fn unwrap(self) -> Value {
+        intern(self.ser.unwrap())
+    }
+}
+
+macro_rules! forward_fn {
+    ($name:ident, $ty:ty) => {
+        #[inline]
+        fn $name(&mut self, value: $ty) -> Result<(), encode::Error> {
+            self.ser.$name(value)
+        }
+    }
+}
+
+impl serde::ser::Serializer for StringInterningSerializer {
+    type Error = encode::Error;
+
+    forward_fn!(serialize_bool, bool);
+    forward_fn!(serialize_isize, isize);
+    forward_fn!(serialize_i8, i8);
+    forward_fn!(serialize_i16, i16);
+    forward_fn!(serialize_i32, i32);
+    forward_fn!(serialize_i64, i64);
+    forward_fn!(serialize_usize, usize);
+    forward_fn!(serialize_u8, u8);
+    forward_fn!(serialize_u16, u16);
+    forward_fn!(serialize_u32, u32);
+    forward_fn!(serialize_u64, u64);
+    forward_fn!(serialize_f32, f32);
+    forward_fn!(serialize_f64, f64);
+    forward_fn!(serialize_char, char);
+    forward_fn!(serialize_str, &str);
+    forward_fn!(serialize_bytes, &[u8]);
+
+    #[inline]
+    fn serialize_unit(&mut self) -> Result<(), encode::Error> {
+        self.ser.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_unit_struct(&mut self, name: &'static str) -> Result<(), encode::Error> {
+        self.ser.serialize_unit_struct(name)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(&mut self,
+                              name: &'static str,
+                              variant_index: usize,
+                              variant: &'static str) -> Result<(), encode::Error>
+    {
+        self.ser.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(&mut self, name: &'static str, value: T) -> Result<(), encode::Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_newtype_struct(name, value)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(&mut self,
+                                    name: &'static str,
+                                    variant_index: usize,
+                                    variant: &'static str,
+                                    value: T) -> Result<(), encode::Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    #[inline]
+    fn serialize_none(&mut self) -> Result<(), encode::Error> {
+        self.ser.serialize_none()
+    }
+
+    #[inline]
+    fn serialize_some<V>(&mut self, value: V) -> Result<(), encode::Error>
+        where V: serde::ser::Serialize,
+    {
+        self.ser.serialize_some(value)
+    }
+
+    #[inline]
+    fn serialize_seq<V>(&mut self, visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        self.ser.serialize_seq(visitor)
+    }
+
+    #[inline]
+    fn serialize_seq_elt<T>(&mut self, value: T) -> Result<(), encode::Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_seq_elt(value)
+    }
+
+    #[inline]
+    fn serialize_tuple<V>(&mut self, visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        self.ser.serialize_tuple(visitor)
+    }
+
+    #[inline]
+    fn serialize_tuple_elt<T>(&mut self, value: T) -> Result<(), encode::Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_tuple_elt(value)
+    }
+
+    #[inline]
+    fn serialize_fixed_size_array<V>(&mut self, visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        self.ser.serialize_fixed_size_array(visitor)
+    }
+
+    #[inline]
+    fn serialize_tuple_struct<V>(&mut self, name: &'static str, visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        self.ser.serialize_tuple_struct(name, visitor)
+    }
+
+    #[inline]
+    fn serialize_tuple_struct_elt<T>(&mut self, value: T) -> Result<(), encode::Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_tuple_struct_elt(value)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant<V>(&mut self,
+                                  name: &'static str,
+                                  variant_index: usize,
+                                  variant: &'static str,
+                                  visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::SeqVisitor,
+    {
+        self.ser.serialize_tuple_variant(name, variant_index, variant, visitor)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant_elt<T>(&mut self, value: T) -> Result<(), encode::Error>
+        where T: serde::ser::Serialize,
+    {
+        self.ser.serialize_tuple_variant_elt(value)
+    }
+
+    #[inline]
+    fn serialize_map<V>(&mut self, visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::MapVisitor,
+    {
+        self.ser.serialize_map(visitor)
+    }
+
+    #[inline]
+    fn serialize_map_elt<K, V>(&mut self, key: K, value: V) -> Result<(), encode::Error>
+        where K: serde::ser::Serialize,
+              V: serde::ser::Serialize,
+    {
+        self.ser.serialize_map_elt(key, value)
+    }
+
+    #[inline]
+    fn serialize_struct<V>(&mut self, name: &'static str, visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::MapVisitor,
+    {
+        self.ser.serialize_struct(name, visitor)
+    }
+
+    #[inline]
+    fn serialize_struct_elt<V>(&mut self, key: &'static str, value: V) -> Result<(), encode::Error>
+        where V: serde::ser::Serialize,
+    {
+        self.ser.serialize_struct_elt(key, value)
+    }
+
+    #[inline]
+    fn serialize_struct_variant<V>(&mut self,
+                                   name: &'static str,
+                                   variant_index: usize,
+                                   variant: &'static str,
+                                   visitor: V) -> Result<(), encode::Error>
+        where V: serde::ser::MapVisitor,
+    {
+        self.ser.serialize_struct_variant(name, variant_index, variant, visitor)
+    }
+
+    #[inline]
+    fn serialize_struct_variant_elt<V>(&mut self, key: &'static str, value: V) -> Result<(), encode::Error>
+        where V: serde::ser::Serialize,
+    {
+        self.ser.serialize_struct_variant_elt(key, value)
+    }
+}
+
+/// Moves every string occurring more than once into a dictionary where this saves space,
+/// returning the `[dictionary, data]` envelope.
+pub fn intern(value: Value) -> Value {
+    let mut counts = HashMap::new();
+    count_strings(&value, &mut counts);
+
+    let mut dict = Vec::new();
+    let mut indices = HashMap::new();
+    let data = replace_strings(value, &counts, &mut indices, &mut dict);
+
+    Value::Array(vec![Value::Array(dict), data])
+}
+
+/// Restores the original value from a `[dictionary, data]` envelope produced by `intern`.
+pub fn unintern(value: Value) -> decode::Result<Value> {
+    let mut envelope = match value {
+        Value::Array(vec) => vec,
+        _ => return Err(serde::de::Error::invalid_value("expected [dictionary, data] envelope")),
+    };
+
+    if envelope.len() != 2 {
+        return Err(decode::Error::LengthMismatch(envelope.len() as u32));
+    }
+
+    let data = envelope.pop().unwrap();
+    let dict = match envelope.pop().unwrap() {
+        Value::Array(vec) => vec,
+        _ => return Err(serde::de::Error::invalid_value("expected string dictionary")),
+    };
+
+    let mut strings = Vec::with_capacity(dict.len());
+    for entry in dict {
+        match entry {
+            Value::String(s) => strings.push(s),
+            _ => return Err(serde::de::Error::invalid_value("expected string dictionary entry")),
+        }
+    }
+
+    restore_strings(data, &strings)
+}
+
+/// Serializes the given value into an interned `[dictionary, data]` envelope.
+pub fn to_interned_value<T: ?Sized>(value: &T) -> Result<Value, encode::Error>
+    where T: serde::Serialize
+{
+    let mut ser = StringInterningSerializer::new();
+    try!(value.serialize(&mut ser));
+    Ok(ser.unwrap())
+}
+
+/// Deserializes an instance of type `T` from an interned `[dictionary, data]` envelope.
+pub fn from_interned_value<T>(value: Value) -> decode::Result<T>
+    where T: serde::Deserialize
+{
+    let value = try!(unintern(value));
+    serde::Deserialize::deserialize(&mut Deserializer::new(value))
+}
+
+fn count_strings(value: &Value, counts: &mut HashMap<String, usize>) {
+    match *value {
+        Value::String(ref s) => {
+            *counts.entry(s.clone()).or_insert(0) += 1;
+        }
+        Value::Array(ref vec) => {
+            for value in vec {
+                count_strings(value, counts);
+            }
+        }
+        Value::Map(ref vec) => {
+            for &(ref key, ref value) in vec {
+                count_strings(key, counts);
+                count_strings(value, counts);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the encoded size of a string of the given length.
+fn str_size(len: usize) -> usize {
+    let header = if len < 32 {
+        1
+    } else if len < 256 {
+        2
+    } else if len < 65536 {
+        3
+    } else {
+        5
+    };
+
+    header + len
+}
+
+/// Returns the encoded size of a reference to the given dictionary index, an ext value whose
+/// payload is the index as written by `write_uint`.
+fn ref_size(idx: u64) -> usize {
+    if idx < 128 {
+        3
+    } else if idx < 256 {
+        4
+    } else if idx < 65536 {
+        6
+    } else if idx < 4294967296 {
+        8
+    } else {
+        12
+    }
+}
+
+/// Replaces the strings worth interning with references, deciding for each string once, when it
+/// is first met and its index is known.
+///
+/// A string is interned if its dictionary entry and references take less room than its copies.
+fn replace_strings(value: Value,
+                   counts: &HashMap<String, usize>,
+                   indices: &mut HashMap<String, Option<u64>>,
+                   dict: &mut Vec<Value>) -> Value
+{
+    match value {
+        Value::String(s) => {
+            let idx = match indices.get(&s) {
+                Some(&idx) => idx,
+                None => {
+                    let count = counts[&s];
+                    let idx = dict.len() as u64;
+
+                    let size = str_size(s.len());
+                    let interned = count > 1 && size + count * ref_size(idx) < count * size;
+
+                    if interned {
+                        dict.push(Value::String(s.clone()));
+                    }
+
+                    let idx = if interned { Some(idx) } else { None };
+                    indices.insert(s.clone(), idx);
+                    idx
+                }
+            };
+
+            match idx {
+                Some(idx) => {
+                    let mut buf = Vec::new();
+                    write_uint(&mut buf, idx).unwrap();
+                    Value::Ext(STRING_REF_EXT, buf)
+                }
+                None => Value::String(s),
+            }
+        }
+        Value::Array(vec) => {
+            Value::Array(vec.into_iter().map(|v| replace_strings(v, counts, indices, dict)).collect())
+        }
+        Value::Map(vec) => {
+            Value::Map(vec.into_iter()
+                .map(|(k, v)| {
+                    let k = replace_strings(k, counts, indices, dict);
+                    (k, replace_strings(v, counts, indices, dict))
+                })
+                .collect())
+        }
+        value => value,
+    }
+}
+
+fn restore_strings(value: Value, strings: &[String]) -> decode::Result<Value> {
+    match value {
+        Value::Ext(STRING_REF_EXT, buf) => {
+            let idx = match read_u64_loosely(&mut Cursor::new(&buf[..])) {
+                Ok(idx) => idx as usize,
+                Err(..) => return Err(serde::de::Error::invalid_value("invalid string reference")),
+            };
+
+            match strings.get(idx) {
+                Some(s) => Ok(Value::String(s.clone())),
+                None => Err(serde::de::Error::invalid_value(&format!("unknown string reference {}", idx))),
+            }
+        }
+        Value::Array(vec) => {
+            let mut out = Vec::with_capacity(vec.len());
+            for value in vec {
+                out.push(try!(restore_strings(value, strings)));
+            }
+
+            Ok(Value::Array(out))
+        }
+        Value::Map(vec) => {
+            let mut out = Vec::with_capacity(vec.len());
+            for (key, value) in vec {
+                out.push((try!(restore_strings(key, strings)), try!(restore_strings(value, strings))));
+            }
+
+            Ok(Value::Map(out))
+        }
+        value => Ok(value),
+    }
+}
@@ -0,0 +1,84 @@
+//! Compact IP address encoding for use with `serialize_with` and `deserialize_with`.
+//!
+//! Addresses are encoded as 4- or 16-byte binaries instead of their string form, with the length
+//! distinguishing IPv4 from IPv6.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Peer {
+//!     #[serde(serialize_with = "rmp_serde::value::ipaddr::serialize",
+//!             deserialize_with = "rmp_serde::value::ipaddr::deserialize")]
+//!     addr: IpAddr,
+//! }
+//! ```
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::result;
+
+use serde;
+use serde::bytes::ByteBuf;
+
+pub fn serialize<S>(addr: &IpAddr, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    match *addr {
+        IpAddr::V4(ref addr) => serialize_v4(addr, s),
+        IpAddr::V6(ref addr) => serialize_v6(addr, s),
+    }
+}
+
+pub fn deserialize<D>(d: &mut D) -> result::Result<IpAddr, D::Error>
+    where D: serde::Deserializer
+{
+    let buf: ByteBuf = try!(serde::Deserialize::deserialize(d));
+
+    match buf.len() {
+        4 => Ok(IpAddr::V4(Ipv4Addr::new(buf[0], buf[1], buf[2], buf[3]))),
+        16 => {
+            let mut segments = [0u16; 8];
+            for (idx, segment) in segments.iter_mut().enumerate() {
+                *segment = (buf[idx * 2] as u16) << 8 | buf[idx * 2 + 1] as u16;
+            }
+
+            Ok(IpAddr::V6(Ipv6Addr::new(segments[0], segments[1], segments[2], segments[3],
+                                        segments[4], segments[5], segments[6], segments[7])))
+        }
+        len => Err(serde::de::Error::invalid_length(len)),
+    }
+}
+
+pub fn serialize_v4<S>(addr: &Ipv4Addr, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    s.serialize_bytes(&addr.octets())
+}
+
+pub fn deserialize_v4<D>(d: &mut D) -> result::Result<Ipv4Addr, D::Error>
+    where D: serde::Deserializer
+{
+    match try!(deserialize(d)) {
+        IpAddr::V4(addr) => Ok(addr),
+        IpAddr::V6(..) => Err(serde::de::Error::invalid_length(16)),
+    }
+}
+
+pub fn serialize_v6<S>(addr: &Ipv6Addr, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    let mut buf = [0u8; 16];
+    for (idx, segment) in addr.segments().iter().enumerate() {
+        buf[idx * 2] = (segment >> 8) as u8;
+        buf[idx * 2 + 1] = *segment as u8;
+    }
+
+    s.serialize_bytes(&buf)
+}
+
+pub fn deserialize_v6<D>(d: &mut D) -> result::Result<Ipv6Addr, D::Error>
+    where D: serde::Deserializer
+{
+    match try!(deserialize(d)) {
+        IpAddr::V4(..) => Err(serde::de::Error::invalid_length(4)),
+        IpAddr::V6(addr) => Ok(addr),
+    }
+}
@@ -0,0 +1,33 @@
+//! Wire format inspection without encoding.
+
+use std::io;
+
+use rmp::{Marker, Value};
+use rmp::encode::{write_array_len, write_bin_len, write_ext_meta, write_map_len, write_sint,
+                  write_str_len, write_uint};
+use rmp::value::{Float, Integer};
+
+/// Returns the marker the given value is encoded with by `rmp::encode::write_value`.
+///
+/// The marker is selected by the same rules the encoder applies, depending on the integer value
+/// or the length of strings, binaries, arrays, maps and ext payloads, like `FixStr` for strings
+/// shorter than 32 bytes and `Str8` for longer ones.
+pub fn marker_of(value: &Value) -> Marker {
+    // The length prefixes are written into a sink, which never fails.
+    let mut wr = io::sink();
+
+    match *value {
+        Value::Nil => Marker::Null,
+        Value::Boolean(true) => Marker::True,
+        Value::Boolean(false) => Marker::False,
+        Value::Integer(Integer::U64(val)) => write_uint(&mut wr, val).unwrap(),
+        Value::Integer(Integer::I64(val)) => write_sint(&mut wr, val).unwrap(),
+        Value::Float(Float::F32(..)) => Marker::F32,
+        Value::Float(Float::F64(..)) => Marker::F64,
+        Value::String(ref val) => write_str_len(&mut wr, val.len() as u32).unwrap(),
+        Value::Binary(ref val) => write_bin_len(&mut wr, val.len() as u32).unwrap(),
+        Value::Array(ref vec) => write_array_len(&mut wr, vec.len() as u32).unwrap(),
+        Value::Map(ref vec) => write_map_len(&mut wr, vec.len() as u32).unwrap(),
+        Value::Ext(ty, ref data) => write_ext_meta(&mut wr, data.len() as u32, ty).unwrap(),
+    }
+}
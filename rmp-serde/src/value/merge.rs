@@ -0,0 +1,51 @@
+//! Merging of values, for layering user settings over defaults.
+
+use std::mem;
+
+use rmp::Value;
+
+/// Merges `overlay` over `base`, recursively for entries that are maps on both sides.
+///
+/// Entries of `base` missing from `overlay` are kept, in place, and entries only found in
+/// `overlay` are appended. Anything else, including arrays and nil, is taken from `overlay` as is.
+///
+/// # Examples
+/// ```
+/// use rmp::Value;
+/// use rmp::value::Integer;
+/// use rmp_serde::value::merge;
+///
+/// # extern crate rmp;
+/// # extern crate rmp_serde;
+/// # fn main() {
+/// let base = Value::Map(vec![
+///     (Value::String("a".into()), Value::Integer(Integer::U64(1))),
+///     (Value::String("b".into()), Value::Integer(Integer::U64(2))),
+/// ]);
+/// let overlay = Value::Map(vec![(Value::String("b".into()), Value::Integer(Integer::U64(3)))]);
+///
+/// let expected = Value::Map(vec![
+///     (Value::String("a".into()), Value::Integer(Integer::U64(1))),
+///     (Value::String("b".into()), Value::Integer(Integer::U64(3))),
+/// ]);
+/// assert_eq!(expected, merge(base, overlay));
+/// # }
+/// ```
+pub fn merge(base: Value, overlay: Value) -> Value {
+    match (base, overlay) {
+        (Value::Map(mut base), Value::Map(overlay)) => {
+            for (key, value) in overlay {
+                match base.iter().position(|&(ref k, _)| *k == key) {
+                    Some(pos) => {
+                        let prev = mem::replace(&mut base[pos].1, Value::Nil);
+                        base[pos].1 = merge(prev, value);
+                    }
+                    None => base.push((key, value)),
+                }
+            }
+
+            Value::Map(base)
+        }
+        (_, overlay) => overlay,
+    }
+}
@@ -1,4 +1,51 @@
 pub mod encode;
 pub mod decode;
-pub use self::encode::to_value;
-pub use self::decode::from_value;
+pub mod binary_str;
+pub mod bitset;
+pub mod bitvec;
+pub mod canonical;
+pub mod cow_bytes;
+pub mod diff;
+pub mod error;
+pub mod ext;
+#[cfg(feature = "half")]
+pub mod f16;
+pub mod filter;
+pub mod flatten;
+pub mod intern;
+pub mod ipaddr;
+pub mod marker;
+pub mod merge;
+pub mod path;
+#[cfg(unix)]
+pub mod pathbuf;
+pub mod prune;
+pub mod range;
+pub mod raw;
+pub mod replay;
+pub mod result;
+pub mod shape;
+pub mod systemtime;
+pub mod trace;
+pub mod unique_set;
+pub mod walk;
+#[cfg(feature = "uuid")]
+pub mod uuid;
+pub use self::encode::{SerializerOptions, StreamingSerializer, to_value, to_value_with};
+pub use self::decode::{from_value, from_value_partial, from_value_skipping_prefix, from_value_with_defaults};
+pub use self::canonical::to_canonical_vec;
+pub use self::diff::{diff, value_eq};
+pub use self::error::from_error;
+pub use self::ext::ValueExt;
+pub use self::filter::FieldFilterSerializer;
+pub use self::flatten::{flatten, unflatten};
+pub use self::intern::StringInterningSerializer;
+pub use self::marker::marker_of;
+pub use self::merge::merge;
+pub use self::path::get_path;
+pub use self::prune::prune_nils;
+pub use self::raw::RawValue;
+pub use self::replay::{Recording, RecordingSerializer};
+pub use self::shape::{Shape, validate};
+pub use self::trace::{Trace, TracingSerializer};
+pub use self::walk::walk;
@@ -0,0 +1,89 @@
+//! Extraction of nested values by path, without deserializing the whole value.
+
+use rmp::Value;
+use rmp::value::Integer;
+
+/// Returns the value found at the given path, like `a.b[0].c`, or `None` if there is none.
+///
+/// The path uses the same notation as `diff`: `.key` selects the entry of a map with the given
+/// string key, `[n]` selects the element of an array at the given index or the entry of a map
+/// with the given integer key. The leading dot is optional and the empty path selects the value
+/// itself. Malformed paths select nothing.
+pub fn get_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut value = value;
+    let mut rest = path;
+
+    while !rest.is_empty() {
+        if rest.starts_with('[') {
+            let end = match rest.find(']') {
+                Some(end) => end,
+                None => return None,
+            };
+
+            value = match index(value, &rest[1..end]) {
+                Some(value) => value,
+                None => return None,
+            };
+            rest = &rest[end + 1..];
+        } else {
+            if rest.starts_with('.') {
+                rest = &rest[1..];
+            }
+
+            let end = rest.find(|c| c == '.' || c == '[').unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+
+            value = match field(value, &rest[..end]) {
+                Some(value) => value,
+                None => return None,
+            };
+            rest = &rest[end..];
+        }
+    }
+
+    Some(value)
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match *value {
+        Value::Map(ref map) => {
+            map.iter()
+                .find(|&&(ref k, _)| match *k {
+                    Value::String(ref k) => k == key,
+                    _ => false,
+                })
+                .map(|&(_, ref v)| v)
+        }
+        _ => None,
+    }
+}
+
+fn index<'a>(value: &'a Value, idx: &str) -> Option<&'a Value> {
+    match *value {
+        Value::Array(ref vec) => {
+            match idx.parse::<usize>() {
+                Ok(idx) => vec.get(idx),
+                Err(..) => None,
+            }
+        }
+        Value::Map(ref map) => {
+            let key = match (idx.parse::<u64>(), idx.parse::<i64>()) {
+                (Ok(key), _) => Integer::U64(key),
+                (_, Ok(key)) => Integer::I64(key),
+                _ => return None,
+            };
+
+            map.iter()
+                .find(|&&(ref k, _)| match (k, key) {
+                    (&Value::Integer(Integer::U64(k)), Integer::U64(key)) => k == key,
+                    (&Value::Integer(Integer::I64(k)), Integer::U64(key)) => k >= 0 && k as u64 == key,
+                    (&Value::Integer(Integer::I64(k)), Integer::I64(key)) => k == key,
+                    _ => false,
+                })
+                .map(|&(_, ref v)| v)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,68 @@
+//! Binary encoding of paths for use with `serialize_with` and `deserialize_with`, Unix only.
+//!
+//! Paths are encoded as binaries holding their raw bytes, so that paths which are not valid UTF-8
+//! survive the round-trip, unlike with the string form serde uses. They are decoded from either
+//! form.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Entry {
+//!     #[serde(serialize_with = "rmp_serde::value::pathbuf::serialize",
+//!             deserialize_with = "rmp_serde::value::pathbuf::deserialize")]
+//!     path: PathBuf,
+//! }
+//! ```
+
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use std::result;
+
+use serde;
+
+pub fn serialize<S>(path: &PathBuf, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    s.serialize_bytes(path.as_os_str().as_bytes())
+}
+
+pub fn deserialize<D>(d: &mut D) -> result::Result<PathBuf, D::Error>
+    where D: serde::Deserializer
+{
+    d.deserialize_bytes(PathVisitor).map(|Path(path)| path)
+}
+
+/// Wraps the decoded path, as the visitor must produce a type that is `Deserialize`.
+struct Path(PathBuf);
+
+impl serde::Deserialize for Path {
+    fn deserialize<D>(d: &mut D) -> result::Result<Path, D::Error>
+        where D: serde::Deserializer
+    {
+        deserialize(d).map(Path)
+    }
+}
+
+struct PathVisitor;
+
+impl serde::de::Visitor for PathVisitor {
+    type Value = Path;
+
+    fn visit_str<E>(&mut self, v: &str) -> result::Result<Path, E>
+        where E: serde::de::Error
+    {
+        Ok(Path(PathBuf::from(v)))
+    }
+
+    fn visit_bytes<E>(&mut self, v: &[u8]) -> result::Result<Path, E>
+        where E: serde::de::Error
+    {
+        Ok(Path(PathBuf::from(OsStr::from_bytes(v))))
+    }
+
+    fn visit_byte_buf<E>(&mut self, v: Vec<u8>) -> result::Result<Path, E>
+        where E: serde::de::Error
+    {
+        Ok(Path(PathBuf::from(OsString::from_vec(v))))
+    }
+}
@@ -0,0 +1,43 @@
+//! Removal of nil values, for treating null as absent.
+
+use rmp::Value;
+
+/// Recursively removes map entries whose value is nil, descending into nested maps and arrays.
+///
+/// Nil elements of arrays are removed as well if `in_arrays` is set, otherwise they are kept to
+/// preserve element positions.
+///
+/// # Examples
+/// ```
+/// use rmp::Value;
+/// use rmp_serde::value::prune_nils;
+///
+/// # extern crate rmp;
+/// # extern crate rmp_serde;
+/// # fn main() {
+/// let value = Value::Map(vec![
+///     (Value::String("a".into()), Value::Nil),
+///     (Value::String("b".into()), Value::Array(vec![Value::Nil])),
+/// ]);
+///
+/// let expected = Value::Map(vec![(Value::String("b".into()), Value::Array(vec![]))]);
+/// assert_eq!(expected, prune_nils(value, true));
+/// # }
+/// ```
+pub fn prune_nils(value: Value, in_arrays: bool) -> Value {
+    match value {
+        Value::Array(vec) => {
+            Value::Array(vec.into_iter()
+                .filter(|v| !(in_arrays && *v == Value::Nil))
+                .map(|v| prune_nils(v, in_arrays))
+                .collect())
+        }
+        Value::Map(vec) => {
+            Value::Map(vec.into_iter()
+                .filter(|&(_, ref v)| *v != Value::Nil)
+                .map(|(k, v)| (k, prune_nils(v, in_arrays)))
+                .collect())
+        }
+        value => value,
+    }
+}
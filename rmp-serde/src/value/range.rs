@@ -0,0 +1,34 @@
+//! Range encoding for use with `serialize_with` and `deserialize_with`.
+//!
+//! Ranges are encoded as a `[start, end]` array. Both bounds are kept as is, so empty and
+//! inverted ranges like `5..2` survive the round-trip.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Selection {
+//!     #[serde(serialize_with = "rmp_serde::value::range::serialize",
+//!             deserialize_with = "rmp_serde::value::range::deserialize")]
+//!     lines: Range<usize>,
+//! }
+//! ```
+
+use std::ops::Range;
+use std::result;
+
+use serde;
+
+pub fn serialize<T, S>(range: &Range<T>, s: &mut S) -> result::Result<(), S::Error>
+    where T: serde::Serialize,
+          S: serde::Serializer
+{
+    serde::Serialize::serialize(&(&range.start, &range.end), s)
+}
+
+pub fn deserialize<T, D>(d: &mut D) -> result::Result<Range<T>, D::Error>
+    where T: serde::Deserialize,
+          D: serde::Deserializer
+{
+    let (start, end) = try!(serde::Deserialize::deserialize(d));
+
+    Ok(start..end)
+}
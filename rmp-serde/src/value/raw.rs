@@ -0,0 +1,179 @@
+//! Capturing of undecoded subtrees.
+
+use std::result;
+
+use serde;
+use serde::ser::impls::{MapIteratorVisitor, SeqIteratorVisitor};
+
+use rmp::Value;
+use rmp::value::{Float, Integer};
+
+/// Field type capturing the exact subtree it is deserialized from as a `Value`, leaving it to be
+/// handled manually.
+///
+/// This works with any deserializer, including the direct one, with the exception of ext values,
+/// which are not exposed through serde. Serializing a `RawValue` writes the subtree back as is.
+///
+/// # Examples
+/// ```ignore
+/// #[derive(Deserialize)]
+/// struct Request {
+///     method: String,
+///     params: RawValue,
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawValue(pub Value);
+
+impl RawValue {
+    /// Returns the captured value.
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+impl serde::Serialize for RawValue {
+    fn serialize<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        ValueRef(&self.0).serialize(serializer)
+    }
+}
+
+struct ValueRef<'a>(&'a Value);
+
+impl<'a> serde::Serialize for ValueRef<'a> {
+    fn serialize<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        match *self.0 {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Boolean(v) => serializer.serialize_bool(v),
+            Value::Integer(Integer::I64(v)) => serializer.serialize_i64(v),
+            Value::Integer(Integer::U64(v)) => serializer.serialize_u64(v),
+            Value::Float(Float::F32(v)) => serializer.serialize_f32(v),
+            Value::Float(Float::F64(v)) => serializer.serialize_f64(v),
+            Value::String(ref v) => serializer.serialize_str(v),
+            Value::Binary(ref v) => serializer.serialize_bytes(v),
+            Value::Array(ref vec) => {
+                serializer.serialize_seq(SeqIteratorVisitor::new(vec.iter().map(ValueRef), Some(vec.len())))
+            }
+            Value::Map(ref vec) => {
+                let iter = vec.iter().map(|&(ref k, ref v)| (ValueRef(k), ValueRef(v)));
+                serializer.serialize_map(MapIteratorVisitor::new(iter, Some(vec.len())))
+            }
+            Value::Ext(..) => Err(serde::ser::Error::invalid_value("ext values are not supported")),
+        }
+    }
+}
+
+impl serde::Deserialize for RawValue {
+    fn deserialize<D>(deserializer: &mut D) -> result::Result<RawValue, D::Error>
+        where D: serde::Deserializer
+    {
+        deserializer.deserialize(RawValueVisitor)
+    }
+}
+
+struct RawValueVisitor;
+
+impl serde::de::Visitor for RawValueVisitor {
+    type Value = RawValue;
+
+    fn visit_bool<E>(&mut self, v: bool) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Boolean(v)))
+    }
+
+    fn visit_i64<E>(&mut self, v: i64) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Integer(Integer::I64(v))))
+    }
+
+    fn visit_u64<E>(&mut self, v: u64) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Integer(Integer::U64(v))))
+    }
+
+    fn visit_f32<E>(&mut self, v: f32) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Float(Float::F32(v))))
+    }
+
+    fn visit_f64<E>(&mut self, v: f64) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Float(Float::F64(v))))
+    }
+
+    fn visit_str<E>(&mut self, v: &str) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::String(v.to_string())))
+    }
+
+    fn visit_string<E>(&mut self, v: String) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::String(v)))
+    }
+
+    fn visit_bytes<E>(&mut self, v: &[u8]) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Binary(v.to_vec())))
+    }
+
+    fn visit_byte_buf<E>(&mut self, v: Vec<u8>) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Binary(v)))
+    }
+
+    fn visit_unit<E>(&mut self) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Nil))
+    }
+
+    fn visit_none<E>(&mut self) -> result::Result<RawValue, E>
+        where E: serde::de::Error
+    {
+        Ok(RawValue(Value::Nil))
+    }
+
+    fn visit_some<D>(&mut self, deserializer: &mut D) -> result::Result<RawValue, D::Error>
+        where D: serde::Deserializer
+    {
+        serde::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<V>(&mut self, mut visitor: V) -> result::Result<RawValue, V::Error>
+        where V: serde::de::SeqVisitor
+    {
+        let mut vec = Vec::with_capacity(visitor.size_hint().0);
+        while let Some(RawValue(value)) = try!(visitor.visit()) {
+            vec.push(value);
+        }
+        try!(visitor.end());
+
+        Ok(RawValue(Value::Array(vec)))
+    }
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<RawValue, V::Error>
+        where V: serde::de::MapVisitor
+    {
+        let mut vec = Vec::with_capacity(visitor.size_hint().0);
+        while let Some(RawValue(key)) = try!(visitor.visit_key()) {
+            let RawValue(value) = try!(visitor.visit_value());
+            vec.push((key, value));
+        }
+        try!(visitor.end());
+
+        Ok(RawValue(Value::Map(vec)))
+    }
+}
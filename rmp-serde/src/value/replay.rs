@@ -0,0 +1,527 @@
+//! Capture of serialization operations to re-drive another serializer later.
+//!
+//! Unlike the `TracingSerializer`, the `RecordingSerializer` keeps everything needed to repeat
+//! the operations: primitive values, length hints, and struct and variant names. The resulting
+//! `Recording` can be replayed into any serializer, any number of times.
+//!
+//! # Examples
+//! ```
+//! use serde::Serialize;
+//! use rmp_serde::value::encode::Serializer;
+//! use rmp_serde::value::replay::RecordingSerializer;
+//!
+//! # extern crate serde;
+//! # extern crate rmp_serde;
+//! # fn main() {
+//! let mut rec = RecordingSerializer::new();
+//! (42u8, "le message").serialize(&mut rec).unwrap();
+//!
+//! let mut ser = Serializer::new();
+//! rec.unwrap().replay(&mut ser).unwrap();
+//!
+//! assert_eq!(rmp_serde::to_value(&(42u8, "le message")), ser.unwrap());
+//! # }
+//! ```
+
+use std::result;
+use std::slice;
+
+use serde;
+
+use super::encode::Error;
+
+/// Single recorded operation, along with the operations nested into it.
+#[derive(Clone, Debug, PartialEq)]
+enum Op {
+    Bool(bool),
+    Isize(isize),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Usize(usize),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(String),
+    Bytes(Vec<u8>),
+    Unit,
+    UnitStruct(&'static str),
+    UnitVariant(&'static str, usize, &'static str),
+    NewtypeStruct(&'static str, Box<Op>),
+    NewtypeVariant(&'static str, usize, &'static str, Box<Op>),
+    None,
+    Some(Box<Op>),
+    Seq(Option<usize>, Vec<Op>),
+    Tuple(Option<usize>, Vec<Op>),
+    FixedSizeArray(Option<usize>, Vec<Op>),
+    TupleStruct(&'static str, Option<usize>, Vec<Op>),
+    TupleVariant(&'static str, usize, &'static str, Option<usize>, Vec<Op>),
+    Map(Option<usize>, Vec<(Op, Op)>),
+    Struct(&'static str, Option<usize>, Vec<(&'static str, Op)>),
+    StructVariant(&'static str, usize, &'static str, Option<usize>, Vec<(&'static str, Op)>),
+}
+
+/// Operations captured by a `RecordingSerializer`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    op: Op,
+}
+
+impl Recording {
+    /// Repeats the recorded operations on the given serializer.
+    pub fn replay<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        serde::Serialize::serialize(&self.op, ser)
+    }
+}
+
+impl serde::Serialize for Recording {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        self.replay(ser)
+    }
+}
+
+/// Serializer recording the operations of a single value.
+pub struct RecordingSerializer {
+    op: Option<Op>,
+    elts: Vec<Op>,
+    entries: Vec<(Op, Op)>,
+    fields: Vec<(&'static str, Op)>,
+}
+
+impl RecordingSerializer {
+    pub fn new() -> RecordingSerializer {
+        RecordingSerializer {
+            op: None,
+            elts: Vec::new(),
+            entries: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Returns the recorded operations.
+    pub fn unwrap(self) -> Recording {
+        Recording {
+            op: self.op.expect("nothing was recorded"),
+        }
+    }
+
+    fn set(&mut self, op: Op) -> Result<(), Error> {
+        self.op = Some(op);
+        Ok(())
+    }
+
+    fn visit_seq<V>(&mut self, mut visitor: V) -> Result<Vec<Op>, Error>
+        where V: serde::ser::SeqVisitor
+    {
+        while let Some(()) = try!(visitor.visit(self)) {}
+        Ok(self.elts.drain(..).collect())
+    }
+
+    fn push_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        let op = try!(record(&value));
+        self.elts.push(op);
+        Ok(())
+    }
+
+    fn visit_fields<V>(&mut self, mut visitor: V) -> Result<Vec<(&'static str, Op)>, Error>
+        where V: serde::ser::MapVisitor
+    {
+        while let Some(()) = try!(visitor.visit(self)) {}
+        Ok(self.fields.drain(..).collect())
+    }
+
+    fn push_field<T>(&mut self, key: &'static str, value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        let op = try!(record(&value));
+        self.fields.push((key, op));
+        Ok(())
+    }
+}
+
+fn record<T>(value: &T) -> Result<Op, Error>
+    where T: serde::Serialize
+{
+    let mut rec = RecordingSerializer::new();
+    try!(value.serialize(&mut rec));
+    Ok(rec.unwrap().op)
+}
+
+macro_rules! record_fn {
+    ($name:ident, $ty:ty, $op:ident) => {
+        #[inline]
+        fn $name(&mut self, v: $ty) -> Result<(), Error> {
+            self.set(Op::$op(v))
+        }
+    }
+}
+
+impl serde::Serializer for RecordingSerializer {
+    type Error = Error;
+
+    record_fn!(serialize_bool, bool, Bool);
+    record_fn!(serialize_isize, isize, Isize);
+    record_fn!(serialize_i8, i8, I8);
+    record_fn!(serialize_i16, i16, I16);
+    record_fn!(serialize_i32, i32, I32);
+    record_fn!(serialize_i64, i64, I64);
+    record_fn!(serialize_usize, usize, Usize);
+    record_fn!(serialize_u8, u8, U8);
+    record_fn!(serialize_u16, u16, U16);
+    record_fn!(serialize_u32, u32, U32);
+    record_fn!(serialize_u64, u64, U64);
+    record_fn!(serialize_f32, f32, F32);
+    record_fn!(serialize_f64, f64, F64);
+    record_fn!(serialize_char, char, Char);
+
+    fn serialize_str(&mut self, v: &str) -> Result<(), Error> {
+        self.set(Op::Str(v.to_string()))
+    }
+
+    fn serialize_bytes(&mut self, v: &[u8]) -> Result<(), Error> {
+        self.set(Op::Bytes(v.to_vec()))
+    }
+
+    fn serialize_unit(&mut self) -> Result<(), Error> {
+        self.set(Op::Unit)
+    }
+
+    fn serialize_unit_struct(&mut self, name: &'static str) -> Result<(), Error> {
+        self.set(Op::UnitStruct(name))
+    }
+
+    fn serialize_unit_variant(&mut self,
+                              name: &'static str,
+                              variant_index: usize,
+                              variant: &'static str) -> Result<(), Error>
+    {
+        self.set(Op::UnitVariant(name, variant_index, variant))
+    }
+
+    fn serialize_newtype_struct<T>(&mut self, name: &'static str, value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        let op = try!(record(&value));
+        self.set(Op::NewtypeStruct(name, Box::new(op)))
+    }
+
+    fn serialize_newtype_variant<T>(&mut self,
+                                    name: &'static str,
+                                    variant_index: usize,
+                                    variant: &'static str,
+                                    value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        let op = try!(record(&value));
+        self.set(Op::NewtypeVariant(name, variant_index, variant, Box::new(op)))
+    }
+
+    fn serialize_none(&mut self) -> Result<(), Error> {
+        self.set(Op::None)
+    }
+
+    fn serialize_some<V>(&mut self, value: V) -> Result<(), Error>
+        where V: serde::Serialize
+    {
+        let op = try!(record(&value));
+        self.set(Op::Some(Box::new(op)))
+    }
+
+    fn serialize_seq<V>(&mut self, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor
+    {
+        let len = visitor.len();
+        let elts = try!(self.visit_seq(visitor));
+        self.set(Op::Seq(len, elts))
+    }
+
+    fn serialize_seq_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        self.push_elt(value)
+    }
+
+    fn serialize_tuple<V>(&mut self, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor
+    {
+        let len = visitor.len();
+        let elts = try!(self.visit_seq(visitor));
+        self.set(Op::Tuple(len, elts))
+    }
+
+    fn serialize_tuple_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        self.push_elt(value)
+    }
+
+    fn serialize_fixed_size_array<V>(&mut self, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor
+    {
+        let len = visitor.len();
+        let elts = try!(self.visit_seq(visitor));
+        self.set(Op::FixedSizeArray(len, elts))
+    }
+
+    fn serialize_tuple_struct<V>(&mut self, name: &'static str, visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor
+    {
+        let len = visitor.len();
+        let elts = try!(self.visit_seq(visitor));
+        self.set(Op::TupleStruct(name, len, elts))
+    }
+
+    fn serialize_tuple_struct_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        self.push_elt(value)
+    }
+
+    fn serialize_tuple_variant<V>(&mut self,
+                                  name: &'static str,
+                                  variant_index: usize,
+                                  variant: &'static str,
+                                  visitor: V) -> Result<(), Error>
+        where V: serde::ser::SeqVisitor
+    {
+        let len = visitor.len();
+        let elts = try!(self.visit_seq(visitor));
+        self.set(Op::TupleVariant(name, variant_index, variant, len, elts))
+    }
+
+    fn serialize_tuple_variant_elt<T>(&mut self, value: T) -> Result<(), Error>
+        where T: serde::Serialize
+    {
+        self.push_elt(value)
+    }
+
+    fn serialize_map<V>(&mut self, mut visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor
+    {
+        let len = visitor.len();
+        while let Some(()) = try!(visitor.visit(self)) {}
+        let entries = self.entries.drain(..).collect();
+        self.set(Op::Map(len, entries))
+    }
+
+    fn serialize_map_elt<K, V>(&mut self, key: K, value: V) -> Result<(), Error>
+        where K: serde::Serialize,
+              V: serde::Serialize
+    {
+        let key = try!(record(&key));
+        let value = try!(record(&value));
+        self.entries.push((key, value));
+        Ok(())
+    }
+
+    fn serialize_struct<V>(&mut self, name: &'static str, visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor
+    {
+        let len = visitor.len();
+        let fields = try!(self.visit_fields(visitor));
+        self.set(Op::Struct(name, len, fields))
+    }
+
+    fn serialize_struct_elt<V>(&mut self, key: &'static str, value: V) -> Result<(), Error>
+        where V: serde::Serialize
+    {
+        self.push_field(key, value)
+    }
+
+    fn serialize_struct_variant<V>(&mut self,
+                                   name: &'static str,
+                                   variant_index: usize,
+                                   variant: &'static str,
+                                   visitor: V) -> Result<(), Error>
+        where V: serde::ser::MapVisitor
+    {
+        let len = visitor.len();
+        let fields = try!(self.visit_fields(visitor));
+        self.set(Op::StructVariant(name, variant_index, variant, len, fields))
+    }
+
+    fn serialize_struct_variant_elt<V>(&mut self, key: &'static str, value: V) -> Result<(), Error>
+        where V: serde::Serialize
+    {
+        self.push_field(key, value)
+    }
+}
+
+impl serde::Serialize for Op {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        match *self {
+            Op::Bool(v) => ser.serialize_bool(v),
+            Op::Isize(v) => ser.serialize_isize(v),
+            Op::I8(v) => ser.serialize_i8(v),
+            Op::I16(v) => ser.serialize_i16(v),
+            Op::I32(v) => ser.serialize_i32(v),
+            Op::I64(v) => ser.serialize_i64(v),
+            Op::Usize(v) => ser.serialize_usize(v),
+            Op::U8(v) => ser.serialize_u8(v),
+            Op::U16(v) => ser.serialize_u16(v),
+            Op::U32(v) => ser.serialize_u32(v),
+            Op::U64(v) => ser.serialize_u64(v),
+            Op::F32(v) => ser.serialize_f32(v),
+            Op::F64(v) => ser.serialize_f64(v),
+            Op::Char(v) => ser.serialize_char(v),
+            Op::Str(ref v) => ser.serialize_str(v),
+            Op::Bytes(ref v) => ser.serialize_bytes(v),
+            Op::Unit => ser.serialize_unit(),
+            Op::UnitStruct(name) => ser.serialize_unit_struct(name),
+            Op::UnitVariant(name, idx, variant) => ser.serialize_unit_variant(name, idx, variant),
+            Op::NewtypeStruct(name, ref v) => ser.serialize_newtype_struct(name, &**v),
+            Op::NewtypeVariant(name, idx, variant, ref v) => {
+                ser.serialize_newtype_variant(name, idx, variant, &**v)
+            }
+            Op::None => ser.serialize_none(),
+            Op::Some(ref v) => ser.serialize_some(&**v),
+            Op::Seq(len, ref elts) => ser.serialize_seq(ReplaySeq::new(Elt::Seq, len, elts)),
+            Op::Tuple(len, ref elts) => ser.serialize_tuple(ReplaySeq::new(Elt::Tuple, len, elts)),
+            Op::FixedSizeArray(len, ref elts) => {
+                ser.serialize_fixed_size_array(ReplaySeq::new(Elt::Seq, len, elts))
+            }
+            Op::TupleStruct(name, len, ref elts) => {
+                ser.serialize_tuple_struct(name, ReplaySeq::new(Elt::TupleStruct, len, elts))
+            }
+            Op::TupleVariant(name, idx, variant, len, ref elts) => {
+                ser.serialize_tuple_variant(name, idx, variant, ReplaySeq::new(Elt::TupleVariant, len, elts))
+            }
+            Op::Map(len, ref entries) => {
+                ser.serialize_map(ReplayMap {
+                    len: len,
+                    iter: entries.iter(),
+                })
+            }
+            Op::Struct(name, len, ref fields) => {
+                ser.serialize_struct(name, ReplayFields::new(false, len, fields))
+            }
+            Op::StructVariant(name, idx, variant, len, ref fields) => {
+                ser.serialize_struct_variant(name, idx, variant, ReplayFields::new(true, len, fields))
+            }
+        }
+    }
+}
+
+/// The element method matching the container being replayed.
+#[derive(Clone, Copy)]
+enum Elt {
+    Seq,
+    Tuple,
+    TupleStruct,
+    TupleVariant,
+}
+
+struct ReplaySeq<'a> {
+    elt: Elt,
+    len: Option<usize>,
+    iter: slice::Iter<'a, Op>,
+}
+
+impl<'a> ReplaySeq<'a> {
+    fn new(elt: Elt, len: Option<usize>, elts: &'a [Op]) -> ReplaySeq<'a> {
+        ReplaySeq {
+            elt: elt,
+            len: len,
+            iter: elts.iter(),
+        }
+    }
+}
+
+impl<'a> serde::ser::SeqVisitor for ReplaySeq<'a> {
+    fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+        where S: serde::Serializer
+    {
+        let op = match self.iter.next() {
+            Some(op) => op,
+            None => return Ok(None),
+        };
+
+        try!(match self.elt {
+            Elt::Seq => ser.serialize_seq_elt(op),
+            Elt::Tuple => ser.serialize_tuple_elt(op),
+            Elt::TupleStruct => ser.serialize_tuple_struct_elt(op),
+            Elt::TupleVariant => ser.serialize_tuple_variant_elt(op),
+        });
+
+        Ok(Some(()))
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.len
+    }
+}
+
+struct ReplayMap<'a> {
+    len: Option<usize>,
+    iter: slice::Iter<'a, (Op, Op)>,
+}
+
+impl<'a> serde::ser::MapVisitor for ReplayMap<'a> {
+    fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+        where S: serde::Serializer
+    {
+        match self.iter.next() {
+            Some(&(ref key, ref value)) => {
+                try!(ser.serialize_map_elt(key, value));
+                Ok(Some(()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.len
+    }
+}
+
+struct ReplayFields<'a> {
+    variant: bool,
+    len: Option<usize>,
+    iter: slice::Iter<'a, (&'static str, Op)>,
+}
+
+impl<'a> ReplayFields<'a> {
+    fn new(variant: bool, len: Option<usize>, fields: &'a [(&'static str, Op)]) -> ReplayFields<'a> {
+        ReplayFields {
+            variant: variant,
+            len: len,
+            iter: fields.iter(),
+        }
+    }
+}
+
+impl<'a> serde::ser::MapVisitor for ReplayFields<'a> {
+    fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+        where S: serde::Serializer
+    {
+        let &(key, ref value) = match self.iter.next() {
+            Some(field) => field,
+            None => return Ok(None),
+        };
+
+        if self.variant {
+            try!(ser.serialize_struct_variant_elt(key, value));
+        } else {
+            try!(ser.serialize_struct_elt(key, value));
+        }
+
+        Ok(Some(()))
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.len
+    }
+}
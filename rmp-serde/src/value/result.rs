@@ -0,0 +1,65 @@
+//! Compact `Result` encoding for use with `serialize_with` and `deserialize_with`.
+//!
+//! `Ok(v)` is encoded as `{0: v}` and `Err(e)` as `{1: e}`, instead of the default variant
+//! representation.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Response {
+//!     #[serde(serialize_with = "rmp_serde::value::result::serialize",
+//!             deserialize_with = "rmp_serde::value::result::deserialize")]
+//!     result: Result<u32, String>,
+//! }
+//! ```
+
+use std::marker::PhantomData;
+use std::result;
+
+use serde;
+use serde::ser::impls::MapIteratorVisitor;
+
+const OK: u8 = 0;
+const ERR: u8 = 1;
+
+pub fn serialize<T, E, S>(value: &result::Result<T, E>, s: &mut S) -> result::Result<(), S::Error>
+    where T: serde::Serialize,
+          E: serde::Serialize,
+          S: serde::Serializer
+{
+    match *value {
+        Ok(ref v) => s.serialize_map(MapIteratorVisitor::new(Some((OK, v)).into_iter(), Some(1))),
+        Err(ref e) => s.serialize_map(MapIteratorVisitor::new(Some((ERR, e)).into_iter(), Some(1))),
+    }
+}
+
+pub fn deserialize<T, E, D>(d: &mut D) -> result::Result<result::Result<T, E>, D::Error>
+    where T: serde::Deserialize,
+          E: serde::Deserialize,
+          D: serde::Deserializer
+{
+    d.deserialize_map(ResultVisitor(PhantomData))
+}
+
+struct ResultVisitor<T, E>(PhantomData<(T, E)>);
+
+impl<T, E> serde::de::Visitor for ResultVisitor<T, E>
+    where T: serde::Deserialize,
+          E: serde::Deserialize
+{
+    type Value = result::Result<T, E>;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Self::Value, V::Error>
+        where V: serde::de::MapVisitor
+    {
+        let value = match try!(visitor.visit_key()) {
+            Some(OK) => Ok(try!(visitor.visit_value())),
+            Some(ERR) => Err(try!(visitor.visit_value())),
+            Some(..) => return Err(serde::de::Error::invalid_value("expected result tag 0 or 1")),
+            None => return Err(serde::de::Error::invalid_length(0)),
+        };
+
+        try!(visitor.end());
+
+        Ok(value)
+    }
+}
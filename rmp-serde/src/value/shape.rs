@@ -0,0 +1,73 @@
+//! Structural validation of values without a concrete Rust type.
+
+use rmp::{Marker, Value};
+
+use super::decode::{Error, Result};
+
+/// Describes the expected structure of a value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// Matches any value.
+    Any,
+    /// Matches nil.
+    Nil,
+    /// Matches a boolean.
+    Bool,
+    /// Matches an integer, either signed or unsigned.
+    Int,
+    /// Matches a floating point number of any width.
+    Float,
+    /// Matches a UTF-8 string.
+    Str,
+    /// Matches a byte array.
+    Bin,
+    /// Matches an array whose elements all match the given shape.
+    Array(Box<Shape>),
+    /// Matches a map whose keys and values match the given shapes respectively.
+    Map(Box<Shape>, Box<Shape>),
+}
+
+/// Checks that the given value matches the expected shape.
+///
+/// Returns the first mismatch found during depth-first traversal as a `TypeMismatch` error
+/// containing the marker of the expected type.
+pub fn validate(value: &Value, shape: &Shape) -> Result<()> {
+    match (shape, value) {
+        (&Shape::Any, _) => Ok(()),
+        (&Shape::Nil, &Value::Nil) => Ok(()),
+        (&Shape::Bool, &Value::Boolean(..)) => Ok(()),
+        (&Shape::Int, &Value::Integer(..)) => Ok(()),
+        (&Shape::Float, &Value::Float(..)) => Ok(()),
+        (&Shape::Str, &Value::String(..)) => Ok(()),
+        (&Shape::Bin, &Value::Binary(..)) => Ok(()),
+        (&Shape::Array(ref shape), &Value::Array(ref vec)) => {
+            for value in vec {
+                try!(validate(value, shape));
+            }
+
+            Ok(())
+        }
+        (&Shape::Map(ref key_shape, ref value_shape), &Value::Map(ref vec)) => {
+            for &(ref key, ref value) in vec {
+                try!(validate(key, key_shape));
+                try!(validate(value, value_shape));
+            }
+
+            Ok(())
+        }
+        (shape, _) => Err(Error::TypeMismatch(expected_marker(shape))),
+    }
+}
+
+fn expected_marker(shape: &Shape) -> Marker {
+    match *shape {
+        Shape::Any | Shape::Nil => Marker::Null,
+        Shape::Bool => Marker::True,
+        Shape::Int => Marker::I64,
+        Shape::Float => Marker::F64,
+        Shape::Str => Marker::Str32,
+        Shape::Bin => Marker::Bin32,
+        Shape::Array(..) => Marker::Array32,
+        Shape::Map(..) => Marker::Map32,
+    }
+}
@@ -0,0 +1,135 @@
+//! `SystemTime` encoding for use with `serialize_with` and `deserialize_with`.
+//!
+//! A time is encoded as `{"secs": s, "nanos": n}` relative to `UNIX_EPOCH`. The seconds are
+//! signed to allow times before the epoch, while the nanoseconds are always in `0..1_000_000_000`
+//! and count forward, so 1.5 seconds before the epoch is encoded as `{"secs": -2, "nanos":
+//! 500000000}`.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(serialize_with = "rmp_serde::value::systemtime::serialize",
+//!             deserialize_with = "rmp_serde::value::systemtime::deserialize")]
+//!     at: SystemTime,
+//! }
+//! ```
+
+use std::result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+pub fn serialize<S>(value: &SystemTime, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    let (secs, nanos) = match value.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs(), d.subsec_nanos()),
+        Err(err) => {
+            let d = err.duration();
+            match d.subsec_nanos() {
+                0 => (d.as_secs().wrapping_neg(), 0),
+                nanos => (d.as_secs().wrapping_neg().wrapping_sub(1), NANOS_PER_SEC - nanos),
+            }
+        }
+    };
+
+    // Reinterpreting the wrapped seconds as signed is exact unless they overflow `i64`.
+    let secs = secs as i64;
+    if (secs < 0) == (*value >= UNIX_EPOCH) {
+        return Err(serde::ser::Error::invalid_value("time is too far from the epoch"));
+    }
+
+    s.serialize_map(SystemTimeVisitor {
+        secs: secs,
+        nanos: nanos,
+        state: 0,
+    })
+}
+
+pub fn deserialize<D>(d: &mut D) -> result::Result<SystemTime, D::Error>
+    where D: serde::Deserializer
+{
+    let Time(time) = try!(serde::Deserialize::deserialize(d));
+    Ok(time)
+}
+
+struct SystemTimeVisitor {
+    secs: i64,
+    nanos: u32,
+    state: u8,
+}
+
+impl serde::ser::MapVisitor for SystemTimeVisitor {
+    fn visit<S>(&mut self, s: &mut S) -> result::Result<Option<()>, S::Error>
+        where S: serde::Serializer
+    {
+        self.state += 1;
+        match self.state {
+            1 => s.serialize_map_elt("secs", self.secs).map(Some),
+            2 => s.serialize_map_elt("nanos", self.nanos).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(2)
+    }
+}
+
+/// Deserializable wrapper, as visitors may only produce deserializable values.
+struct Time(SystemTime);
+
+impl serde::Deserialize for Time {
+    fn deserialize<D>(d: &mut D) -> result::Result<Time, D::Error>
+        where D: serde::Deserializer
+    {
+        d.deserialize_map(TimeVisitor)
+    }
+}
+
+struct TimeVisitor;
+
+impl serde::de::Visitor for TimeVisitor {
+    type Value = Time;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Time, V::Error>
+        where V: serde::de::MapVisitor
+    {
+        let mut secs = None;
+        let mut nanos = None;
+
+        while let Some(key) = try!(visitor.visit_key::<String>()) {
+            match &key[..] {
+                "secs" => secs = Some(try!(visitor.visit_value::<i64>())),
+                "nanos" => nanos = Some(try!(visitor.visit_value::<u32>())),
+                _ => return Err(serde::de::Error::unknown_field(&key)),
+            }
+        }
+
+        try!(visitor.end());
+
+        let secs = match secs {
+            Some(secs) => secs,
+            None => return Err(serde::de::Error::missing_field("secs")),
+        };
+        let nanos = match nanos {
+            Some(nanos) if nanos < NANOS_PER_SEC => nanos,
+            Some(..) => return Err(serde::de::Error::invalid_value("nanos must be less than 1000000000")),
+            None => return Err(serde::de::Error::missing_field("nanos")),
+        };
+
+        let time = if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::new(secs.wrapping_neg() as u64, 0))
+                .and_then(|time| time.checked_add(Duration::new(0, nanos)))
+        };
+
+        match time {
+            Some(time) => Ok(Time(time)),
+            None => Err(serde::de::Error::invalid_value("time is out of range")),
+        }
+    }
+}
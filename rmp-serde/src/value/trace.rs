@@ -0,0 +1,324 @@
+//! Debugging aid that records the operations emitted while serializing.
+//!
+//! The `TracingSerializer` decorates another serializer, like the value `Serializer`, forwarding
+//! every call to it unchanged and appending a human-readable record of the call to a `Trace`.
+//!
+//! # Examples
+//! ```
+//! use serde::Serialize;
+//! use rmp_serde::value::encode::Serializer;
+//! use rmp_serde::value::trace::{Trace, TracingSerializer};
+//!
+//! # extern crate serde;
+//! # extern crate rmp_serde;
+//! # fn main() {
+//! let trace = Trace::new();
+//! let mut ser = Serializer::new();
+//!
+//! (5u8, "x").serialize(&mut TracingSerializer::new(&mut ser, &trace)).unwrap();
+//!
+//! assert_eq!(vec!["serialize_tuple(2)", "serialize_tuple_elt", "serialize_u8(5)",
+//!                 "serialize_tuple_elt", "serialize_str(\"x\")"], trace.ops());
+//! # }
+//! ```
+
+use std::cell::RefCell;
+use std::result;
+
+use serde;
+
+/// Accumulates the operations recorded by a `TracingSerializer`.
+pub struct Trace {
+    ops: RefCell<Vec<String>>,
+}
+
+impl Trace {
+    pub fn new() -> Trace {
+        Trace {
+            ops: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns the operations recorded so far, in call order.
+    pub fn ops(&self) -> Vec<String> {
+        self.ops.borrow().clone()
+    }
+
+    fn push(&self, op: String) {
+        self.ops.borrow_mut().push(op);
+    }
+}
+
+/// Serializer decorator recording every operation into a `Trace`.
+pub struct TracingSerializer<'a, S: 'a> {
+    ser: &'a mut S,
+    trace: &'a Trace,
+}
+
+impl<'a, S: 'a> TracingSerializer<'a, S> {
+    pub fn new(ser: &'a mut S, trace: &'a Trace) -> TracingSerializer<'a, S> {
+        TracingSerializer {
+            ser: ser,
+            trace: trace,
+        }
+    }
+}
+
+fn len(len: Option<usize>) -> String {
+    match len {
+        Some(len) => len.to_string(),
+        None => "_".to_string(),
+    }
+}
+
+macro_rules! trace_fn {
+    ($name:ident, $ty:ty) => {
+        #[inline]
+        fn $name(&mut self, v: $ty) -> result::Result<(), S::Error> {
+            self.trace.push(format!("{}({:?})", stringify!($name), v));
+            self.ser.$name(v)
+        }
+    }
+}
+
+impl<'a, S> serde::Serializer for TracingSerializer<'a, S>
+    where S: serde::Serializer
+{
+    type Error = S::Error;
+
+    trace_fn!(serialize_bool, bool);
+    trace_fn!(serialize_isize, isize);
+    trace_fn!(serialize_i8, i8);
+    trace_fn!(serialize_i16, i16);
+    trace_fn!(serialize_i32, i32);
+    trace_fn!(serialize_i64, i64);
+    trace_fn!(serialize_usize, usize);
+    trace_fn!(serialize_u8, u8);
+    trace_fn!(serialize_u16, u16);
+    trace_fn!(serialize_u32, u32);
+    trace_fn!(serialize_u64, u64);
+    trace_fn!(serialize_f32, f32);
+    trace_fn!(serialize_f64, f64);
+    trace_fn!(serialize_char, char);
+    trace_fn!(serialize_str, &str);
+    trace_fn!(serialize_bytes, &[u8]);
+
+    fn serialize_unit(&mut self) -> result::Result<(), S::Error> {
+        self.trace.push("serialize_unit".to_string());
+        self.ser.serialize_unit()
+    }
+
+    fn serialize_unit_struct(&mut self, name: &'static str) -> result::Result<(), S::Error> {
+        self.trace.push(format!("serialize_unit_struct({})", name));
+        self.ser.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(&mut self,
+                              name: &'static str,
+                              variant_index: usize,
+                              variant: &'static str) -> result::Result<(), S::Error>
+    {
+        self.trace.push(format!("serialize_unit_variant({}, {}, {})", name, variant_index, variant));
+        self.ser.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(&mut self, name: &'static str, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.trace.push(format!("serialize_newtype_struct({})", name));
+        self.ser.serialize_newtype_struct(name, Traced::new(value, self.trace))
+    }
+
+    fn serialize_newtype_variant<T>(&mut self,
+                                    name: &'static str,
+                                    variant_index: usize,
+                                    variant: &'static str,
+                                    value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.trace.push(format!("serialize_newtype_variant({}, {}, {})", name, variant_index, variant));
+        self.ser.serialize_newtype_variant(name, variant_index, variant, Traced::new(value, self.trace))
+    }
+
+    fn serialize_none(&mut self) -> result::Result<(), S::Error> {
+        self.trace.push("serialize_none".to_string());
+        self.ser.serialize_none()
+    }
+
+    fn serialize_some<V>(&mut self, value: V) -> result::Result<(), S::Error>
+        where V: serde::Serialize
+    {
+        self.trace.push("serialize_some".to_string());
+        self.ser.serialize_some(Traced::new(value, self.trace))
+    }
+
+    fn serialize_seq<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.trace.push(format!("serialize_seq({})", len(visitor.len())));
+        self.ser.serialize_seq(Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_seq_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.trace.push("serialize_seq_elt".to_string());
+        self.ser.serialize_seq_elt(Traced::new(value, self.trace))
+    }
+
+    fn serialize_tuple<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.trace.push(format!("serialize_tuple({})", len(visitor.len())));
+        self.ser.serialize_tuple(Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_tuple_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.trace.push("serialize_tuple_elt".to_string());
+        self.ser.serialize_tuple_elt(Traced::new(value, self.trace))
+    }
+
+    fn serialize_fixed_size_array<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.trace.push(format!("serialize_fixed_size_array({})", len(visitor.len())));
+        self.ser.serialize_fixed_size_array(Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_tuple_struct<V>(&mut self, name: &'static str, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.trace.push(format!("serialize_tuple_struct({}, {})", name, len(visitor.len())));
+        self.ser.serialize_tuple_struct(name, Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_tuple_struct_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.trace.push("serialize_tuple_struct_elt".to_string());
+        self.ser.serialize_tuple_struct_elt(Traced::new(value, self.trace))
+    }
+
+    fn serialize_tuple_variant<V>(&mut self,
+                                  name: &'static str,
+                                  variant_index: usize,
+                                  variant: &'static str,
+                                  visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::SeqVisitor
+    {
+        self.trace.push(format!("serialize_tuple_variant({}, {}, {}, {})",
+                                name, variant_index, variant, len(visitor.len())));
+        self.ser.serialize_tuple_variant(name, variant_index, variant, Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_tuple_variant_elt<T>(&mut self, value: T) -> result::Result<(), S::Error>
+        where T: serde::Serialize
+    {
+        self.trace.push("serialize_tuple_variant_elt".to_string());
+        self.ser.serialize_tuple_variant_elt(Traced::new(value, self.trace))
+    }
+
+    fn serialize_map<V>(&mut self, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::MapVisitor
+    {
+        self.trace.push(format!("serialize_map({})", len(visitor.len())));
+        self.ser.serialize_map(Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_map_elt<K, V>(&mut self, key: K, value: V) -> result::Result<(), S::Error>
+        where K: serde::Serialize,
+              V: serde::Serialize
+    {
+        self.trace.push("serialize_map_elt".to_string());
+        self.ser.serialize_map_elt(Traced::new(key, self.trace), Traced::new(value, self.trace))
+    }
+
+    fn serialize_struct<V>(&mut self, name: &'static str, visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::MapVisitor
+    {
+        self.trace.push(format!("serialize_struct({}, {})", name, len(visitor.len())));
+        self.ser.serialize_struct(name, Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_struct_elt<V>(&mut self, key: &'static str, value: V) -> result::Result<(), S::Error>
+        where V: serde::Serialize
+    {
+        self.trace.push(format!("serialize_struct_elt({})", key));
+        self.ser.serialize_struct_elt(key, Traced::new(value, self.trace))
+    }
+
+    fn serialize_struct_variant<V>(&mut self,
+                                   name: &'static str,
+                                   variant_index: usize,
+                                   variant: &'static str,
+                                   visitor: V) -> result::Result<(), S::Error>
+        where V: serde::ser::MapVisitor
+    {
+        self.trace.push(format!("serialize_struct_variant({}, {}, {}, {})",
+                                name, variant_index, variant, len(visitor.len())));
+        self.ser.serialize_struct_variant(name, variant_index, variant, Traced::new(visitor, self.trace))
+    }
+
+    fn serialize_struct_variant_elt<V>(&mut self, key: &'static str, value: V) -> result::Result<(), S::Error>
+        where V: serde::Serialize
+    {
+        self.trace.push(format!("serialize_struct_variant_elt({})", key));
+        self.ser.serialize_struct_variant_elt(key, Traced::new(value, self.trace))
+    }
+}
+
+/// Wraps values and visitors handed to the underlying serializer, so that nested calls are
+/// traced too.
+struct Traced<'a, T> {
+    inner: T,
+    trace: &'a Trace,
+}
+
+impl<'a, T> Traced<'a, T> {
+    fn new(inner: T, trace: &'a Trace) -> Traced<'a, T> {
+        Traced {
+            inner: inner,
+            trace: trace,
+        }
+    }
+}
+
+impl<'a, T> serde::Serialize for Traced<'a, T>
+    where T: serde::Serialize
+{
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        self.inner.serialize(&mut TracingSerializer::new(ser, self.trace))
+    }
+}
+
+impl<'a, V> serde::ser::SeqVisitor for Traced<'a, V>
+    where V: serde::ser::SeqVisitor
+{
+    fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+        where S: serde::Serializer
+    {
+        self.inner.visit(&mut TracingSerializer::new(ser, self.trace))
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.inner.len()
+    }
+}
+
+impl<'a, V> serde::ser::MapVisitor for Traced<'a, V>
+    where V: serde::ser::MapVisitor
+{
+    fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+        where S: serde::Serializer
+    {
+        self.inner.visit(&mut TracingSerializer::new(ser, self.trace))
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.inner.len()
+    }
+}
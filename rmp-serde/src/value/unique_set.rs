@@ -0,0 +1,71 @@
+//! Set decoding rejecting duplicates, for use with `serialize_with` and `deserialize_with`.
+//!
+//! Sets like `HashSet` silently drop duplicate elements when decoded from an array, since serde
+//! decodes them the same way as `Vec` and the `Deserializer` has no way to tell them apart. With
+//! `deserialize`, arrays containing the same element twice are rejected instead.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Group {
+//!     #[serde(serialize_with = "rmp_serde::value::unique_set::serialize",
+//!             deserialize_with = "rmp_serde::value::unique_set::deserialize")]
+//!     members: HashSet<String>,
+//! }
+//! ```
+
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::result;
+
+use serde;
+
+/// Insertion into a set, telling whether the element was already present.
+pub trait Set: Default {
+    type Item;
+
+    /// Returns `false` if the set already contained the element.
+    fn insert(&mut self, item: Self::Item) -> bool;
+}
+
+impl<T, H> Set for HashSet<T, H>
+    where T: Eq + Hash,
+          H: BuildHasher + Default
+{
+    type Item = T;
+
+    fn insert(&mut self, item: T) -> bool {
+        HashSet::insert(self, item)
+    }
+}
+
+impl<T: Ord> Set for BTreeSet<T> {
+    type Item = T;
+
+    fn insert(&mut self, item: T) -> bool {
+        BTreeSet::insert(self, item)
+    }
+}
+
+pub fn serialize<T, S>(set: &T, s: &mut S) -> result::Result<(), S::Error>
+    where T: serde::Serialize,
+          S: serde::Serializer
+{
+    set.serialize(s)
+}
+
+pub fn deserialize<T, D>(d: &mut D) -> result::Result<T, D::Error>
+    where T: Set,
+          T::Item: serde::Deserialize,
+          D: serde::Deserializer
+{
+    let items: Vec<T::Item> = try!(serde::Deserialize::deserialize(d));
+
+    let mut set = T::default();
+    for (idx, item) in items.into_iter().enumerate() {
+        if !set.insert(item) {
+            return Err(serde::de::Error::invalid_value(&format!("duplicate set element at index {}", idx)));
+        }
+    }
+
+    Ok(set)
+}
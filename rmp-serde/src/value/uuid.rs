@@ -0,0 +1,36 @@
+//! Compact UUID encoding for use with `serialize_with` and `deserialize_with`.
+//!
+//! UUIDs are encoded as 16-byte binaries instead of their 36-byte string form.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Session {
+//!     #[serde(serialize_with = "rmp_serde::value::uuid::serialize",
+//!             deserialize_with = "rmp_serde::value::uuid::deserialize")]
+//!     id: Uuid,
+//! }
+//! ```
+
+use std::result;
+
+use serde;
+use serde::bytes::ByteBuf;
+
+use uuid::Uuid;
+
+pub fn serialize<S>(uuid: &Uuid, s: &mut S) -> result::Result<(), S::Error>
+    where S: serde::Serializer
+{
+    s.serialize_bytes(uuid.as_bytes())
+}
+
+pub fn deserialize<D>(d: &mut D) -> result::Result<Uuid, D::Error>
+    where D: serde::Deserializer
+{
+    let buf: ByteBuf = try!(serde::Deserialize::deserialize(d));
+
+    match Uuid::from_bytes(&buf) {
+        Ok(uuid) => Ok(uuid),
+        Err(..) => Err(serde::de::Error::invalid_length(buf.len())),
+    }
+}
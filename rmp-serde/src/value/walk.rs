@@ -0,0 +1,44 @@
+//! Depth-first traversal of values.
+
+use rmp::Value;
+
+/// Calls `f` with every node of the given value along with its path, like `a.b[0]`, parents
+/// before their children, starting with the value itself at the empty path.
+///
+/// Paths use the notation of `get_path`, without the leading dot: `key` or `.key` for string map
+/// keys, `[key]` for other map keys and array indices.
+pub fn walk<F>(value: &Value, f: &mut F)
+    where F: FnMut(&str, &Value)
+{
+    walk_at(&mut String::new(), value, f);
+}
+
+fn walk_at<F>(path: &mut String, value: &Value, f: &mut F)
+    where F: FnMut(&str, &Value)
+{
+    f(path, value);
+
+    match *value {
+        Value::Array(ref vec) => {
+            for (idx, value) in vec.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", idx));
+                walk_at(path, value, f);
+                path.truncate(len);
+            }
+        }
+        Value::Map(ref map) => {
+            for &(ref key, ref value) in map.iter() {
+                let len = path.len();
+                match *key {
+                    Value::String(ref key) if path.is_empty() => path.push_str(key),
+                    Value::String(ref key) => path.push_str(&format!(".{}", key)),
+                    ref key => path.push_str(&format!("[{}]", key)),
+                }
+                walk_at(path, value, f);
+                path.truncate(len);
+            }
+        }
+        _ => (),
+    }
+}
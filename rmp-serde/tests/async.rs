@@ -0,0 +1,89 @@
+#![cfg(feature = "tokio")]
+
+extern crate futures;
+extern crate tokio_io;
+extern crate rmp_serde;
+
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+use futures::{Async, Future};
+use tokio_io::AsyncRead;
+
+use rmp_serde::decode::{Error, from_async_read};
+
+/// In-memory reader delivering its chunks one at a time, pretending it would block in between.
+struct ChunkedReader {
+    chunks: VecDeque<Vec<u8>>,
+    ready: bool,
+}
+
+impl ChunkedReader {
+    fn new(chunks: Vec<Vec<u8>>) -> ChunkedReader {
+        ChunkedReader {
+            chunks: chunks.into_iter().collect(),
+            ready: false,
+        }
+    }
+}
+
+impl Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.ready {
+            self.ready = true;
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        self.ready = false;
+        match self.chunks.pop_front() {
+            Some(chunk) => {
+                buf[..chunk.len()].copy_from_slice(&chunk);
+                Ok(chunk.len())
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl AsyncRead for ChunkedReader {}
+
+#[test]
+fn pass_from_async_read_in_chunks() {
+    // The encoded bytearray is: [42, 'le message'], split in three chunks.
+    let mut rd = ChunkedReader::new(vec![
+        vec![0x92, 0x2a],
+        vec![0xaa, 0x6c, 0x65, 0x20, 0x6d],
+        vec![0x65, 0x73, 0x73, 0x61, 0x67, 0x65],
+    ]);
+
+    let mut future = from_async_read::<_, (u32, String)>(&mut rd);
+
+    let mut pending = 0;
+    let actual = loop {
+        match future.poll().unwrap() {
+            Async::Ready(value) => break value,
+            Async::NotReady => pending += 1,
+        }
+    };
+
+    assert_eq!((42, "le message".to_string()), actual);
+    assert_eq!(3, pending);
+}
+
+#[test]
+fn fail_from_async_read_truncated() {
+    let mut rd = ChunkedReader::new(vec![vec![0x92, 0x2a]]);
+    let mut future = from_async_read::<_, (u32, u32)>(&mut rd);
+
+    let res = loop {
+        match future.poll() {
+            Ok(Async::NotReady) => continue,
+            res => break res,
+        }
+    };
+
+    match res.err() {
+        Some(Error::InvalidDataRead(..)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
@@ -0,0 +1,22 @@
+#![cfg(feature = "bytes")]
+
+extern crate bytes;
+extern crate serde;
+extern crate rmp_serde;
+
+use bytes::BytesMut;
+
+use rmp_serde::encode::to_buf;
+
+#[test]
+fn pass_to_buf_reused() {
+    let mut buf = BytesMut::with_capacity(64);
+
+    to_buf(&mut buf, &(42u8, "le message")).unwrap();
+    assert_eq!(&[0x92, 0x2a, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65][..], &buf[..]);
+
+    buf.clear();
+
+    to_buf(&mut buf, &vec![1u8, 2, 3]).unwrap();
+    assert_eq!(&[0x93, 0x01, 0x02, 0x03][..], &buf[..]);
+}
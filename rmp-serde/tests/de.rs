@@ -232,6 +232,43 @@ fn fail_tuple_len_mismatch() {
     }
 }
 
+#[test]
+fn pass_tuple_variant_args() {
+    use serde::de::VariantVisitor as Visitor;
+    use serde::de::impls::TupleVisitor2;
+    use rmp_serde::decode::VariantVisitor;
+
+    // The encoded variant is: 1, [42, -1].
+    let buf = [0x01, 0x92, 0x2a, 0xff];
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+
+    let mut visitor = VariantVisitor::new(&mut deserializer);
+    let id: usize = visitor.visit_variant().unwrap();
+    let args = visitor.visit_tuple(2, TupleVisitor2::<i32, i32>::new()).unwrap();
+
+    assert_eq!(1, id);
+    assert_eq!((42, -1), args);
+}
+
+#[test]
+fn fail_tuple_variant_args_len_mismatch() {
+    use serde::de::VariantVisitor as Visitor;
+    use serde::de::impls::TupleVisitor2;
+    use rmp_serde::decode::VariantVisitor;
+
+    // The encoded variant is: 1, [42, -1, 0].
+    let buf = [0x01, 0x93, 0x2a, 0xff, 0x00];
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+
+    let mut visitor = VariantVisitor::new(&mut deserializer);
+    let _: usize = visitor.visit_variant().unwrap();
+
+    match visitor.visit_tuple(2, TupleVisitor2::<i32, i32>::new()).err() {
+        Some(Error::LengthMismatch(3)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
 #[test]
 fn pass_option_some() {
     let buf = [0x1f];
@@ -339,3 +376,465 @@ fn pass_bin32_into_bytebuf() {
 
     assert_eq!(vec![0xcc, 0x80], actual);
 }
+
+#[test]
+fn pass_stream_decoder_byte_by_byte() {
+    use rmp::Value;
+    use rmp::value::Integer;
+    use rmp_serde::decode::StreamDecoder;
+
+    // [42, "le message"], {1: nil}, true
+    let buf = [
+        0x92, 0x2a, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65,
+        0x81, 0x01, 0xc0,
+        0xc3,
+    ];
+
+    let mut decoder = StreamDecoder::new();
+    let mut values = Vec::new();
+
+    for byte in buf.iter() {
+        decoder.feed(&[*byte]);
+        while let Some(value) = decoder.next_value().unwrap() {
+            values.push(value);
+        }
+    }
+
+    assert_eq!(vec![
+        Value::Array(vec![Value::Integer(Integer::U64(42)), Value::String("le message".to_string())]),
+        Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::Nil)]),
+        Value::Boolean(true),
+    ], values);
+    assert_eq!(0, decoder.buffered());
+}
+
+#[test]
+fn pass_stream_decoder_value_split_across_feeds() {
+    use rmp_serde::decode::StreamDecoder;
+
+    let mut decoder = StreamDecoder::new();
+
+    decoder.feed(&[0xcd, 0x01]);
+    assert!(decoder.next_value().unwrap().is_none());
+    assert_eq!(2, decoder.buffered());
+
+    decoder.feed(&[0x02, 0xc2]);
+    assert!(decoder.next_value().unwrap().is_some());
+    assert!(decoder.next_value().unwrap().is_some());
+    assert!(decoder.next_value().unwrap().is_none());
+}
+
+#[test]
+fn pass_stream_decoder_long_headers_byte_by_byte() {
+    use rmp::Value;
+    use rmp::value::Integer;
+    use rmp_serde::decode::StreamDecoder;
+
+    // Array16 of [Str8 "le message", Bin16 [0xcc], Ext8 (1, [0x2a]), Map16 {}], then U32 5.
+    let buf = [
+        0xdc, 0x00, 0x04,
+        0xd9, 0x0a, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65,
+        0xc5, 0x00, 0x01, 0xcc,
+        0xc7, 0x01, 0x01, 0x2a,
+        0xde, 0x00, 0x00,
+        0xce, 0x00, 0x00, 0x00, 0x05,
+    ];
+
+    let mut decoder = StreamDecoder::new();
+    let mut values = Vec::new();
+
+    for byte in buf.iter() {
+        decoder.feed(&[*byte]);
+        while let Some(value) = decoder.next_value().unwrap() {
+            values.push(value);
+        }
+    }
+
+    assert_eq!(vec![
+        Value::Array(vec![
+            Value::String("le message".to_string()),
+            Value::Binary(vec![0xcc]),
+            Value::Ext(1, vec![0x2a]),
+            Value::Map(vec![]),
+        ]),
+        Value::Integer(Integer::U64(5)),
+    ], values);
+    assert_eq!(0, decoder.buffered());
+}
+
+#[test]
+fn fail_stream_decoder_invalid_utf8() {
+    use rmp_serde::decode::StreamDecoder;
+
+    let mut decoder = StreamDecoder::new();
+    decoder.feed(&[0xa2, 0xc3, 0x28]);
+
+    match decoder.next_value().err() {
+        Some(Error::InvalidUtf8(..)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_stream_decoder_deeply_nested() {
+    use rmp_serde::decode::StreamDecoder;
+
+    // [[[...[nil]...]]], nested far deeper than the stack allows to decode recursively.
+    let mut buf = vec![0x91; 100000];
+    buf.push(0xc0);
+
+    let mut decoder = StreamDecoder::new();
+    decoder.feed(&buf);
+
+    match decoder.next_value().err() {
+        Some(Error::DepthLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_stream_decoder_max_depth_same_as_deserializer() {
+    use rmp_serde::decode::StreamDecoder;
+
+    // [[1]]
+    let buf = [0x91, 0x91, 0x01];
+
+    let mut decoder = StreamDecoder::new();
+    decoder.set_max_depth(3);
+    decoder.feed(&buf);
+    assert!(decoder.next_value().unwrap().is_some());
+
+    let mut deserializer = Deserializer::new(&buf[..]);
+    deserializer.set_max_depth(3);
+    let actual: Vec<Vec<u8>> = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!(vec![vec![1]], actual);
+
+    // [[[1]]]
+    let buf = [0x91, 0x91, 0x91, 0x01];
+
+    let mut decoder = StreamDecoder::new();
+    decoder.set_max_depth(3);
+    decoder.feed(&buf);
+    match decoder.next_value().err() {
+        Some(Error::DepthLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let mut deserializer = Deserializer::new(&buf[..]);
+    deserializer.set_max_depth(3);
+    let actual: Result<Vec<Vec<Vec<u8>>>> = Deserialize::deserialize(&mut deserializer);
+    match actual.err() {
+        Some(Error::DepthLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_stream_decoder_reserved_marker() {
+    use rmp_serde::decode::StreamDecoder;
+
+    let mut decoder = StreamDecoder::new();
+    decoder.feed(&[0xc1]);
+
+    match decoder.next_value().err() {
+        Some(Error::TypeMismatch(Marker::Reserved)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_within_max_total_bytes() {
+    // The encoded bytearray is: ['le message', 'le message'].
+    let mut buf = vec![0x92];
+    for _ in 0..2 {
+        buf.extend_from_slice(&[0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65]);
+    }
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_total_bytes(22);
+
+    let actual: Vec<String> = Deserialize::deserialize(&mut deserializer).unwrap();
+
+    assert_eq!(vec!["le message".to_string(), "le message".to_string()], actual);
+}
+
+#[test]
+fn fail_cumulative_size_exceeds_max_total_bytes() {
+    // The encoded bytearray is: ['le message', 'le message'].
+    let mut buf = vec![0x92];
+    for _ in 0..2 {
+        buf.extend_from_slice(&[0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65]);
+    }
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_total_bytes(21);
+
+    let actual: Result<Vec<String>> = Deserialize::deserialize(&mut deserializer);
+
+    match actual.err() {
+        Some(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_claimed_length_exceeds_max_total_bytes() {
+    // A bin32 claiming 4GB of data, which is never allocated.
+    let buf = [0xc6, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_total_bytes(1024);
+
+    let actual: Result<serde::bytes::ByteBuf> = Deserialize::deserialize(&mut deserializer);
+
+    match actual.err() {
+        Some(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_option_vec_of_units() {
+    // Nested nils must not be mistaken for the nil of the outer option.
+    let buf = [0x92, 0xc0, 0xc0];
+    let mut deserializer = Deserializer::new(&buf[..]);
+
+    let actual: Option<Vec<()>> = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!(Some(vec![(), ()]), actual);
+}
+
+#[test]
+fn pass_option_vec_from_empty_array() {
+    let buf = [0x90];
+    let mut deserializer = Deserializer::new(&buf[..]);
+
+    let actual: Option<Vec<String>> = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!(Some(vec![]), actual);
+}
+
+#[test]
+fn fail_str_invalid_utf8() {
+    let buf = [0xa2, 0xc3, 0x28];
+    let mut deserializer = Deserializer::new(&buf[..]);
+
+    let actual: Result<String> = Deserialize::deserialize(&mut deserializer);
+    match actual.err().unwrap() {
+        Error::Uncategorized(ref msg) if msg == "InvalidUtf8" => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+/// Keeps the bytes of the string it is deserialized from, without interpreting them.
+struct StrBytes(Vec<u8>);
+
+impl Deserialize for StrBytes {
+    fn deserialize<D>(de: &mut D) -> result::Result<StrBytes, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = StrBytes;
+
+            fn visit_str<E>(&mut self, v: &str) -> result::Result<StrBytes, E>
+                where E: serde::de::Error
+            {
+                Ok(StrBytes(v.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E>(&mut self, v: &[u8]) -> result::Result<StrBytes, E>
+                where E: serde::de::Error
+            {
+                Ok(StrBytes(v.to_vec()))
+            }
+        }
+
+        de.deserialize_str(Visitor)
+    }
+}
+
+#[test]
+fn pass_str_without_utf8_validation() {
+    let buf = [0xa2, 0xc3, 0x28];
+    let mut deserializer = Deserializer::new(&buf[..]);
+    deserializer.set_validate_utf8(false);
+
+    // The bytes are passed on as is, without being checked.
+    let StrBytes(actual) = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!(vec![0xc3, 0x28], actual);
+
+    let mut deserializer = Deserializer::new(&buf[..]);
+    let actual: Result<StrBytes> = Deserialize::deserialize(&mut deserializer);
+    match actual.err() {
+        Some(Error::Uncategorized(ref msg)) if msg == "InvalidUtf8" => (),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    let buf = [0xa4, 0x6c, 0x65, 0x20, 0x6d];
+    let mut deserializer = Deserializer::new(&buf[..]);
+    deserializer.set_validate_utf8(false);
+
+    let actual: String = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!("le m", actual);
+}
+
+#[test]
+fn fail_string_from_invalid_utf8_without_validation() {
+    let buf = [0xa2, 0xc3, 0x28];
+    let mut deserializer = Deserializer::new(&buf[..]);
+    deserializer.set_validate_utf8(false);
+
+    // Strings still check the bytes they are handed.
+    let actual: Result<String> = Deserialize::deserialize(&mut deserializer);
+    match actual.err() {
+        Some(Error::TypeMismatch(Marker::Str32)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_within_max_str_len() {
+    let buf = [0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65];
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_str_len(10);
+
+    let actual: String = Deserialize::deserialize(&mut deserializer).unwrap();
+
+    assert_eq!("le message", actual);
+}
+
+#[test]
+fn fail_str_exceeds_max_str_len() {
+    // A str32 claiming 4GB of data, which is never allocated.
+    let buf = [0xdb, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_str_len(1024);
+
+    let actual: Result<String> = Deserialize::deserialize(&mut deserializer);
+
+    match actual.err() {
+        Some(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_bin_exceeds_max_bin_len() {
+    let buf = [0xc4, 0x03, 0xcc, 0x80, 0x01];
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_bin_len(2);
+
+    let actual: Result<serde::bytes::ByteBuf> = Deserialize::deserialize(&mut deserializer);
+
+    match actual.err() {
+        Some(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    // The string limit does not apply to binaries.
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_str_len(2);
+
+    let actual: serde::bytes::ByteBuf = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!(vec![0xcc, 0x80, 0x01], actual.to_vec());
+}
+
+#[test]
+fn fail_array_exceeds_max_seq_len() {
+    // An array claiming 2^32 - 1 elements, of which only three are present.
+    let buf = [0xdd, 0xff, 0xff, 0xff, 0xff, 0x01, 0x02, 0x03];
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_seq_len(2);
+
+    let actual: Result<Vec<u8>> = Deserialize::deserialize(&mut deserializer);
+
+    match actual.err() {
+        Some(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    // Only the two elements within the limit have been read.
+    assert_eq!(7, deserializer.get_ref().position());
+}
+
+#[test]
+fn pass_array_within_max_seq_len() {
+    let buf = [0x92, 0x01, 0x02];
+
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+    deserializer.set_max_seq_len(2);
+
+    let actual: Vec<u8> = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!(vec![1, 2], actual);
+}
+
+/// Implements only `visit_bytes`, borrowing the binary.
+#[derive(Debug, PartialEq)]
+struct BorrowedBytes(Vec<u8>);
+
+impl Deserialize for BorrowedBytes {
+    fn deserialize<D>(de: &mut D) -> result::Result<BorrowedBytes, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = BorrowedBytes;
+
+            fn visit_bytes<E>(&mut self, v: &[u8]) -> result::Result<BorrowedBytes, E>
+                where E: serde::de::Error
+            {
+                Ok(BorrowedBytes(v.to_vec()))
+            }
+        }
+
+        de.deserialize_bytes(Visitor)
+    }
+}
+
+/// Implements only `visit_byte_buf`, taking ownership of the binary.
+#[derive(Debug, PartialEq)]
+struct OwnedBytes(Vec<u8>);
+
+impl Deserialize for OwnedBytes {
+    fn deserialize<D>(de: &mut D) -> result::Result<OwnedBytes, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = OwnedBytes;
+
+            fn visit_byte_buf<E>(&mut self, v: Vec<u8>) -> result::Result<OwnedBytes, E>
+                where E: serde::de::Error
+            {
+                Ok(OwnedBytes(v))
+            }
+        }
+
+        de.deserialize_bytes(Visitor)
+    }
+}
+
+#[test]
+fn pass_bin_to_borrowing_visitor() {
+    let buf = [0xc4, 0x02, 0xcc, 0x80];
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+
+    let actual: BorrowedBytes = Deserialize::deserialize(&mut deserializer).unwrap();
+    assert_eq!(BorrowedBytes(vec![0xcc, 0x80]), actual);
+}
+
+#[test]
+fn fail_bin_to_owning_visitor() {
+    // Binaries are lent from the internal buffer, which owning visitors can't take.
+    let buf = [0xc4, 0x02, 0xcc, 0x80];
+    let mut deserializer = Deserializer::new(Cursor::new(&buf[..]));
+
+    let actual: Result<OwnedBytes> = Deserialize::deserialize(&mut deserializer);
+    assert!(actual.is_err());
+}
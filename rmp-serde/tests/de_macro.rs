@@ -131,6 +131,27 @@ fn pass_tuple_enum_with_args() {
     assert_eq!(5, de.get_ref().position())
 }
 
+#[test]
+fn fail_tuple_enum_args_len_mismatch() {
+    // The encoded bytearray is: [1, [42]].
+    let buf = [0x92, 0x01, 0x91, 0x2a];
+    let cur = Cursor::new(&buf[..]);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Op {
+        Neg(i32),
+        Add(i32, i32),
+    }
+
+    let mut de = Deserializer::new(cur);
+    let actual: Result<Op> = Deserialize::deserialize(&mut de);
+
+    match actual.err().unwrap() {
+        Error::LengthMismatch(1) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
 #[test]
 fn fail_enum_sequence_mismatch() {
     // The encoded bytearray is: [1, 2, 100500].
@@ -192,6 +213,25 @@ fn pass_struct_enum_with_arg() {
     assert_eq!(4, de.get_ref().position())
 }
 
+#[test]
+fn pass_struct_enum_with_named_args() {
+    // The encoded bytearray is: [1, {'w': 3, 'h': 4}].
+    let buf = [0x92, 0x01, 0x82, 0xa1, 0x77, 0x03, 0xa1, 0x68, 0x04];
+    let cur = Cursor::new(&buf[..]);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Circle { r: u32 },
+        Rect { w: u32, h: u32 },
+    }
+
+    let mut de = Deserializer::new(cur);
+    let actual: Shape = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(Shape::Rect { w: 3, h: 4 }, actual);
+    assert_eq!(9, de.get_ref().position())
+}
+
 #[test]
 fn pass_enum_with_nested_struct() {
     // The encoded bytearray is: [0, [['le message']]].
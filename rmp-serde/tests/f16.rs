@@ -0,0 +1,58 @@
+#![cfg(feature = "half")]
+
+extern crate serde;
+extern crate rmp;
+extern crate rmp_serde;
+extern crate half;
+
+use half::f16;
+
+use rmp::Value;
+use rmp::value::Float;
+use rmp_serde::value::decode::Deserializer;
+use rmp_serde::value::encode::Serializer;
+use rmp_serde::value::f16::{deserialize, serialize};
+
+fn round_trip(value: f16) -> f16 {
+    let mut ser = Serializer::new();
+    serialize(&value, &mut ser).unwrap();
+
+    let encoded = ser.unwrap();
+    match encoded {
+        Value::Float(Float::F32(..)) => (),
+        ref other => panic!("unexpected value: {:?}", other)
+    }
+
+    deserialize(&mut Deserializer::new(encoded)).unwrap()
+}
+
+#[test]
+fn pass_f16_round_trip() {
+    for value in &[0.0, -0.0, 1.0, -2.5, 65504.0] {
+        let value = f16::from_f32(*value);
+        assert_eq!(value.to_bits(), round_trip(value).to_bits());
+    }
+}
+
+#[test]
+fn pass_f16_subnormal_round_trip() {
+    // The smallest positive subnormal and the largest subnormal.
+    for bits in &[0x0001, 0x03ff, 0x8001] {
+        let value = f16::from_bits(*bits);
+        assert_eq!(*bits, round_trip(value).to_bits());
+    }
+}
+
+#[test]
+fn pass_f16_infinity_round_trip() {
+    assert_eq!(f16::INFINITY, round_trip(f16::INFINITY));
+    assert_eq!(f16::NEG_INFINITY, round_trip(f16::NEG_INFINITY));
+    assert!(round_trip(f16::NAN).is_nan());
+}
+
+#[test]
+fn pass_f16_from_f64() {
+    let value = deserialize(&mut Deserializer::new(Value::Float(Float::F64(0.5)))).unwrap();
+
+    assert_eq!(f16::from_f32(0.5), value);
+}
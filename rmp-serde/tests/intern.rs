@@ -0,0 +1,135 @@
+extern crate serde;
+extern crate rmp;
+extern crate rmp_serde;
+
+use std::collections::BTreeMap;
+
+use rmp::Value;
+use rmp::encode::write_value;
+use rmp_serde::value::intern::{STRING_REF_EXT, from_interned_value, to_interned_value};
+use rmp_serde::value::to_value;
+
+fn records() -> Vec<BTreeMap<String, String>> {
+    (0..100).map(|i| {
+        let mut record = BTreeMap::new();
+        record.insert("hostname".to_string(), "frontend.example.com".to_string());
+        record.insert("severity".to_string(), if i % 2 == 0 { "warning" } else { "error" }.to_string());
+        record.insert("message".to_string(), format!("request #{} failed", i));
+        record
+    }).collect()
+}
+
+enum Level {
+    Warning,
+    Custom(String),
+}
+
+impl serde::Serialize for Level {
+    fn serialize<S>(&self, ser: &mut S) -> Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        match *self {
+            Level::Warning => ser.serialize_unit_variant("Level", 0, "Warning"),
+            Level::Custom(ref name) => ser.serialize_newtype_variant("Level", 1, "Custom", name),
+        }
+    }
+}
+
+fn encoded_len(value: &Value) -> usize {
+    let mut buf = Vec::new();
+    write_value(&mut buf, value).unwrap();
+    buf.len()
+}
+
+#[test]
+fn pass_interned_round_trip() {
+    let expected = records();
+
+    let actual: Vec<BTreeMap<String, String>> =
+        from_interned_value(to_interned_value(&expected).unwrap()).unwrap();
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn pass_interned_envelope_layout() {
+    let value = to_interned_value(&vec!["unique", "repeated twice", "repeated twice"]).unwrap();
+
+    let expected = Value::Array(vec![
+        Value::Array(vec![Value::String("repeated twice".to_string())]),
+        Value::Array(vec![
+            Value::String("unique".to_string()),
+            Value::Ext(STRING_REF_EXT, vec![0x00]),
+            Value::Ext(STRING_REF_EXT, vec![0x00]),
+        ]),
+    ]);
+
+    assert_eq!(expected, value);
+}
+
+#[test]
+fn pass_interned_is_smaller() {
+    let records = records();
+
+    let plain = encoded_len(&to_value(&records));
+    let interned = encoded_len(&to_interned_value(&records).unwrap());
+
+    assert!(interned * 2 < plain, "interned: {}, plain: {}", interned, plain);
+}
+
+#[test]
+fn pass_interned_keeps_short_strings() {
+    // A reference takes 3 bytes, more than the 2 bytes of a one-character string.
+    let value = to_interned_value(&vec!["a", "a", "a", "twice", "twice"]).unwrap();
+
+    let expected = Value::Array(vec![
+        Value::Array(vec![]),
+        Value::Array(vec![
+            Value::String("a".to_string()),
+            Value::String("a".to_string()),
+            Value::String("a".to_string()),
+            Value::String("twice".to_string()),
+            Value::String("twice".to_string()),
+        ]),
+    ]);
+
+    assert_eq!(expected, value);
+}
+
+#[test]
+fn pass_interned_enum_same_as_plain() {
+    use rmp_serde::value::intern::intern;
+
+    let value = vec![
+        Level::Warning,
+        Level::Custom("repeated twice".to_string()),
+        Level::Custom("repeated twice".to_string()),
+    ];
+
+    assert_eq!(intern(to_value(&value)), to_interned_value(&value).unwrap());
+}
+
+#[test]
+fn fail_interned_serialize_error() {
+    struct Failing;
+
+    impl serde::Serialize for Failing {
+        fn serialize<S>(&self, _ser: &mut S) -> Result<(), S::Error>
+            where S: serde::Serializer
+        {
+            Err(serde::ser::Error::custom("le error"))
+        }
+    }
+
+    assert!(to_interned_value(&vec![Failing]).is_err());
+}
+
+#[test]
+fn fail_interned_unknown_reference() {
+    let value = Value::Array(vec![
+        Value::Array(vec![]),
+        Value::Ext(STRING_REF_EXT, vec![0x01]),
+    ]);
+
+    assert!(from_interned_value::<String>(value).is_err());
+}
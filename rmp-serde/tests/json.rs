@@ -0,0 +1,157 @@
+extern crate serde;
+extern crate serde_json;
+extern crate rmp;
+extern crate rmp_serde;
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use rmp_serde::{Deserializer, Serializer};
+use rmp_serde::decode::from_slice;
+
+#[test]
+fn pass_json_value_from_slice() {
+    // {"id": 42, "name": "le message", "tags": [-1, 0.5, nil, true]}
+    let buf = [
+        0x83,
+        0xa2, 0x69, 0x64, 0x2a,
+        0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65,
+        0xa4, 0x74, 0x61, 0x67, 0x73, 0x94, 0xff, 0xcb, 0x3f, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0xc3,
+    ];
+
+    let mut expected = BTreeMap::new();
+    expected.insert("id".to_string(), Value::U64(42));
+    expected.insert("name".to_string(), Value::String("le message".to_string()));
+    expected.insert("tags".to_string(), Value::Array(vec![
+        Value::I64(-1),
+        Value::F64(0.5),
+        Value::Null,
+        Value::Bool(true),
+    ]));
+
+    assert_eq!(Value::Object(expected), from_slice::<Value>(&buf).unwrap());
+}
+
+#[test]
+fn pass_json_value_from_bin_as_seq() {
+    let buf = [0xc4, 0x02, 0xcc, 0x80];
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.set_bin_as_seq(true);
+
+    let actual: Value = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(Value::Array(vec![Value::U64(0xcc), Value::U64(0x80)]), actual);
+}
+
+#[test]
+fn fail_json_value_from_bin() {
+    let buf = [0xc4, 0x02, 0xcc, 0x80];
+
+    assert!(from_slice::<Value>(&buf).is_err());
+}
+
+#[test]
+fn pass_json_value_from_ext_as_map() {
+    // FixExt2 of type 42.
+    let buf = [0xd5, 0x2a, 0xcc, 0x80];
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.set_bin_as_seq(true);
+    de.set_ext_as_map(true);
+
+    let mut expected = BTreeMap::new();
+    expected.insert("type".to_string(), Value::U64(42));
+    expected.insert("data".to_string(), Value::Array(vec![Value::U64(0xcc), Value::U64(0x80)]));
+
+    let actual: Value = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(Value::Object(expected), actual);
+}
+
+#[test]
+fn fail_json_value_from_ext() {
+    use rmp_serde::decode::Error;
+
+    let buf = [0xd5, 0x2a, 0xcc, 0x80];
+
+    match from_slice::<Value>(&buf) {
+        Err(Error::TypeMismatch(..)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+/// Encodes the given JSON value with the direct `Serializer`.
+fn to_msgpack(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.serialize(&mut Serializer::new(&mut buf)).unwrap();
+    buf
+}
+
+#[test]
+fn pass_json_value_to_vec_markers() {
+    assert_eq!(vec![0xc0], to_msgpack(&Value::Null));
+    assert_eq!(vec![0xc3], to_msgpack(&Value::Bool(true)));
+    assert_eq!(vec![0xc2], to_msgpack(&Value::Bool(false)));
+
+    // Integers use the most compact marker, whether they are modelled as `U64` or `I64`.
+    assert_eq!(vec![0x2a], to_msgpack(&Value::U64(42)));
+    assert_eq!(vec![0x2a], to_msgpack(&Value::I64(42)));
+    assert_eq!(vec![0xcd, 0x01, 0x2c], to_msgpack(&Value::U64(300)));
+    assert_eq!(vec![0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], to_msgpack(&Value::U64(u64::max_value())));
+    assert_eq!(vec![0xff], to_msgpack(&Value::I64(-1)));
+    assert_eq!(vec![0xd1, 0xff, 0x38], to_msgpack(&Value::I64(-200)));
+    assert_eq!(vec![0xd3, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], to_msgpack(&Value::I64(i64::min_value())));
+
+    // Floats stay 64-bit, even whole-valued ones.
+    assert_eq!(vec![0xcb, 0x3f, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], to_msgpack(&Value::F64(0.5)));
+    assert_eq!(vec![0xcb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], to_msgpack(&Value::F64(1.0)));
+
+    assert_eq!(vec![0xa2, 0x6c, 0x65], to_msgpack(&Value::String("le".to_string())));
+    assert_eq!(vec![0x92, 0x01, 0xc0], to_msgpack(&Value::Array(vec![Value::U64(1), Value::Null])));
+
+    let mut object = BTreeMap::new();
+    object.insert("id".to_string(), Value::U64(42));
+    assert_eq!(vec![0x81, 0xa2, 0x69, 0x64, 0x2a], to_msgpack(&Value::Object(object)));
+}
+
+#[test]
+fn pass_json_value_round_trip() {
+    let mut expected = BTreeMap::new();
+    expected.insert("id".to_string(), Value::U64(42));
+    expected.insert("name".to_string(), Value::String("le message".to_string()));
+    expected.insert("tags".to_string(), Value::Array(vec![
+        Value::I64(-1),
+        Value::F64(0.5),
+        Value::Null,
+        Value::Bool(true),
+    ]));
+    let expected = Value::Object(expected);
+
+    assert_eq!(expected, from_slice::<Value>(&to_msgpack(&expected)).unwrap());
+}
+
+#[test]
+fn pass_json_value_to_value() {
+    use rmp::Value as MsgValue;
+    use rmp::value::{Float, Integer};
+    use rmp_serde::value::to_value;
+
+    let json = Value::Array(vec![
+        Value::Null,
+        Value::Bool(true),
+        Value::U64(42),
+        Value::I64(-1),
+        Value::F64(0.5),
+        Value::String("le message".to_string()),
+    ]);
+
+    assert_eq!(MsgValue::Array(vec![
+        MsgValue::Nil,
+        MsgValue::Boolean(true),
+        MsgValue::Integer(Integer::U64(42)),
+        MsgValue::Integer(Integer::I64(-1)),
+        MsgValue::Float(Float::F64(0.5)),
+        MsgValue::String("le message".to_string()),
+    ]), to_value(&json));
+}
@@ -70,3 +70,91 @@ fn round_trip_option_cow() {
 
     assert_eq!(expected, actual);
 }
+
+#[test]
+fn round_trip_struct_variant() {
+    use std::io::Cursor;
+    use serde::Serialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Circle { r: u32 },
+        Rect { w: u32, h: u32 },
+    }
+
+    let expected = Shape::Rect { w: 3, h: 4 };
+
+    let mut data = vec![];
+    expected.serialize(&mut rmp_serde::encode::Serializer::new(&mut data)).unwrap();
+
+    let mut de = rmp_serde::decode::Deserializer::new(Cursor::new(&data[..]));
+    let actual: Shape = serde::Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn round_trip_tuple_variant() {
+    use std::io::Cursor;
+    use serde::Serialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Op {
+        Neg(i32),
+        Add(i32, i32),
+    }
+
+    let expected = Op::Add(42, -1);
+
+    let mut data = vec![];
+    expected.serialize(&mut rmp_serde::encode::Serializer::new(&mut data)).unwrap();
+
+    let mut de = rmp_serde::decode::Deserializer::new(Cursor::new(&data[..]));
+    let actual: Op = serde::Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn round_trip_zero_field_struct() {
+    use std::io::Cursor;
+    use serde::Serialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Empty {}
+
+    let expected = Empty {};
+
+    let mut data = vec![];
+    expected.serialize(&mut rmp_serde::encode::Serializer::new(&mut data)).unwrap();
+
+    let mut de = rmp_serde::decode::Deserializer::new(Cursor::new(&data[..]));
+    let actual: Empty = serde::Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(expected, actual);
+    assert_eq!(expected, rmp_serde::from_value(rmp_serde::to_value(&expected)).unwrap());
+}
+
+#[test]
+fn round_trip_phantom_data() {
+    use std::io::Cursor;
+    use std::marker::PhantomData;
+    use serde::Serialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Foo {
+        id: u32,
+        marker: PhantomData<String>,
+    }
+
+    let expected = Foo { id: 42, marker: PhantomData };
+
+    let mut data = vec![];
+    expected.serialize(&mut rmp_serde::encode::Serializer::new(&mut data)).unwrap();
+
+    let mut de = rmp_serde::decode::Deserializer::new(Cursor::new(&data[..]));
+    let actual: Foo = serde::Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(expected, actual);
+    assert_eq!(expected, rmp_serde::from_value(rmp_serde::to_value(&expected)).unwrap());
+}
@@ -301,3 +301,168 @@ fn pass_bin() {
 
     assert_eq!(vec![0xc4, 0x02, 0xcc, 0x80], buf);
 }
+
+#[test]
+fn pass_bin_from_read_large() {
+    use serde::Deserialize;
+    use serde::bytes::ByteBuf;
+
+    let data: Vec<u8> = (0..10 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+    let mut buf = Vec::new();
+    Serializer::new(&mut buf).serialize_bin_from_read(&mut Cursor::new(&data[..]), data.len() as u32).unwrap();
+
+    assert_eq!([0xc6, 0x00, 0xa0, 0x00, 0x00], buf[..5]);
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    let actual: ByteBuf = Deserialize::deserialize(&mut de).unwrap();
+
+    assert!(data == &actual[..]);
+}
+
+#[test]
+fn pass_bin_from_read_takes_len_bytes() {
+    let mut rd = Cursor::new(vec![0xcc, 0x80, 0x01]);
+
+    let mut buf = Vec::new();
+    Serializer::new(&mut buf).serialize_bin_from_read(&mut rd, 2).unwrap();
+
+    assert_eq!(vec![0xc4, 0x02, 0xcc, 0x80], buf);
+    assert_eq!(2, rd.position());
+}
+
+#[test]
+fn fail_bin_from_read_short() {
+    let mut buf = Vec::new();
+
+    match Serializer::new(&mut buf).serialize_bin_from_read(&mut Cursor::new(vec![0xcc, 0x80]), 3) {
+        Err(Error::InvalidDataRead(ref err)) => assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind()),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_to_vec_bounded_fits() {
+    use rmp_serde::encode::to_vec_bounded;
+
+    let buf = to_vec_bounded(&vec![1u8, 2, 3], 4).unwrap();
+
+    assert_eq!(vec![0x93, 0x01, 0x02, 0x03], buf);
+}
+
+#[test]
+fn fail_to_vec_bounded_exceeds() {
+    use rmp_serde::encode::to_vec_bounded;
+
+    match to_vec_bounded(&vec![1u8, 2, 3], 3) {
+        Err(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_to_vec_bounded_stops_early() {
+    use std::cell::Cell;
+    use serde::Serializer as SerdeSerializer;
+    use serde::ser::impls::SeqIteratorVisitor;
+    use rmp_serde::encode::to_vec_bounded;
+
+    /// Counts the elements pulled out of a sequence of a million elements.
+    struct Counted<'a>(&'a Cell<usize>);
+
+    impl<'a> Serialize for Counted<'a> {
+        fn serialize<S>(&self, ser: &mut S) -> Result<(), S::Error>
+            where S: SerdeSerializer
+        {
+            let count = self.0;
+            let iter = (0..1000000u32).map(|v| {
+                count.set(count.get() + 1);
+                v
+            });
+
+            ser.serialize_seq(SeqIteratorVisitor::new(iter, Some(1000000)))
+        }
+    }
+
+    let count = Cell::new(0);
+
+    match to_vec_bounded(&Counted(&count), 16) {
+        Err(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // The array header takes 5 bytes, leaving room for 11 single-byte elements.
+    assert_eq!(12, count.get());
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn fail_seq_length_overflow() {
+    use std::iter;
+    use serde::Serializer as SerdeSerializer;
+    use serde::ser::impls::SeqIteratorVisitor;
+
+    /// Claims more elements than an array length can hold, without producing any.
+    struct Huge;
+
+    impl Serialize for Huge {
+        fn serialize<S>(&self, ser: &mut S) -> Result<(), S::Error>
+            where S: SerdeSerializer
+        {
+            let len = u32::max_value() as usize + 1;
+            ser.serialize_seq(SeqIteratorVisitor::new(iter::empty::<u8>(), Some(len)))
+        }
+    }
+
+    let mut buf = Vec::new();
+
+    match Huge.serialize(&mut Serializer::new(&mut buf)) {
+        Err(Error::LengthOverflow(4294967296)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    assert!(buf.is_empty());
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn fail_map_length_overflow() {
+    use std::iter;
+    use serde::Serializer as SerdeSerializer;
+    use serde::ser::impls::MapIteratorVisitor;
+
+    /// Claims more entries than a map length can hold, without producing any.
+    struct Huge;
+
+    impl Serialize for Huge {
+        fn serialize<S>(&self, ser: &mut S) -> Result<(), S::Error>
+            where S: SerdeSerializer
+        {
+            let len = u32::max_value() as usize + 1;
+            ser.serialize_map(MapIteratorVisitor::new(iter::empty::<(u8, u8)>(), Some(len)))
+        }
+    }
+
+    let mut buf = Vec::new();
+
+    match Huge.serialize(&mut Serializer::new(&mut buf)) {
+        Err(Error::LengthOverflow(4294967296)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    assert!(buf.is_empty());
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+#[ignore] // Allocates 4 GiB.
+fn fail_str_length_overflow() {
+    use std::io;
+
+    let val = String::from_utf8(vec![b'a'; u32::max_value() as usize + 1]).unwrap();
+
+    match val.serialize(&mut Serializer::new(&mut io::sink())) {
+        Err(Error::LengthOverflow(4294967296)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
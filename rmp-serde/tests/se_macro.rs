@@ -123,6 +123,25 @@ fn pass_tuple_enum_with_arg() {
     assert_eq!(out, buf);
 }
 
+#[test]
+fn pass_struct_enum_with_args() {
+    // We encode struct variants as [id, {field: value...}].
+    #[allow(unused)]
+    #[derive(Debug, PartialEq, Serialize)]
+    enum Shape {
+        Circle { r: u32 },
+        Rect { w: u32, h: u32 },
+    }
+
+    let mut buf = Vec::new();
+
+    let val = Shape::Rect { w: 3, h: 4 };
+    val.serialize(&mut Serializer::new(&mut buf)).ok().unwrap();
+
+    let out = vec![0x92, 0x01, 0x82, 0xa1, 0x77, 0x03, 0xa1, 0x68, 0x04];
+    assert_eq!(out, buf);
+}
+
 #[test]
 fn encode_struct_with_string_using_vec() {
     #[derive(Debug, PartialEq, Serialize)]
@@ -1,4 +1,5 @@
 extern crate serde;
+extern crate rmp;
 extern crate rmp_serde;
 
 use std::io::Cursor;
@@ -43,3 +44,115 @@ fn pass_deserializer_into_inner() {
 
     assert_eq!(1, cur.position());
 }
+
+#[test]
+fn pass_framed_round_trip() {
+    use rmp_serde::decode::read_framed;
+    use rmp_serde::encode::write_framed;
+
+    let mut buf = Vec::new();
+    write_framed(&mut buf, &(42u8, "le message")).unwrap();
+    write_framed(&mut buf, &vec![1, 2, 3]).unwrap();
+
+    assert_eq!([0x00, 0x00, 0x00, 0x0d, 0x92, 0x2a, 0xaa], buf[..7]);
+
+    let mut cur = Cursor::new(&buf[..]);
+    assert_eq!((42u8, "le message".to_string()), read_framed(&mut cur).unwrap());
+    assert_eq!(vec![1, 2, 3], read_framed::<_, Vec<u32>>(&mut cur).unwrap());
+    assert_eq!(buf.len() as u64, cur.position());
+}
+
+#[test]
+fn fail_framed_too_long() {
+    use rmp_serde::decode::{Error, read_framed_with_max};
+
+    let buf = [0x00, 0x00, 0x01, 0x00, 0xc0];
+    let mut cur = Cursor::new(&buf[..]);
+
+    match read_framed_with_max::<_, ()>(&mut cur, 255) {
+        Err(Error::SizeLimitExceeded) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // The frame contents are left unread.
+    assert_eq!(4, cur.position());
+}
+
+#[test]
+fn fail_framed_short_read() {
+    use rmp::decode::ReadError;
+    use rmp_serde::decode::{Error, read_framed};
+
+    match read_framed::<_, ()>(&mut Cursor::new(&[0x00, 0x00][..])) {
+        Err(Error::InvalidMarkerRead(ReadError::UnexpectedEOF)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    match read_framed::<_, Vec<u8>>(&mut Cursor::new(&[0x00, 0x00, 0x00, 0x03, 0x92, 0x01][..])) {
+        Err(Error::InvalidDataRead(ReadError::UnexpectedEOF)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_from_slice_exact() {
+    use rmp_serde::decode::from_slice_exact;
+
+    let buf = [0x92, 0x2a, 0xc3];
+
+    assert_eq!((42u8, true), from_slice_exact(&buf).unwrap());
+}
+
+#[test]
+fn fail_from_slice_exact_trailing_bytes() {
+    use rmp_serde::decode::{Error, from_slice_exact};
+
+    let buf = [0x92, 0x2a, 0xc3, 0xde, 0xad];
+
+    match from_slice_exact::<(u8, bool)>(&buf) {
+        Err(Error::TrailingBytes(2)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_cow_round_trip() {
+    use std::borrow::Cow;
+    use serde::Serialize;
+
+    fn encode<T: ?Sized + Serialize>(value: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        value.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+        buf
+    }
+
+    let s = "le message";
+    for cow in [Cow::Borrowed(s), Cow::Owned(s.to_string())].iter() {
+        let buf = encode(cow);
+        assert_eq!(encode(s), buf);
+
+        let mut de = Deserializer::new(Cursor::new(&buf[..]));
+        let actual: Cow<str> = Deserialize::deserialize(&mut de).unwrap();
+        assert_eq!(*cow, actual);
+    }
+
+    let bytes = [1u8, 2];
+    for cow in [Cow::Borrowed(&bytes[..]), Cow::Owned(bytes.to_vec())].iter() {
+        let buf = encode(cow);
+        assert_eq!(vec![0x92, 0x01, 0x02], buf);
+
+        let mut de = Deserializer::new(Cursor::new(&buf[..]));
+        let actual: Cow<[u8]> = Deserialize::deserialize(&mut de).unwrap();
+        assert_eq!(*cow, actual);
+    }
+
+    let words = ["a".to_string(), "b".to_string()];
+    for cow in [Cow::Borrowed(&words[..]), Cow::Owned(words.to_vec())].iter() {
+        let buf = encode(cow);
+        assert_eq!(vec![0x92, 0xa1, 0x61, 0xa1, 0x62], buf);
+
+        let mut de = Deserializer::new(Cursor::new(&buf[..]));
+        let actual: Cow<[String]> = Deserialize::deserialize(&mut de).unwrap();
+        assert_eq!(*cow, actual);
+    }
+}
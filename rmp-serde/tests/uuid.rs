@@ -0,0 +1,39 @@
+#![cfg(feature = "uuid")]
+
+extern crate serde;
+extern crate rmp;
+extern crate rmp_serde;
+extern crate uuid;
+
+use rmp::Value;
+use rmp_serde::value::decode::Deserializer;
+use rmp_serde::value::encode::Serializer;
+use rmp_serde::value::uuid::{deserialize, serialize};
+use uuid::Uuid;
+
+fn round_trip(uuid: &Uuid) -> Uuid {
+    let mut ser = Serializer::new();
+    serialize(uuid, &mut ser).unwrap();
+
+    let value = ser.unwrap();
+    assert_eq!(Value::Binary(uuid.as_bytes().to_vec()), value);
+
+    deserialize(&mut Deserializer::new(value)).unwrap()
+}
+
+#[test]
+fn pass_nil_uuid_round_trip() {
+    assert_eq!(Uuid::nil(), round_trip(&Uuid::nil()));
+}
+
+#[test]
+fn pass_uuid_round_trip() {
+    let uuid = Uuid::parse_str("936da01f-9abd-4d9d-80c7-02af85c822a8").unwrap();
+
+    assert_eq!(uuid, round_trip(&uuid));
+}
+
+#[test]
+fn fail_uuid_from_short_binary() {
+    assert!(deserialize(&mut Deserializer::new(Value::Binary(vec![0; 15]))).is_err());
+}
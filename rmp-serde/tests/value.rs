@@ -0,0 +1,4078 @@
+extern crate serde;
+extern crate rmp;
+extern crate rmp_serde;
+
+use std::collections::HashMap;
+use std::result;
+
+use serde::Deserialize;
+
+use rmp::{Marker, Value};
+use rmp::value::Integer;
+use rmp_serde::value::{Shape, ValueExt, validate};
+use rmp_serde::value::decode::{Deserializer, Error};
+
+type Result<T> = result::Result<T, Error>;
+
+#[test]
+fn pass_tuple_from_map_with_integer_keys() {
+    let value = Value::Map(vec![
+        (Value::Integer(Integer::U64(2)), Value::String("le message".to_string())),
+        (Value::Integer(Integer::U64(0)), Value::Integer(Integer::U64(42))),
+        (Value::Integer(Integer::U64(1)), Value::Boolean(true)),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.set_tuple_from_map(true);
+
+    let actual: (u32, bool, String) = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!((42, true, "le message".to_string()), actual);
+}
+
+#[test]
+fn fail_tuple_from_map_missing_index() {
+    let value = Value::Map(vec![
+        (Value::Integer(Integer::U64(0)), Value::Integer(Integer::U64(42))),
+        (Value::Integer(Integer::U64(2)), Value::Boolean(true)),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.set_tuple_from_map(true);
+
+    let res: Result<(u32, bool, bool)> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(..)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_tuple_from_map_when_disabled() {
+    let value = Value::Map(vec![
+        (Value::Integer(Integer::U64(0)), Value::Integer(Integer::U64(42))),
+    ]);
+
+    let mut de = Deserializer::new(value);
+
+    let res: Result<(u32,)> = Deserialize::deserialize(&mut de);
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_validate_matching_shape() {
+    let value = Value::Map(vec![
+        (Value::String("ids".to_string()), Value::Array(vec![
+            Value::Integer(Integer::U64(1)),
+            Value::Integer(Integer::I64(-2)),
+        ])),
+        (Value::String("tags".to_string()), Value::Array(vec![])),
+    ]);
+
+    let shape = Shape::Map(Box::new(Shape::Str), Box::new(Shape::Array(Box::new(Shape::Int))));
+
+    validate(&value, &shape).unwrap();
+    validate(&value, &Shape::Any).unwrap();
+}
+
+#[test]
+fn fail_validate_mismatched_nested_shape() {
+    let value = Value::Map(vec![
+        (Value::String("ids".to_string()), Value::Array(vec![
+            Value::Integer(Integer::U64(1)),
+            Value::String("2".to_string()),
+        ])),
+    ]);
+
+    let shape = Shape::Map(Box::new(Shape::Str), Box::new(Shape::Array(Box::new(Shape::Int))));
+
+    match validate(&value, &shape).err() {
+        Some(Error::TypeMismatch(Marker::I64)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_validate_mismatched_container() {
+    let value = Value::Array(vec![Value::Nil]);
+
+    match validate(&value, &Shape::Map(Box::new(Shape::Any), Box::new(Shape::Any))).err() {
+        Some(Error::TypeMismatch(Marker::Map32)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_into_array() {
+    let value = Value::Array(vec![Value::Nil, Value::Boolean(true)]);
+
+    assert_eq!(vec![Value::Nil, Value::Boolean(true)], value.into_array().unwrap());
+}
+
+#[test]
+fn fail_into_array_from_map() {
+    match Value::Map(vec![]).into_array().err() {
+        Some(Error::TypeMismatch(Marker::Array32)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_into_map() {
+    let value = Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::Nil)]);
+
+    assert_eq!(vec![(Value::Integer(Integer::U64(1)), Value::Nil)], value.into_map().unwrap());
+}
+
+#[test]
+fn fail_into_map_from_array() {
+    match Value::Array(vec![]).into_map().err() {
+        Some(Error::TypeMismatch(Marker::Map32)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_into_btree_map_with_mixed_keys() {
+    use std::io::Cursor;
+    use rmp::decode::read_value;
+    use rmp::encode::write_value;
+
+    let value = Value::Map(vec![
+        (Value::String("key".to_string()), Value::Integer(Integer::U64(1))),
+        (Value::Integer(Integer::I64(-1)), Value::Boolean(true)),
+        (Value::Nil, Value::Array(vec![])),
+        (Value::Array(vec![Value::Nil]), Value::String("value".to_string())),
+    ]);
+
+    let mut buf = Vec::new();
+    write_value(&mut buf, &value).unwrap();
+    let map = read_value(&mut Cursor::new(&buf[..])).unwrap().into_btree_map().unwrap();
+
+    assert_eq!(4, map.len());
+    assert_eq!(Some(&Value::Integer(Integer::U64(1))), map.get(&Value::String("key".to_string())));
+    assert_eq!(Some(&Value::Boolean(true)), map.get(&Value::Integer(Integer::I64(-1))));
+    assert_eq!(Some(&Value::String("value".to_string())), map.get(&Value::Array(vec![Value::Nil])));
+
+    let keys: Vec<&Value> = map.keys().collect();
+    assert_eq!(&Value::Nil, keys[0]);
+}
+
+#[test]
+fn pass_unit_from_nil() {
+    let actual: () = Deserialize::deserialize(&mut Deserializer::new(Value::Nil)).unwrap();
+
+    assert_eq!((), actual);
+}
+
+#[test]
+fn pass_unit_from_empty_array() {
+    let actual: () = Deserialize::deserialize(&mut Deserializer::new(Value::Array(vec![]))).unwrap();
+
+    assert_eq!((), actual);
+}
+
+#[test]
+fn fail_unit_from_non_empty_array() {
+    let res: Result<()> = Deserialize::deserialize(&mut Deserializer::new(Value::Array(vec![Value::Nil])));
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_long_string_round_trip() {
+    use std::io::Cursor;
+    use serde::Serialize;
+    use rmp_serde::value::{from_value, to_value};
+
+    let expected: String = (0..100 * 1024).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+
+    let value = to_value(&expected);
+    assert_eq!(Value::String(expected.clone()), value);
+
+    let actual: String = from_value(value).unwrap();
+    assert_eq!(expected, actual);
+
+    let mut buf = Vec::new();
+    expected.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+
+    // Str32 marker followed by the big-endian length.
+    assert_eq!(&[0xdb, 0x00, 0x01, 0x90, 0x00][..], &buf[..5]);
+    assert_eq!(expected.len() + 5, buf.len());
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    let actual: String = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn fail_bool_from_string_names_found_value() {
+    let res: Result<bool> = Deserialize::deserialize(&mut Deserializer::new(Value::String("yes".to_string())));
+
+    match res.err() {
+        Some(Error::Uncategorized(ref msg)) if msg == "invalid type: expected bool, found string \"yes\"" => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_bool() {
+    let actual: bool = Deserialize::deserialize(&mut Deserializer::new(Value::Boolean(true))).unwrap();
+
+    assert!(actual);
+}
+
+#[test]
+fn pass_result_ok_round_trip() {
+    use rmp_serde::value::encode::Serializer;
+
+    let expected: result::Result<u32, String> = Ok(42);
+
+    let mut ser = Serializer::new();
+    rmp_serde::value::result::serialize(&expected, &mut ser).unwrap();
+    let value = ser.unwrap();
+
+    assert_eq!(Value::Map(vec![(Value::Integer(Integer::U64(0)), Value::Integer(Integer::U64(42)))]), value);
+
+    let actual: result::Result<u32, String> =
+        rmp_serde::value::result::deserialize(&mut Deserializer::new(value)).unwrap();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn pass_result_err_round_trip() {
+    use rmp_serde::value::encode::Serializer;
+
+    let expected: result::Result<u32, String> = Err("le message".to_string());
+
+    let mut ser = Serializer::new();
+    rmp_serde::value::result::serialize(&expected, &mut ser).unwrap();
+    let value = ser.unwrap();
+
+    assert_eq!(Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::String("le message".to_string()))]), value);
+
+    let actual: result::Result<u32, String> =
+        rmp_serde::value::result::deserialize(&mut Deserializer::new(value)).unwrap();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn fail_result_unknown_tag() {
+    let value = Value::Map(vec![(Value::Integer(Integer::U64(2)), Value::Nil)]);
+
+    let res: Result<result::Result<(), ()>> = rmp_serde::value::result::deserialize(&mut Deserializer::new(value));
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_ipaddr_round_trip() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::ipaddr;
+
+    let addrs = [
+        (IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), vec![0; 4]),
+        (IpAddr::V4(Ipv4Addr::new(255, 255, 255, 255)), vec![0xff; 4]),
+        (IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), vec![127, 0, 0, 1]),
+        (IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)), vec![0; 16]),
+        (IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)), vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
+        (IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0xffff)), vec![0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff]),
+    ];
+
+    for &(ref addr, ref bytes) in addrs.iter() {
+        let mut ser = Serializer::new();
+        ipaddr::serialize(addr, &mut ser).unwrap();
+        let value = ser.unwrap();
+
+        assert_eq!(Value::Binary(bytes.clone()), value);
+        assert_eq!(*addr, ipaddr::deserialize(&mut Deserializer::new(value)).unwrap());
+    }
+}
+
+#[test]
+fn pass_ipv4addr_round_trip() {
+    use std::net::Ipv4Addr;
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::ipaddr;
+
+    let addr = Ipv4Addr::new(192, 168, 0, 1);
+
+    let mut ser = Serializer::new();
+    ipaddr::serialize_v4(&addr, &mut ser).unwrap();
+
+    assert_eq!(addr, ipaddr::deserialize_v4(&mut Deserializer::new(ser.unwrap())).unwrap());
+}
+
+#[test]
+fn fail_ipaddr_invalid_length() {
+    use rmp_serde::value::ipaddr;
+
+    let res = ipaddr::deserialize(&mut Deserializer::new(Value::Binary(vec![1, 2, 3])));
+    match res.err() {
+        Some(Error::LengthMismatch(3)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_ipv6addr_from_ipv4_bytes() {
+    use rmp_serde::value::ipaddr;
+
+    assert!(ipaddr::deserialize_v6(&mut Deserializer::new(Value::Binary(vec![127, 0, 0, 1]))).is_err());
+}
+
+#[test]
+fn pass_cow_transparent_round_trip() {
+    use std::borrow::Cow;
+    use rmp_serde::value::{from_value, to_value};
+
+    let s = "le message";
+    for cow in [Cow::Borrowed(s), Cow::Owned(s.to_string())].iter() {
+        let value = to_value(cow);
+        assert_eq!(Value::String(s.to_string()), value);
+        assert_eq!(*cow, from_value::<Cow<str>>(value).unwrap());
+    }
+
+    // Without the `cow_bytes` helper, bytes are a sequence like any other slice.
+    let bytes = [1u8, 2];
+    for cow in [Cow::Borrowed(&bytes[..]), Cow::Owned(bytes.to_vec())].iter() {
+        let value = to_value(cow);
+        assert_eq!(Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(2))]), value);
+        assert_eq!(*cow, from_value::<Cow<[u8]>>(value).unwrap());
+    }
+
+    let pairs = [(1u32, true), (2, false)];
+    for cow in [Cow::Borrowed(&pairs[..]), Cow::Owned(pairs.to_vec())].iter() {
+        let value = to_value(cow);
+        assert_eq!(to_value(&pairs.to_vec()), value);
+        assert_eq!(*cow, from_value::<Cow<[(u32, bool)]>>(value).unwrap());
+    }
+
+    // Nor does the wrapper show up when nested.
+    let nested = (Cow::Borrowed("x"), Some(Cow::Borrowed(&bytes[..])));
+    assert_eq!(to_value(&("x", Some(vec![1u8, 2]))), to_value(&nested));
+}
+
+#[test]
+fn pass_cow_bytes_round_trip() {
+    use std::borrow::Cow;
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::cow_bytes;
+
+    let bytes = [0xc0, 0x01, 0xff];
+    let cows = [Cow::Borrowed(&bytes[..]), Cow::Owned(bytes.to_vec())];
+
+    for cow in cows.iter() {
+        let mut ser = Serializer::new();
+        cow_bytes::serialize(cow, &mut ser).unwrap();
+        let value = ser.unwrap();
+
+        assert_eq!(Value::Binary(bytes.to_vec()), value);
+
+        let actual: Cow<[u8]> = cow_bytes::deserialize(&mut Deserializer::new(value)).unwrap();
+        assert_eq!(*cow, actual);
+    }
+}
+
+#[test]
+fn pass_bitvec_pack() {
+    use rmp_serde::value::bitvec::{pack, unpack};
+
+    let bits = [true, false, true, true, false, false, false, false, true, true];
+    let buf = pack(&bits);
+
+    assert_eq!(vec![0x00, 0x00, 0x00, 0x0a, 0b10110000, 0b11000000], buf);
+    assert_eq!(Some(bits.to_vec()), unpack(&buf));
+
+    assert_eq!(vec![0x00, 0x00, 0x00, 0x00], pack(&[]));
+    assert_eq!(Some(vec![]), unpack(&[0x00, 0x00, 0x00, 0x00]));
+
+    let bits = [true; 8];
+    assert_eq!(vec![0x00, 0x00, 0x00, 0x08, 0xff], pack(&bits));
+    assert_eq!(Some(bits.to_vec()), unpack(&pack(&bits)));
+}
+
+#[test]
+fn pass_bitvec_round_trip() {
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::bitvec;
+
+    let bits: Vec<bool> = (0..1000).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+
+    let mut ser = Serializer::new();
+    bitvec::serialize(&bits, &mut ser).unwrap();
+    let value = ser.unwrap();
+
+    match value {
+        Value::Binary(ref buf) => assert_eq!(4 + 125, buf.len()),
+        ref other => panic!("unexpected value: {:?}", other),
+    }
+
+    assert_eq!(bits, bitvec::deserialize(&mut Deserializer::new(value)).unwrap());
+}
+
+#[test]
+fn fail_bitvec_length_mismatch() {
+    use rmp_serde::value::bitvec::{self, unpack};
+
+    assert_eq!(None, unpack(&[0x00, 0x00]));
+    assert_eq!(None, unpack(&[0x00, 0x00, 0x00, 0x09, 0xff]));
+    assert_eq!(None, unpack(&[0x00, 0x00, 0x00, 0x01, 0x80, 0x00]));
+
+    let res = bitvec::deserialize(&mut Deserializer::new(Value::Binary(vec![0x00, 0x00, 0x00, 0x09, 0xff])));
+    match res.err() {
+        Some(Error::LengthMismatch(5)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Permissions(u8);
+
+impl rmp_serde::value::bitset::Bits for Permissions {
+    fn bits(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn from_bits(bits: u64) -> Option<Permissions> {
+        if bits & !0b111 == 0 { Some(Permissions(bits as u8)) } else { None }
+    }
+}
+
+impl rmp_serde::value::bitset::NamedBits for Permissions {
+    fn names() -> &'static [(&'static str, u64)] {
+        static NAMES: [(&'static str, u64); 3] = [("read", 0b001), ("write", 0b010), ("exec", 0b100)];
+        &NAMES
+    }
+}
+
+#[test]
+fn pass_unique_set() {
+    use std::collections::{BTreeSet, HashSet};
+    use rmp_serde::value::unique_set;
+
+    let value = Value::Array(vec![
+        Value::String("John".to_string()),
+        Value::String("Jane".to_string()),
+    ]);
+
+    let actual: HashSet<String> = unique_set::deserialize(&mut Deserializer::new(value.clone())).unwrap();
+    assert_eq!(vec!["Jane".to_string(), "John".to_string()].into_iter().collect::<HashSet<_>>(), actual);
+
+    let actual: BTreeSet<String> = unique_set::deserialize(&mut Deserializer::new(value)).unwrap();
+    assert_eq!(vec!["Jane".to_string(), "John".to_string()].into_iter().collect::<BTreeSet<_>>(), actual);
+}
+
+#[test]
+fn fail_unique_set_duplicates() {
+    use std::collections::HashSet;
+    use rmp_serde::value::unique_set;
+
+    let value = Value::Array(vec![
+        Value::Integer(Integer::U64(1)),
+        Value::Integer(Integer::U64(2)),
+        Value::Integer(Integer::U64(1)),
+    ]);
+
+    let res: Result<HashSet<u32>> = unique_set::deserialize(&mut Deserializer::new(value.clone()));
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("duplicate set element at index 2") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    // Without the helper, sets drop duplicates and other sequences keep them.
+    let actual: HashSet<u32> = Deserialize::deserialize(&mut Deserializer::new(value.clone())).unwrap();
+    assert_eq!(2, actual.len());
+
+    let actual: Vec<u32> = Deserialize::deserialize(&mut Deserializer::new(value)).unwrap();
+    assert_eq!(vec![1, 2, 1], actual);
+}
+
+#[test]
+fn pass_bitset_round_trip() {
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::bitset;
+
+    let perms = Permissions(0b101);
+
+    let mut ser = Serializer::new();
+    bitset::serialize(&perms, &mut ser).unwrap();
+    let value = ser.unwrap();
+
+    assert_eq!(Value::Integer(Integer::U64(5)), value);
+    assert_eq!(perms, bitset::deserialize(&mut Deserializer::new(value)).unwrap());
+}
+
+#[test]
+fn fail_bitset_invalid_bits() {
+    use rmp_serde::value::bitset;
+
+    let res: Result<Permissions> = bitset::deserialize(&mut Deserializer::new(Value::Integer(Integer::U64(8))));
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("invalid bits 0x8") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_bitset_names_round_trip() {
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::bitset;
+
+    let perms = Permissions(0b110);
+
+    let mut ser = Serializer::new();
+    bitset::serialize_names(&perms, &mut ser).unwrap();
+    let value = ser.unwrap();
+
+    assert_eq!(Value::Array(vec![
+        Value::String("write".to_string()),
+        Value::String("exec".to_string()),
+    ]), value);
+    assert_eq!(perms, bitset::deserialize_names(&mut Deserializer::new(value)).unwrap());
+
+    let mut ser = Serializer::new();
+    bitset::serialize_names(&Permissions(0), &mut ser).unwrap();
+
+    assert_eq!(Value::Array(vec![]), ser.unwrap());
+}
+
+#[test]
+fn fail_bitset_unknown_name() {
+    use rmp_serde::value::bitset;
+
+    let value = Value::Array(vec![Value::String("delete".to_string())]);
+
+    let res: Result<Permissions> = bitset::deserialize_names(&mut Deserializer::new(value));
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("unknown flag `delete`") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_bitset_unnamed_bits() {
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::bitset;
+
+    let mut ser = Serializer::new();
+    assert!(bitset::serialize_names(&Permissions(0b1001), &mut ser).is_err());
+}
+
+/// Mixes a string field encoded as usual with one encoded as binary.
+#[derive(Debug, PartialEq)]
+struct Message {
+    topic: String,
+    payload: String,
+}
+
+impl serde::Serialize for Message {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        use rmp_serde::value::binary_str;
+
+        struct Payload<'a>(&'a String);
+
+        impl<'a> serde::Serialize for Payload<'a> {
+            fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+                where S: serde::Serializer
+            {
+                binary_str::serialize(self.0, ser)
+            }
+        }
+
+        (&self.topic, Payload(&self.payload)).serialize(ser)
+    }
+}
+
+impl Deserialize for Message {
+    fn deserialize<D>(de: &mut D) -> result::Result<Message, D::Error>
+        where D: serde::Deserializer
+    {
+        use rmp_serde::value::binary_str;
+
+        struct Payload(String);
+
+        impl Deserialize for Payload {
+            fn deserialize<D>(de: &mut D) -> result::Result<Payload, D::Error>
+                where D: serde::Deserializer
+            {
+                binary_str::deserialize(de).map(Payload)
+            }
+        }
+
+        let (topic, Payload(payload)) = try!(Deserialize::deserialize(de));
+
+        Ok(Message { topic: topic, payload: payload })
+    }
+}
+
+#[test]
+fn pass_binary_str_round_trip() {
+    use rmp_serde::value::{from_value, to_value};
+
+    let message = Message { topic: "news".to_string(), payload: "le message".to_string() };
+
+    let value = to_value(&message);
+    assert_eq!(Value::Array(vec![
+        Value::String("news".to_string()),
+        Value::Binary(b"le message".to_vec()),
+    ]), value);
+
+    assert_eq!(message, from_value(value).unwrap());
+}
+
+#[test]
+fn pass_binary_str_from_str_or_bin() {
+    use std::io::Cursor;
+    use rmp_serde::value::binary_str;
+
+    let actual = binary_str::deserialize(&mut Deserializer::new(Value::String("le message".to_string())));
+    assert_eq!("le message", actual.unwrap());
+
+    let actual = binary_str::deserialize(&mut Deserializer::new(Value::Binary(b"le message".to_vec())));
+    assert_eq!("le message", actual.unwrap());
+
+    let buf = [0xc4, 0x02, 0x68, 0x69];
+    let actual = rmp_serde::as_binary_str::deserialize(&mut rmp_serde::Deserializer::new(Cursor::new(&buf[..])));
+    assert_eq!("hi", actual.unwrap());
+}
+
+#[test]
+fn fail_binary_str_invalid_utf8() {
+    use rmp_serde::value::binary_str;
+
+    assert!(binary_str::deserialize(&mut Deserializer::new(Value::Binary(vec![0xff, 0xfe]))).is_err());
+}
+
+/// Implements only `visit_bytes`, borrowing the binary.
+#[derive(Debug, PartialEq)]
+struct BorrowedBytes(Vec<u8>);
+
+impl Deserialize for BorrowedBytes {
+    fn deserialize<D>(de: &mut D) -> result::Result<BorrowedBytes, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = BorrowedBytes;
+
+            fn visit_bytes<E>(&mut self, v: &[u8]) -> result::Result<BorrowedBytes, E>
+                where E: serde::de::Error
+            {
+                Ok(BorrowedBytes(v.to_vec()))
+            }
+        }
+
+        de.deserialize_bytes(Visitor)
+    }
+}
+
+/// Implements only `visit_byte_buf`, taking ownership of the binary.
+#[derive(Debug, PartialEq)]
+struct OwnedBytes(Vec<u8>);
+
+impl Deserialize for OwnedBytes {
+    fn deserialize<D>(de: &mut D) -> result::Result<OwnedBytes, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = OwnedBytes;
+
+            fn visit_byte_buf<E>(&mut self, v: Vec<u8>) -> result::Result<OwnedBytes, E>
+                where E: serde::de::Error
+            {
+                Ok(OwnedBytes(v))
+            }
+        }
+
+        de.deserialize_bytes(Visitor)
+    }
+}
+
+#[test]
+fn pass_binary_to_borrowing_or_owning_visitor() {
+    let value = Value::Binary(vec![0xcc, 0x80]);
+
+    let actual: BorrowedBytes = Deserialize::deserialize(&mut Deserializer::new(value.clone())).unwrap();
+    assert_eq!(BorrowedBytes(vec![0xcc, 0x80]), actual);
+
+    let actual: OwnedBytes = Deserialize::deserialize(&mut Deserializer::new(value)).unwrap();
+    assert_eq!(OwnedBytes(vec![0xcc, 0x80]), actual);
+}
+
+#[cfg(unix)]
+#[test]
+fn pass_pathbuf_round_trip() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::pathbuf;
+
+    let paths = [
+        PathBuf::from("/tmp/le message.txt"),
+        PathBuf::from(OsStr::from_bytes(b"/tmp/caf\xe9.txt")),
+    ];
+
+    for path in paths.iter() {
+        let mut ser = Serializer::new();
+        pathbuf::serialize(path, &mut ser).unwrap();
+        let value = ser.unwrap();
+
+        assert_eq!(Value::Binary(path.as_os_str().as_bytes().to_vec()), value);
+        assert_eq!(*path, pathbuf::deserialize(&mut Deserializer::new(value)).unwrap());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn pass_pathbuf_from_str_or_bin() {
+    use std::io::Cursor;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use rmp_serde::value::pathbuf;
+
+    let actual = pathbuf::deserialize(&mut Deserializer::new(Value::String("/tmp".to_string())));
+    assert_eq!(PathBuf::from("/tmp"), actual.unwrap());
+
+    let buf = [0xc4, 0x03, 0x2f, 0xff, 0x61];
+    let actual = pathbuf::deserialize(&mut rmp_serde::Deserializer::new(Cursor::new(&buf[..])));
+    assert_eq!(PathBuf::from(OsStr::from_bytes(b"/\xffa")), actual.unwrap());
+}
+
+#[test]
+fn pass_range_round_trip() {
+    use std::ops::Range;
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::range;
+
+    let ranges: [(Range<usize>, (u64, u64)); 3] = [(2..5, (2, 5)), (3..3, (3, 3)), (5..2, (5, 2))];
+
+    for &(ref r, (start, end)) in ranges.iter() {
+        let mut ser = Serializer::new();
+        range::serialize(r, &mut ser).unwrap();
+        let value = ser.unwrap();
+
+        assert_eq!(Value::Array(vec![
+            Value::Integer(Integer::U64(start)),
+            Value::Integer(Integer::U64(end)),
+        ]), value);
+
+        let actual: Range<usize> = range::deserialize(&mut Deserializer::new(value)).unwrap();
+        assert_eq!(*r, actual);
+    }
+}
+
+#[test]
+fn fail_range_wrong_length() {
+    use std::ops::Range;
+    use rmp_serde::value::range;
+
+    let value = Value::Array(vec![Value::Integer(Integer::U64(1))]);
+
+    let res: Result<Range<u32>> = range::deserialize(&mut Deserializer::new(value));
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_integer_boundaries_round_trip() {
+    use std::io::Cursor;
+    use std::{i64, u64};
+    use serde::Serialize;
+    use rmp_serde::value::{from_value, to_value};
+
+    assert_eq!(Value::Integer(Integer::I64(i64::MAX)), to_value(&i64::MAX));
+    assert_eq!(Value::Integer(Integer::I64(i64::MIN)), to_value(&i64::MIN));
+    assert_eq!(Value::Integer(Integer::U64(i64::MAX as u64 + 1)), to_value(&(i64::MAX as u64 + 1)));
+    assert_eq!(Value::Integer(Integer::U64(u64::MAX)), to_value(&u64::MAX));
+
+    assert_eq!(i64::MAX, from_value::<i64>(to_value(&i64::MAX)).unwrap());
+    assert_eq!(i64::MIN, from_value::<i64>(to_value(&i64::MIN)).unwrap());
+    assert_eq!(i64::MAX as u64 + 1, from_value::<u64>(to_value(&(i64::MAX as u64 + 1))).unwrap());
+    assert_eq!(u64::MAX, from_value::<u64>(to_value(&u64::MAX)).unwrap());
+
+    assert!(from_value::<i64>(to_value(&(i64::MAX as u64 + 1))).is_err());
+    assert!(from_value::<i64>(to_value(&u64::MAX)).is_err());
+
+    let cases: [(u64, [u8; 9]); 2] = [
+        (i64::MAX as u64 + 1, [0xcf, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+        (u64::MAX, [0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+    ];
+
+    for &(val, ref bytes) in cases.iter() {
+        let mut buf = Vec::new();
+        val.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+        assert_eq!(&bytes[..], &buf[..]);
+
+        let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+        assert_eq!(val, Deserialize::deserialize(&mut de).unwrap());
+    }
+
+    let mut buf = Vec::new();
+    i64::MAX.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+    assert_eq!(&[0xd3, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff][..], &buf[..]);
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    assert_eq!(i64::MAX, Deserialize::deserialize(&mut de).unwrap());
+
+    let mut buf = Vec::new();
+    i64::MIN.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+    assert_eq!(&[0xd3, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00][..], &buf[..]);
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    assert_eq!(i64::MIN, Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_int_from_whole_float() {
+    use rmp::value::Float;
+
+    let mut de = Deserializer::new(Value::Float(Float::F64(3.0)));
+    de.set_float_as_int(true);
+
+    assert_eq!(3i32, Deserialize::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::new(Value::Float(Float::F32(-3.0)));
+    de.set_float_as_int(true);
+
+    assert_eq!(-3i64, Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn fail_int_from_fractional_float() {
+    use rmp::value::Float;
+
+    let mut de = Deserializer::new(Value::Float(Float::F64(3.5)));
+    de.set_float_as_int(true);
+
+    let res: Result<i32> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(..)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+fn mixed_numbers() -> Value {
+    use rmp::value::Float;
+
+    Value::Array(vec![
+        Value::Integer(Integer::I64(-3)),
+        Value::Integer(Integer::U64(42)),
+        Value::Float(Float::F32(1.5)),
+        Value::Float(Float::F64(0.25)),
+    ])
+}
+
+#[test]
+fn pass_f64_from_mixed_numbers() {
+    use rmp_serde::value::from_value;
+
+    let actual: Vec<f64> = from_value(mixed_numbers()).unwrap();
+    assert_eq!(vec![-3.0, 42.0, 1.5, 0.25], actual);
+}
+
+#[test]
+fn pass_number_as_f64() {
+    use rmp::value::Float;
+    use rmp_serde::value::RawValue;
+
+    let mut de = Deserializer::new(mixed_numbers());
+    de.set_number_as_f64(true);
+
+    let actual: RawValue = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(Value::Array(vec![
+        Value::Float(Float::F64(-3.0)),
+        Value::Float(Float::F64(42.0)),
+        Value::Float(Float::F64(1.5)),
+        Value::Float(Float::F64(0.25)),
+    ]), actual.into_inner());
+
+    // Integer fields are not affected.
+    let mut de = Deserializer::new(Value::Integer(Integer::U64(42)));
+    de.set_number_as_f64(true);
+
+    assert_eq!(42u32, Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_trace_nested_value() {
+    use std::collections::BTreeMap;
+    use serde::Serialize;
+    use rmp_serde::value::{Trace, TracingSerializer};
+    use rmp_serde::value::encode::Serializer;
+
+    let mut map = BTreeMap::new();
+    map.insert("x", vec![Some(5i64), None]);
+
+    let trace = Trace::new();
+    let mut ser = Serializer::new();
+    map.serialize(&mut TracingSerializer::new(&mut ser, &trace)).unwrap();
+
+    assert_eq!(vec!["serialize_map(1)",
+                    "serialize_map_elt",
+                    "serialize_str(\"x\")",
+                    "serialize_seq(2)",
+                    "serialize_seq_elt",
+                    "serialize_some",
+                    "serialize_i64(5)",
+                    "serialize_seq_elt",
+                    "serialize_none"], trace.ops());
+
+    let expected = Value::Map(vec![
+        (Value::String("x".to_string()), Value::Array(vec![
+            Value::Integer(Integer::I64(5)),
+            Value::Nil,
+        ])),
+    ]);
+    assert_eq!(expected, ser.unwrap());
+}
+
+#[test]
+fn pass_smart_pointers_round_trip() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use rmp_serde::value::{from_value, to_value};
+
+    let boxed = Box::new(42u32);
+    assert_eq!(boxed, from_value(to_value(&boxed)).unwrap());
+
+    let boxed: Box<[u32]> = vec![1, 2, 3].into_boxed_slice();
+    assert_eq!(boxed, from_value(to_value(&boxed)).unwrap());
+
+    let rc = Rc::new(vec!["le".to_string(), "message".to_string()]);
+    assert_eq!(rc, from_value(to_value(&rc)).unwrap());
+
+    let arc = Arc::new((42u8, Some(true)));
+    assert_eq!(arc, from_value(to_value(&arc)).unwrap());
+
+    // Serde provides no impls for `Rc<str>`, `Rc<String>` is the closest equivalent.
+    let rc = Rc::new("le message".to_string());
+    assert_eq!(rc, from_value(to_value(&rc)).unwrap());
+}
+
+#[test]
+fn pass_compress_floats() {
+    use serde::Serialize;
+    use rmp::value::Float;
+    use rmp_serde::value::encode::Serializer;
+
+    let mut ser = Serializer::new();
+    ser.set_compress_floats(true);
+    (1.5f64, 0.1f64).serialize(&mut ser).unwrap();
+
+    let expected = Value::Array(vec![Value::Float(Float::F32(1.5)), Value::Float(Float::F64(0.1))]);
+    assert_eq!(expected, ser.unwrap());
+}
+
+#[test]
+fn pass_floats_not_compressed_by_default() {
+    use serde::Serialize;
+    use rmp::value::Float;
+    use rmp_serde::value::encode::Serializer;
+
+    let mut ser = Serializer::new();
+    1.5f64.serialize(&mut ser).unwrap();
+
+    assert_eq!(Value::Float(Float::F64(1.5)), ser.unwrap());
+}
+
+fn flatten_sample() -> Value {
+    Value::Map(vec![
+        (Value::String("a".to_string()), Value::Map(vec![
+            (Value::String("b".to_string()), Value::Array(vec![
+                Value::Integer(Integer::U64(1)),
+                Value::Map(vec![(Value::String("c".to_string()), Value::Boolean(true))]),
+            ])),
+            (Value::String("empty".to_string()), Value::Map(vec![])),
+        ])),
+        (Value::String("d".to_string()), Value::Array(vec![])),
+        (Value::String("e".to_string()), Value::Nil),
+    ])
+}
+
+#[test]
+fn pass_flatten() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::flatten;
+
+    let mut expected = BTreeMap::new();
+    expected.insert("a.b[0]".to_string(), Value::Integer(Integer::U64(1)));
+    expected.insert("a.b[1].c".to_string(), Value::Boolean(true));
+    expected.insert("a.empty".to_string(), Value::Map(vec![]));
+    expected.insert("d".to_string(), Value::Array(vec![]));
+    expected.insert("e".to_string(), Value::Nil);
+
+    assert_eq!(expected, flatten(&flatten_sample()));
+}
+
+#[test]
+fn pass_flatten_round_trip() {
+    use rmp_serde::value::{flatten, unflatten};
+
+    let value = flatten_sample();
+    assert_eq!(Some(value.clone()), unflatten(flatten(&value)));
+
+    let value = Value::Array(vec![Value::Array(vec![]), Value::String("le message".to_string())]);
+    assert_eq!(Some(value.clone()), unflatten(flatten(&value)));
+
+    let value = Value::Integer(Integer::U64(42));
+    assert_eq!(Some(value.clone()), unflatten(flatten(&value)));
+}
+
+#[test]
+fn pass_walk_leaf_paths() {
+    use rmp_serde::value::walk;
+
+    let mut paths = Vec::new();
+    walk(&flatten_sample(), &mut |path, value| {
+        match *value {
+            Value::Array(..) | Value::Map(..) => (),
+            _ => paths.push(path.to_string()),
+        }
+    });
+
+    assert_eq!(vec!["a.b[0]", "a.b[1].c", "e"], paths);
+}
+
+#[test]
+fn pass_walk_parents_first() {
+    use rmp_serde::value::walk;
+
+    let value = Value::Map(vec![
+        (Value::Integer(Integer::U64(1)), Value::Array(vec![Value::Nil])),
+    ]);
+
+    let mut nodes = Vec::new();
+    walk(&value, &mut |path, value| nodes.push((path.to_string(), value.clone())));
+
+    assert_eq!(vec![
+        ("".to_string(), value.clone()),
+        ("[1]".to_string(), Value::Array(vec![Value::Nil])),
+        ("[1][0]".to_string(), Value::Nil),
+    ], nodes);
+}
+
+#[test]
+fn pass_unflatten_sparse_array() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::unflatten;
+
+    let mut leaves = BTreeMap::new();
+    leaves.insert("a[10]".to_string(), Value::Integer(Integer::U64(10)));
+    leaves.insert("a[2]".to_string(), Value::Integer(Integer::U64(2)));
+    for idx in 0..9 {
+        leaves.insert(format!("b[{}]", idx), Value::Nil);
+    }
+
+    let mut vec = vec![Value::Nil; 11];
+    vec[2] = Value::Integer(Integer::U64(2));
+    vec[10] = Value::Integer(Integer::U64(10));
+
+    let expected = Value::Map(vec![
+        (Value::String("a".to_string()), Value::Array(vec)),
+        (Value::String("b".to_string()), Value::Array(vec![Value::Nil; 9])),
+    ]);
+    assert_eq!(Some(expected), unflatten(leaves));
+}
+
+#[test]
+fn fail_unflatten_huge_index() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::unflatten;
+
+    let mut leaves = BTreeMap::new();
+    leaves.insert("[18446744073709551615]".to_string(), Value::Nil);
+    assert_eq!(None, unflatten(leaves));
+
+    let mut leaves = BTreeMap::new();
+    leaves.insert("a[4000000000]".to_string(), Value::Nil);
+    assert_eq!(None, unflatten(leaves));
+
+    // Indices must stay below the number of leaves.
+    let mut leaves = BTreeMap::new();
+    leaves.insert("a[0]".to_string(), Value::Nil);
+    leaves.insert("a[2]".to_string(), Value::Nil);
+    assert_eq!(None, unflatten(leaves));
+}
+
+#[test]
+fn fail_unflatten_conflicting_paths() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::unflatten;
+
+    let mut leaves = BTreeMap::new();
+    leaves.insert("a".to_string(), Value::Integer(Integer::U64(1)));
+    leaves.insert("a.b".to_string(), Value::Integer(Integer::U64(2)));
+    assert_eq!(None, unflatten(leaves));
+
+    let mut leaves = BTreeMap::new();
+    leaves.insert("a.b".to_string(), Value::Integer(Integer::U64(1)));
+    leaves.insert("a[0]".to_string(), Value::Integer(Integer::U64(2)));
+    assert_eq!(None, unflatten(leaves));
+
+    let mut leaves = BTreeMap::new();
+    leaves.insert("a[x]".to_string(), Value::Integer(Integer::U64(1)));
+    assert_eq!(None, unflatten(leaves));
+}
+
+#[test]
+fn pass_merge_nested_maps() {
+    use rmp_serde::value::merge;
+
+    let base = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("app".to_string())),
+        (Value::String("server".to_string()), Value::Map(vec![
+            (Value::String("host".to_string()), Value::String("localhost".to_string())),
+            (Value::String("port".to_string()), Value::Integer(Integer::U64(80))),
+        ])),
+        (Value::String("tags".to_string()), Value::Array(vec![Value::String("a".to_string())])),
+    ]);
+    let overlay = Value::Map(vec![
+        (Value::String("server".to_string()), Value::Map(vec![
+            (Value::String("port".to_string()), Value::Integer(Integer::U64(8080))),
+        ])),
+        (Value::String("tags".to_string()), Value::Array(vec![])),
+        (Value::String("debug".to_string()), Value::Boolean(true)),
+    ]);
+
+    let expected = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("app".to_string())),
+        (Value::String("server".to_string()), Value::Map(vec![
+            (Value::String("host".to_string()), Value::String("localhost".to_string())),
+            (Value::String("port".to_string()), Value::Integer(Integer::U64(8080))),
+        ])),
+        (Value::String("tags".to_string()), Value::Array(vec![])),
+        (Value::String("debug".to_string()), Value::Boolean(true)),
+    ]);
+
+    assert_eq!(expected, merge(base, overlay));
+    assert_eq!(Value::Nil, merge(Value::Integer(Integer::U64(1)), Value::Nil));
+}
+
+#[test]
+fn pass_from_value_with_defaults() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::from_value_with_defaults;
+
+    let defaults = Value::Map(vec![
+        (Value::String("host".to_string()), Value::String("localhost".to_string())),
+        (Value::String("port".to_string()), Value::String("80".to_string())),
+    ]);
+    let user = Value::Map(vec![
+        (Value::String("port".to_string()), Value::String("8080".to_string())),
+    ]);
+
+    let config: BTreeMap<String, String> = from_value_with_defaults(defaults.clone(), user).unwrap();
+    assert_eq!(Some(&"localhost".to_string()), config.get("host"));
+    assert_eq!(Some(&"8080".to_string()), config.get("port"));
+
+    // Without defaults, the omitted field is missing.
+    let profile: Result<Profile> = from_value_with_defaults(Value::Map(vec![]), Value::Map(vec![]));
+    assert!(profile.is_err());
+
+    let defaults = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("anonymous".to_string())),
+    ]);
+    let user = Value::Map(vec![
+        (Value::String("nickname".to_string()), Value::String("johnny".to_string())),
+    ]);
+
+    let profile: Profile = from_value_with_defaults(defaults, user).unwrap();
+    assert_eq!(Profile { name: "anonymous".to_string(), nickname: Some("johnny".to_string()) }, profile);
+}
+
+fn prune_sample() -> Value {
+    Value::Map(vec![
+        (Value::String("nil".to_string()), Value::Nil),
+        (Value::String("nested".to_string()), Value::Map(vec![
+            (Value::String("nil".to_string()), Value::Nil),
+            (Value::String("int".to_string()), Value::Integer(Integer::U64(42))),
+        ])),
+        (Value::String("array".to_string()), Value::Array(vec![
+            Value::Nil,
+            Value::Map(vec![(Value::String("nil".to_string()), Value::Nil)]),
+        ])),
+    ])
+}
+
+#[test]
+fn pass_prune_nils_keeping_array_elements() {
+    use rmp_serde::value::prune_nils;
+
+    let expected = Value::Map(vec![
+        (Value::String("nested".to_string()), Value::Map(vec![
+            (Value::String("int".to_string()), Value::Integer(Integer::U64(42))),
+        ])),
+        (Value::String("array".to_string()), Value::Array(vec![
+            Value::Nil,
+            Value::Map(vec![]),
+        ])),
+    ]);
+
+    assert_eq!(expected, prune_nils(prune_sample(), false));
+}
+
+#[test]
+fn pass_prune_nils_in_arrays() {
+    use rmp_serde::value::prune_nils;
+
+    let expected = Value::Map(vec![
+        (Value::String("nested".to_string()), Value::Map(vec![
+            (Value::String("int".to_string()), Value::Integer(Integer::U64(42))),
+        ])),
+        (Value::String("array".to_string()), Value::Array(vec![
+            Value::Map(vec![]),
+        ])),
+    ]);
+
+    assert_eq!(expected, prune_nils(prune_sample(), true));
+}
+
+#[test]
+fn pass_seq_of_pairs_from_map() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::{from_value, to_value};
+
+    let mut map = BTreeMap::new();
+    map.insert("first".to_string(), 1i32);
+    map.insert("second".to_string(), -2i32);
+
+    let actual: Vec<(String, i32)> = from_value(to_value(&map)).unwrap();
+
+    assert_eq!(vec![("first".to_string(), 1), ("second".to_string(), -2)], actual);
+}
+
+#[test]
+fn pass_seq_from_array_unchanged() {
+    use rmp_serde::value::{from_value, to_value};
+
+    let expected = vec![("first".to_string(), 1i32)];
+
+    assert_eq!(expected, from_value::<Vec<(String, i32)>>(to_value(&expected)).unwrap());
+}
+
+fn raw_sample() -> Value {
+    use rmp::value::Float;
+
+    Value::Array(vec![
+        Value::Integer(Integer::I64(-1)),
+        Value::Map(vec![
+            (Value::String("float".to_string()), Value::Float(Float::F32(1.5))),
+            (Value::Integer(Integer::U64(42)), Value::Binary(vec![1, 2])),
+            (Value::Nil, Value::Array(vec![Value::Boolean(true), Value::Nil])),
+        ]),
+    ])
+}
+
+#[test]
+fn pass_raw_value_captures_subtree() {
+    use rmp_serde::value::{RawValue, from_value, to_value};
+
+    let value = Value::Array(vec![Value::String("method".to_string()), raw_sample()]);
+
+    let (method, raw): (String, RawValue) = from_value(value.clone()).unwrap();
+
+    assert_eq!("method", method);
+    assert_eq!(raw_sample(), raw.clone().into_inner());
+    assert_eq!(value, to_value(&(method, raw)));
+}
+
+#[test]
+fn pass_raw_value_from_bytes() {
+    use std::io::Cursor;
+    use rmp::encode::write_value;
+    use rmp_serde::value::RawValue;
+
+    let mut buf = Vec::new();
+    write_value(&mut buf, &raw_sample()).unwrap();
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    let raw: RawValue = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(raw_sample(), raw.into_inner());
+}
+
+/// Keeps the entries it doesn't know about in a catch-all map, written back when serialized.
+///
+/// serde has no `flatten` attribute, so the impls are written by hand.
+#[derive(Debug, PartialEq)]
+struct Event {
+    id: u32,
+    extra: HashMap<String, rmp_serde::value::RawValue>,
+}
+
+impl serde::Serialize for Event {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        use serde::ser::impls::MapIteratorVisitor;
+        use rmp_serde::value::RawValue;
+
+        let id = RawValue(Value::Integer(Integer::U64(self.id as u64)));
+        let entries = Some(("id", &id)).into_iter()
+            .chain(self.extra.iter().map(|(key, value)| (&key[..], value)));
+
+        ser.serialize_map(MapIteratorVisitor::new(entries, Some(self.extra.len() + 1)))
+    }
+}
+
+impl Deserialize for Event {
+    fn deserialize<D>(de: &mut D) -> result::Result<Event, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = Event;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Event, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                let mut id = None;
+                let mut extra = HashMap::new();
+
+                while let Some(key) = try!(visitor.visit_key::<String>()) {
+                    if key == "id" {
+                        id = Some(try!(visitor.visit_value()));
+                    } else {
+                        extra.insert(key, try!(visitor.visit_value()));
+                    }
+                }
+                try!(visitor.end());
+
+                let id = match id {
+                    Some(id) => id,
+                    None => try!(visitor.missing_field("id")),
+                };
+
+                Ok(Event { id: id, extra: extra })
+            }
+        }
+
+        de.deserialize_map(Visitor)
+    }
+}
+
+fn event_sample() -> Value {
+    Value::Map(vec![
+        (Value::String("id".to_string()), Value::Integer(Integer::U64(42))),
+        (Value::String("name".to_string()), Value::String("login".to_string())),
+        (Value::String("tags".to_string()), Value::Array(vec![Value::Boolean(true), Value::Nil])),
+        (Value::String("meta".to_string()), Value::Map(vec![
+            (Value::Integer(Integer::I64(-1)), Value::Binary(vec![1, 2])),
+        ])),
+    ])
+}
+
+#[test]
+fn pass_unknown_fields_into_catch_all_map() {
+    use rmp_serde::value::{RawValue, from_value, to_value, value_eq};
+
+    let event: Event = from_value(event_sample()).unwrap();
+
+    assert_eq!(42, event.id);
+    assert_eq!(3, event.extra.len());
+    assert_eq!(Some(&RawValue(Value::String("login".to_string()))), event.extra.get("name"));
+    assert_eq!(Some(&RawValue(Value::Array(vec![Value::Boolean(true), Value::Nil]))), event.extra.get("tags"));
+
+    // The catch-all entries are written back along with the known fields, in any order.
+    assert!(value_eq(&event_sample(), &to_value(&event), false));
+}
+
+#[test]
+fn pass_unknown_fields_into_catch_all_map_from_bytes() {
+    use std::io::Cursor;
+    use rmp::encode::write_value;
+
+    let mut buf = Vec::new();
+    write_value(&mut buf, &event_sample()).unwrap();
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    let event: Event = Deserialize::deserialize(&mut de).unwrap();
+
+    let mut buf = Vec::new();
+    serde::Serialize::serialize(&event, &mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    assert_eq!(event, Deserialize::deserialize(&mut de).unwrap());
+}
+
+/// Newtype struct, implemented the way `#[derive]` does.
+#[derive(Debug, PartialEq)]
+struct Wrapper<T>(T);
+
+impl<T: serde::Serialize> serde::Serialize for Wrapper<T> {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        ser.serialize_newtype_struct("Wrapper", &self.0)
+    }
+}
+
+impl<T: Deserialize> Deserialize for Wrapper<T> {
+    fn deserialize<D>(de: &mut D) -> result::Result<Wrapper<T>, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor<T>(::std::marker::PhantomData<T>);
+
+        impl<T: Deserialize> serde::de::Visitor for Visitor<T> {
+            type Value = Wrapper<T>;
+
+            fn visit_newtype_struct<D>(&mut self, de: &mut D) -> result::Result<Wrapper<T>, D::Error>
+                where D: serde::Deserializer
+            {
+                Ok(Wrapper(try!(Deserialize::deserialize(de))))
+            }
+
+            fn visit_seq<V>(&mut self, mut visitor: V) -> result::Result<Wrapper<T>, V::Error>
+                where V: serde::de::SeqVisitor
+            {
+                let value = match try!(visitor.visit()) {
+                    Some(value) => value,
+                    None => return Err(serde::de::Error::end_of_stream()),
+                };
+                try!(visitor.end());
+
+                Ok(Wrapper(value))
+            }
+        }
+
+        de.deserialize_newtype_struct("Wrapper", Visitor(::std::marker::PhantomData))
+    }
+}
+
+#[test]
+fn pass_nested_newtype_structs_round_trip() {
+    use rmp_serde::value::{from_value, to_value};
+
+    let nested = Wrapper(Wrapper(Wrapper(Wrapper(vec![Wrapper(42u32)]))));
+
+    let value = to_value(&nested);
+    assert_eq!(Value::Array(vec![Value::Array(vec![Value::Array(vec![Value::Array(vec![
+        Value::Array(vec![Value::Array(vec![Value::Integer(Integer::U64(42))])]),
+    ])])])]), value);
+
+    assert_eq!(nested, from_value(value).unwrap());
+}
+
+#[test]
+fn pass_newtype_struct_from_bare_value() {
+    use rmp_serde::value::from_value;
+
+    let actual: Wrapper<Wrapper<String>> = from_value(Value::String("le message".into())).unwrap();
+    assert_eq!(Wrapper(Wrapper("le message".to_string())), actual);
+}
+
+#[test]
+fn fail_newtype_struct_from_longer_array() {
+    use rmp_serde::value::from_value;
+
+    let value = Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(2))]);
+
+    let res: Result<Wrapper<u32>> = from_value(value);
+    assert!(res.is_err());
+}
+
+/// Stands in for `std::num::NonZeroU32`, which serde provides no impls for.
+#[derive(Debug, PartialEq)]
+struct NonZeroU32(u32);
+
+impl Deserialize for NonZeroU32 {
+    fn deserialize<D>(de: &mut D) -> result::Result<NonZeroU32, D::Error>
+        where D: serde::Deserializer
+    {
+        match try!(u32::deserialize(de)) {
+            0 => Err(serde::de::Error::invalid_value("expected a non-zero integer")),
+            v => Ok(NonZeroU32(v)),
+        }
+    }
+}
+
+#[test]
+fn pass_non_zero() {
+    use rmp_serde::value::from_value;
+
+    assert_eq!(NonZeroU32(42), from_value(Value::Integer(Integer::U64(42))).unwrap());
+}
+
+#[test]
+fn fail_non_zero_from_zero_keeps_message() {
+    use std::io::Cursor;
+    use rmp_serde::value::from_value;
+
+    let res: Result<NonZeroU32> = from_value(Value::Integer(Integer::U64(0)));
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("expected a non-zero integer") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let buf = [0x00];
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    let res: result::Result<NonZeroU32, _> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(rmp_serde::decode::Error::Syntax(ref msg)) if msg.contains("expected a non-zero integer") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_diff_equal() {
+    use rmp_serde::value::diff;
+
+    assert_eq!(None, diff(&raw_sample(), &raw_sample()));
+}
+
+#[test]
+fn pass_diff_scalar() {
+    use rmp_serde::value::diff;
+
+    let lhs = Value::Integer(Integer::U64(5));
+
+    assert_eq!(Some(".: 5 != \"5\"".to_string()), diff(&lhs, &Value::String("5".to_string())));
+    assert_eq!(Some(".: Integer(U64(5)) != Integer(I64(5))".to_string()),
+               diff(&lhs, &Value::Integer(Integer::I64(5))));
+}
+
+#[test]
+fn pass_diff_array_length() {
+    use rmp_serde::value::diff;
+
+    let lhs = Value::Array(vec![Value::Array(vec![Value::Nil])]);
+    let rhs = Value::Array(vec![Value::Array(vec![Value::Nil, Value::Nil])]);
+
+    assert_eq!(Some("[0]: array length 1 != 2".to_string()), diff(&lhs, &rhs));
+}
+
+#[test]
+fn pass_diff_nested_map() {
+    use rmp_serde::value::diff;
+
+    fn config(timeout: u64) -> Value {
+        Value::Map(vec![
+            (Value::String("config".to_string()), Value::Map(vec![
+                (Value::String("retries".to_string()), Value::Integer(Integer::U64(3))),
+                (Value::String("timeout".to_string()), Value::Integer(Integer::U64(timeout))),
+            ])),
+        ])
+    }
+
+    assert_eq!(Some(".config.timeout: 5 != 7".to_string()), diff(&config(5), &config(7)));
+
+    let rhs = Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::Nil)]);
+    assert_eq!(Some(".config: {\"retries\": 3, \"timeout\": 5} != <missing>".to_string()),
+               diff(&config(5), &rhs));
+}
+
+/// Fails to serialize when set.
+struct Failing(bool);
+
+impl serde::Serialize for Failing {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        if self.0 {
+            Err(serde::ser::Error::custom("failed on purpose"))
+        } else {
+            ser.serialize_bool(false)
+        }
+    }
+}
+
+#[test]
+fn fail_map_value_partway_leaves_serializer_consistent() {
+    use std::collections::BTreeMap;
+    use serde::Serialize;
+    use rmp_serde::value::encode::{self, Serializer};
+
+    let mut map = BTreeMap::new();
+    map.insert("first", vec![Failing(false)]);
+    map.insert("second", vec![Failing(false), Failing(true)]);
+
+    let mut ser = Serializer::new();
+    match vec![map].serialize(&mut ser).err() {
+        Some(encode::Error::Custom(ref msg)) if msg == "failed on purpose" => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    // Nothing is left behind, so the serializer can be reused.
+    42u64.serialize(&mut ser).unwrap();
+    assert_eq!(Value::Integer(Integer::U64(42)), ser.unwrap());
+}
+
+/// Serializes as a struct with an optional field, like derived impls do.
+struct Point {
+    x: i64,
+    label: Option<String>,
+}
+
+impl serde::Serialize for Point {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        struct Visitor<'a>(&'a Point, usize);
+
+        impl<'a> serde::ser::MapVisitor for Visitor<'a> {
+            fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                self.1 += 1;
+                match self.1 {
+                    1 => ser.serialize_struct_elt("x", self.0.x).map(Some),
+                    2 => ser.serialize_struct_elt("label", &self.0.label).map(Some),
+                    _ => Ok(None),
+                }
+            }
+
+            fn len(&self) -> Option<usize> {
+                Some(2)
+            }
+        }
+
+        ser.serialize_struct("Point", Visitor(self, 0))
+    }
+}
+
+#[test]
+fn pass_to_value_with_default_options() {
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let point = Point { x: 5, label: None };
+
+    let expected = Value::Map(vec![
+        (Value::String("x".to_string()), Value::Integer(Integer::I64(5))),
+        (Value::String("label".to_string()), Value::Nil),
+    ]);
+    assert_eq!(expected, to_value_with(&point, SerializerOptions::default()).unwrap());
+}
+
+#[test]
+fn pass_to_value_with_options() {
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions {
+        skip_none: true,
+        narrow_ints: true,
+        canonical: true,
+        ..SerializerOptions::default()
+    };
+
+    let point = Point { x: 5, label: None };
+    let expected = Value::Map(vec![(Value::String("x".to_string()), Value::Integer(Integer::U64(5)))]);
+    assert_eq!(expected, to_value_with(&point, opts.clone()).unwrap());
+
+    let point = Point { x: -5, label: Some("le message".to_string()) };
+    let expected = Value::Map(vec![
+        (Value::String("label".to_string()), Value::String("le message".to_string())),
+        (Value::String("x".to_string()), Value::Integer(Integer::I64(-5))),
+    ]);
+    assert_eq!(expected, to_value_with(&point, opts).unwrap());
+}
+
+#[test]
+fn pass_to_value_with_compact_structs() {
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions {
+        compact_structs: true,
+        skip_none: true,
+        ..SerializerOptions::default()
+    };
+
+    let point = Point { x: 5, label: None };
+    let expected = Value::Array(vec![Value::Integer(Integer::I64(5)), Value::Nil]);
+    assert_eq!(expected, to_value_with(&point, opts).unwrap());
+}
+
+fn alias_sample(key: &str) -> Value {
+    Value::Map(vec![
+        (Value::String(key.to_string()), Value::Integer(Integer::U64(42))),
+        (Value::String("id".to_string()), Value::Integer(Integer::U64(1))),
+    ])
+}
+
+fn aliases() -> ::std::collections::HashMap<String, String> {
+    let mut aliases = ::std::collections::HashMap::new();
+    aliases.insert("ts".to_string(), "timestamp".to_string());
+    aliases
+}
+
+#[test]
+fn pass_field_aliases() {
+    use std::collections::BTreeMap;
+
+    let mut de = Deserializer::new(alias_sample("ts"));
+    de.set_field_aliases(aliases());
+
+    let actual: BTreeMap<String, u64> = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(Some(&42), actual.get("timestamp"));
+    assert_eq!(Some(&1), actual.get("id"));
+    assert_eq!(None, actual.get("ts"));
+}
+
+#[test]
+fn fail_field_alias_collides_with_field() {
+    use std::collections::BTreeMap;
+
+    let mut value = alias_sample("ts");
+    if let Value::Map(ref mut vec) = value {
+        vec.push((Value::String("timestamp".to_string()), Value::Integer(Integer::U64(43))));
+    }
+
+    let mut de = Deserializer::new(value);
+    de.set_field_aliases(aliases());
+
+    let res: Result<BTreeMap<String, u64>> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("alias `ts`") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+/// Encodes like an adjacently tagged enum, `{"t": variant, "c": payload}`, where the payload type
+/// depends on the tag decoded before it.
+#[derive(Debug, PartialEq)]
+enum Msg {
+    Ping,
+    Echo(String),
+    Move { x: i32 },
+}
+
+impl serde::Serialize for Msg {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        struct Visitor<'a>(&'a Msg, usize);
+
+        impl<'a> serde::ser::MapVisitor for Visitor<'a> {
+            fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                self.1 += 1;
+                match (self.1, self.0) {
+                    (1, &Msg::Ping) => ser.serialize_map_elt("t", "Ping").map(Some),
+                    (1, &Msg::Echo(..)) => ser.serialize_map_elt("t", "Echo").map(Some),
+                    (1, &Msg::Move { .. }) => ser.serialize_map_elt("t", "Move").map(Some),
+                    (2, &Msg::Echo(ref s)) => ser.serialize_map_elt("c", s).map(Some),
+                    (2, &Msg::Move { x }) => {
+                        let mut fields = ::std::collections::BTreeMap::new();
+                        fields.insert("x", x);
+                        ser.serialize_map_elt("c", fields).map(Some)
+                    }
+                    _ => Ok(None),
+                }
+            }
+
+            fn len(&self) -> Option<usize> {
+                match *self.0 {
+                    Msg::Ping => Some(1),
+                    _ => Some(2),
+                }
+            }
+        }
+
+        ser.serialize_map(Visitor(self, 0))
+    }
+}
+
+impl Deserialize for Msg {
+    fn deserialize<D>(de: &mut D) -> result::Result<Msg, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = Msg;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Msg, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                match try!(visitor.visit_key::<String>()) {
+                    Some(ref key) if key == "t" => (),
+                    _ => return Err(serde::de::Error::missing_field("t")),
+                }
+
+                let tag: String = try!(visitor.visit_value());
+                if tag == "Ping" {
+                    try!(visitor.end());
+                    return Ok(Msg::Ping);
+                }
+
+                match try!(visitor.visit_key::<String>()) {
+                    Some(ref key) if key == "c" => (),
+                    _ => return Err(serde::de::Error::missing_field("c")),
+                }
+
+                let msg = match &tag[..] {
+                    "Echo" => Msg::Echo(try!(visitor.visit_value())),
+                    "Move" => {
+                        let fields: ::std::collections::BTreeMap<String, i32> = try!(visitor.visit_value());
+                        match fields.get("x") {
+                            Some(&x) => Msg::Move { x: x },
+                            None => return Err(serde::de::Error::missing_field("x")),
+                        }
+                    }
+                    _ => return Err(serde::de::Error::invalid_value("unknown variant")),
+                };
+
+                try!(visitor.end());
+                Ok(msg)
+            }
+        }
+
+        de.deserialize_map(Visitor)
+    }
+}
+
+#[test]
+fn pass_adjacently_tagged_round_trip() {
+    use std::io::Cursor;
+    use serde::Serialize;
+    use rmp_serde::value::{from_value, to_value};
+
+    for msg in &[Msg::Ping, Msg::Echo("le message".to_string()), Msg::Move { x: -42 }] {
+        assert_eq!(*msg, from_value(to_value(msg)).unwrap());
+
+        let mut buf = Vec::new();
+        msg.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+
+        let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+        assert_eq!(*msg, Deserialize::deserialize(&mut de).unwrap());
+    }
+}
+
+#[test]
+fn pass_value_eq_integers() {
+    use rmp_serde::value::value_eq;
+
+    let lhs = Value::Array(vec![Value::Integer(Integer::I64(5))]);
+
+    assert!(value_eq(&lhs, &Value::Array(vec![Value::Integer(Integer::U64(5))]), false));
+    assert!(!value_eq(&lhs, &Value::Array(vec![Value::Integer(Integer::U64(6))]), false));
+    assert!(!value_eq(&Value::Integer(Integer::I64(-1)), &Value::Integer(Integer::U64(u64::max_value())), false));
+}
+
+#[test]
+fn pass_value_eq_mixed_floats() {
+    use rmp::value::Float;
+    use rmp_serde::value::value_eq;
+
+    let lhs = Value::Float(Float::F32(1.5));
+    let rhs = Value::Float(Float::F64(1.5));
+
+    assert!(!value_eq(&lhs, &rhs, false));
+    assert!(value_eq(&lhs, &rhs, true));
+    assert!(!value_eq(&Value::Float(Float::F32(0.1)), &Value::Float(Float::F64(0.1)), true));
+}
+
+#[test]
+fn pass_value_eq_map_order() {
+    use rmp_serde::value::value_eq;
+
+    let lhs = Value::Map(vec![
+        (Value::String("a".to_string()), Value::Integer(Integer::I64(1))),
+        (Value::String("b".to_string()), Value::Nil),
+    ]);
+    let rhs = Value::Map(vec![
+        (Value::String("b".to_string()), Value::Nil),
+        (Value::String("a".to_string()), Value::Integer(Integer::U64(1))),
+    ]);
+
+    assert!(value_eq(&lhs, &rhs, false));
+    assert!(!value_eq(&lhs, &Value::Map(vec![]), false));
+}
+
+#[test]
+fn pass_value_eq_map_duplicate_keys() {
+    use rmp_serde::value::value_eq;
+
+    let key = || Value::String("a".to_string());
+
+    let lhs = Value::Map(vec![
+        (key(), Value::Integer(Integer::U64(1))),
+        (key(), Value::Integer(Integer::U64(1))),
+    ]);
+    let rhs = Value::Map(vec![
+        (key(), Value::Integer(Integer::U64(1))),
+        (key(), Value::Integer(Integer::U64(2))),
+    ]);
+
+    assert!(!value_eq(&lhs, &rhs, false));
+    assert!(!value_eq(&rhs, &lhs, false));
+    assert!(value_eq(&rhs, &Value::Map(vec![
+        (key(), Value::Integer(Integer::I64(2))),
+        (key(), Value::Integer(Integer::I64(1))),
+    ]), false));
+}
+
+#[test]
+fn pass_value_eq_signed_zeros() {
+    use rmp::value::Float;
+    use rmp_serde::value::value_eq;
+
+    assert!(value_eq(&Value::Float(Float::F64(0.0)), &Value::Float(Float::F64(-0.0)), false));
+}
+
+#[test]
+fn pass_float_from_int() {
+    use rmp_serde::value::from_value;
+
+    assert_eq!(42.0f64, from_value(Value::Integer(Integer::U64(42))).unwrap());
+    assert_eq!(-42.0f32, from_value(Value::Integer(Integer::I64(-42))).unwrap());
+}
+
+#[test]
+fn pass_float_from_large_int_loses_precision() {
+    use rmp_serde::value::from_value;
+
+    // Integers above 2^53 have no exact f64 representation and are rounded to the nearest one.
+    let actual: f64 = from_value(Value::Integer(Integer::U64((1 << 53) + 1))).unwrap();
+
+    assert_eq!(9007199254740992.0, actual);
+}
+
+#[test]
+fn pass_marker_of_scalars() {
+    use rmp::value::Float;
+    use rmp_serde::value::marker_of;
+
+    assert_eq!(Marker::Null, marker_of(&Value::Nil));
+    assert_eq!(Marker::True, marker_of(&Value::Boolean(true)));
+    assert_eq!(Marker::False, marker_of(&Value::Boolean(false)));
+    assert_eq!(Marker::F32, marker_of(&Value::Float(Float::F32(1.5))));
+    assert_eq!(Marker::F64, marker_of(&Value::Float(Float::F64(1.5))));
+}
+
+#[test]
+fn pass_marker_of_ints() {
+    use rmp_serde::value::marker_of;
+
+    let uint = |v| marker_of(&Value::Integer(Integer::U64(v)));
+    assert_eq!(Marker::FixPos(127), uint(127));
+    assert_eq!(Marker::U8, uint(128));
+    assert_eq!(Marker::U8, uint(255));
+    assert_eq!(Marker::U16, uint(256));
+    assert_eq!(Marker::U32, uint(65536));
+    assert_eq!(Marker::U64, uint(4294967296));
+
+    let sint = |v| marker_of(&Value::Integer(Integer::I64(v)));
+    assert_eq!(Marker::FixNeg(-32), sint(-32));
+    assert_eq!(Marker::I8, sint(-33));
+    assert_eq!(Marker::I16, sint(-129));
+    assert_eq!(Marker::I32, sint(-32769));
+    assert_eq!(Marker::I64, sint(-2147483649));
+}
+
+#[test]
+fn pass_marker_of_lengths() {
+    use std::iter;
+    use rmp_serde::value::marker_of;
+
+    let string = |len| marker_of(&Value::String(iter::repeat('a').take(len).collect()));
+    assert_eq!(Marker::FixStr(31), string(31));
+    assert_eq!(Marker::Str8, string(32));
+    assert_eq!(Marker::Str16, string(256));
+    assert_eq!(Marker::Str32, string(65536));
+
+    assert_eq!(Marker::Bin8, marker_of(&Value::Binary(vec![0; 255])));
+    assert_eq!(Marker::Bin16, marker_of(&Value::Binary(vec![0; 256])));
+
+    let array = |len| marker_of(&Value::Array(vec![Value::Nil; len]));
+    assert_eq!(Marker::FixArray(15), array(15));
+    assert_eq!(Marker::Array16, array(16));
+    assert_eq!(Marker::Array32, array(65536));
+
+    let map = |len| marker_of(&Value::Map(vec![(Value::Nil, Value::Nil); len]));
+    assert_eq!(Marker::FixMap(15), map(15));
+    assert_eq!(Marker::Map16, map(16));
+
+    assert_eq!(Marker::FixExt4, marker_of(&Value::Ext(1, vec![0; 4])));
+    assert_eq!(Marker::Ext8, marker_of(&Value::Ext(1, vec![0; 3])));
+}
+
+#[test]
+fn pass_bytes_into_binary() {
+    use serde::bytes::{ByteBuf, Bytes};
+    use rmp_serde::value::{from_value, to_value};
+
+    let data: &[u8] = &[0xcc, 0x80];
+    assert_eq!(Value::Binary(vec![0xcc, 0x80]), to_value(&Bytes::from(data)));
+
+    let boxed: Box<[u8]> = vec![0xcc, 0x80].into_boxed_slice();
+    assert_eq!(Value::Binary(vec![0xcc, 0x80]), to_value(&Bytes::from(&boxed[..])));
+
+    let buf: ByteBuf = from_value(to_value(&Bytes::from(data))).unwrap();
+    assert_eq!(data, &buf[..]);
+}
+
+#[test]
+fn pass_plain_slice_into_array() {
+    use rmp_serde::value::to_value;
+
+    // Without the `Bytes` wrapper serde serializes byte slices element-wise.
+    let data: &[u8] = &[1, 2];
+    let expected = Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(2))]);
+
+    assert_eq!(expected, to_value(&data));
+}
+
+/// Unit-only enum, implemented the way `#[derive]` does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Color {
+    Red,
+    Green,
+}
+
+impl serde::Serialize for Color {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        match *self {
+            Color::Red => ser.serialize_unit_variant("Color", 0, "Red"),
+            Color::Green => ser.serialize_unit_variant("Color", 1, "Green"),
+        }
+    }
+}
+
+impl Deserialize for Color {
+    fn deserialize<D>(de: &mut D) -> result::Result<Color, D::Error>
+        where D: serde::Deserializer
+    {
+        /// Accepts the variant by name or by index, as derived impls do.
+        struct Variant(Color);
+
+        impl Deserialize for Variant {
+            fn deserialize<D>(de: &mut D) -> result::Result<Variant, D::Error>
+                where D: serde::Deserializer
+            {
+                struct Visitor;
+
+                impl serde::de::Visitor for Visitor {
+                    type Value = Variant;
+
+                    fn visit_usize<E>(&mut self, value: usize) -> result::Result<Variant, E>
+                        where E: serde::de::Error
+                    {
+                        match value {
+                            0 => Ok(Variant(Color::Red)),
+                            1 => Ok(Variant(Color::Green)),
+                            _ => Err(serde::de::Error::invalid_value("unknown variant")),
+                        }
+                    }
+
+                    fn visit_str<E>(&mut self, value: &str) -> result::Result<Variant, E>
+                        where E: serde::de::Error
+                    {
+                        match value {
+                            "Red" => Ok(Variant(Color::Red)),
+                            "Green" => Ok(Variant(Color::Green)),
+                            _ => Err(serde::de::Error::invalid_value("unknown variant")),
+                        }
+                    }
+                }
+
+                de.deserialize(Visitor)
+            }
+        }
+
+        struct Visitor;
+
+        impl serde::de::EnumVisitor for Visitor {
+            type Value = Color;
+
+            fn visit<V>(&mut self, mut visitor: V) -> result::Result<Color, V::Error>
+                where V: serde::de::VariantVisitor
+            {
+                let Variant(color) = try!(visitor.visit_variant());
+
+                try!(visitor.visit_unit());
+                Ok(color)
+            }
+        }
+
+        de.deserialize_enum("Color", &["Red", "Green"], Visitor)
+    }
+}
+
+#[test]
+fn pass_enum_keyed_map_round_trip() {
+    use std::collections::{BTreeMap, HashMap};
+    use rmp_serde::value::{from_value, to_value};
+
+    let mut map = HashMap::new();
+    map.insert(Color::Red, 1u32);
+    map.insert(Color::Green, 2u32);
+
+    let value = to_value(&map);
+    let keys: BTreeMap<Value, Value> = value.clone().into_btree_map().unwrap();
+    let keys: Vec<&Value> = keys.keys().collect();
+
+    assert_eq!(vec![&Value::String("Green".to_string()), &Value::String("Red".to_string())], keys);
+    assert_eq!(map, from_value(value).unwrap());
+}
+
+#[test]
+fn pass_unit_variant_outside_key_into_nil() {
+    use rmp_serde::value::to_value;
+
+    assert_eq!(Value::Nil, to_value(&Color::Red));
+    assert_eq!(Value::Array(vec![Value::Nil]), to_value(&vec![Color::Red]));
+}
+
+struct User {
+    id: u32,
+    name: String,
+    email: String,
+    admin: bool,
+}
+
+impl serde::Serialize for User {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        struct Visitor<'a>(&'a User, usize);
+
+        impl<'a> serde::ser::MapVisitor for Visitor<'a> {
+            fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                self.1 += 1;
+                match self.1 {
+                    1 => ser.serialize_struct_elt("id", self.0.id).map(Some),
+                    2 => ser.serialize_struct_elt("name", &self.0.name).map(Some),
+                    3 => ser.serialize_struct_elt("email", &self.0.email).map(Some),
+                    4 => ser.serialize_struct_elt("admin", self.0.admin).map(Some),
+                    _ => Ok(None),
+                }
+            }
+
+            fn len(&self) -> Option<usize> {
+                Some(4)
+            }
+        }
+
+        ser.serialize_struct("User", Visitor(self, 0))
+    }
+}
+
+#[test]
+fn pass_field_filter_struct() {
+    use serde::Serialize;
+    use rmp_serde::value::FieldFilterSerializer;
+    use rmp_serde::value::encode::Serializer;
+
+    let user = User {
+        id: 42,
+        name: "John".to_string(),
+        email: "john@example.com".to_string(),
+        admin: false,
+    };
+
+    let mut ser = Serializer::new();
+    user.serialize(&mut FieldFilterSerializer::new(&mut ser, &["email", "name"])).unwrap();
+
+    let expected = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+        (Value::String("email".to_string()), Value::String("john@example.com".to_string())),
+    ]);
+
+    assert_eq!(expected, ser.unwrap());
+}
+
+#[test]
+fn pass_field_filter_outermost_only() {
+    use std::collections::BTreeMap;
+    use serde::Serialize;
+    use rmp_serde::value::FieldFilterSerializer;
+    use rmp_serde::value::encode::Serializer;
+
+    let mut inner = BTreeMap::new();
+    inner.insert("id", 1);
+
+    let mut outer = BTreeMap::new();
+    outer.insert("id", inner.clone());
+    outer.insert("parent", inner);
+
+    let mut ser = Serializer::new();
+    outer.serialize(&mut FieldFilterSerializer::new(&mut ser, &["parent"])).unwrap();
+
+    let expected = Value::Map(vec![
+        (Value::String("parent".to_string()), Value::Map(vec![
+            (Value::String("id".to_string()), Value::Integer(Integer::I64(1))),
+        ])),
+    ]);
+
+    assert_eq!(expected, ser.unwrap());
+}
+
+#[test]
+fn pass_registered_ext_decoder() {
+    use std::fmt::Write;
+
+    let value = Value::Array(vec![
+        Value::Integer(Integer::U64(1)),
+        Value::Ext(5, vec![0xde, 0xad, 0xbe, 0xef]),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.register_ext(5, Box::new(|data: &[u8]| {
+        let mut s = String::new();
+        for byte in data {
+            write!(s, "{:02x}", byte).unwrap();
+        }
+        Ok(Value::String(s))
+    }));
+
+    let actual: (u32, String) = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!((1, "deadbeef".to_string()), actual);
+}
+
+#[test]
+fn fail_unregistered_ext() {
+    let mut de = Deserializer::new(Value::Ext(5, vec![0x00]));
+    de.register_ext(6, Box::new(|_: &[u8]| Ok(Value::Nil)));
+
+    let res: Result<String> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("ext of type 5") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_ext_decoder_error() {
+    let mut de = Deserializer::new(Value::Ext(5, vec![0x00]));
+    de.register_ext(5, Box::new(|_: &[u8]| Err(Error::LengthMismatch(16))));
+
+    let res: Result<String> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::LengthMismatch(16)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_resolved_references() {
+    use std::collections::BTreeMap;
+
+    let value = Value::Array(vec![
+        Value::Ext(0x10, vec![0x01]),
+        Value::String("bob".to_string()),
+        Value::Ext(0x10, vec![0x01, 0x00]),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.set_resolver(0x10, Box::new(|id| {
+        let mut objects = BTreeMap::new();
+        objects.insert(1, "alice");
+        objects.insert(256, "carol");
+
+        objects.get(&id).map(|name| Value::String(name.to_string()))
+    }));
+
+    let actual: Vec<String> = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(vec!["alice", "bob", "carol"], actual);
+}
+
+#[test]
+fn fail_unresolved_reference() {
+    let mut de = Deserializer::new(Value::Ext(0x10, vec![0x02]));
+    de.set_resolver(0x10, Box::new(|_| None));
+
+    let res: Result<String> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("unresolved reference 2") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let mut de = Deserializer::new(Value::Ext(0x10, vec![0x00; 9]));
+    de.set_resolver(0x10, Box::new(|_| Some(Value::Nil)));
+
+    let res: Result<Option<String>> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::LengthMismatch(9)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_from_value_skipping_version_prefix() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::from_value_skipping_prefix;
+
+    let frame = Value::Array(vec![
+        Value::Integer(Integer::U64(2)),
+        Value::Map(vec![
+            (Value::String("id".to_string()), Value::Integer(Integer::U64(42))),
+        ]),
+    ]);
+
+    let payload: BTreeMap<String, u32> = from_value_skipping_prefix(frame, 1).unwrap();
+
+    assert_eq!(Some(&42), payload.get("id"));
+}
+
+#[test]
+fn pass_from_value_skipping_prefix_many_remaining() {
+    use rmp_serde::value::from_value_skipping_prefix;
+
+    let frame = Value::Array(vec![
+        Value::Integer(Integer::U64(2)),
+        Value::Integer(Integer::U64(3)),
+        Value::String("le message".to_string()),
+    ]);
+
+    let payload: (u32, String) = from_value_skipping_prefix(frame, 1).unwrap();
+
+    assert_eq!((3, "le message".to_string()), payload);
+}
+
+#[test]
+fn fail_from_value_skipping_prefix_too_short() {
+    use rmp_serde::value::from_value_skipping_prefix;
+
+    let frame = Value::Array(vec![Value::Integer(Integer::U64(2))]);
+
+    match from_value_skipping_prefix::<u32>(frame, 2).err() {
+        Some(Error::LengthMismatch(1)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    match from_value_skipping_prefix::<u32>(Value::Nil, 1).err() {
+        Some(Error::TypeMismatch(Marker::Array32)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_empty_as_nil() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions { empty_as_nil: true, ..SerializerOptions::default() };
+
+    assert_eq!(Value::Nil, to_value_with("", opts.clone()).unwrap());
+    assert_eq!(Value::Nil, to_value_with(&Vec::<u32>::new(), opts.clone()).unwrap());
+    assert_eq!(Value::Nil, to_value_with(&BTreeMap::<String, u32>::new(), opts.clone()).unwrap());
+    assert_eq!(Value::String("x".to_string()), to_value_with("x", opts.clone()).unwrap());
+
+    // Empty map keys are kept as strings.
+    let mut map = BTreeMap::new();
+    map.insert("", "");
+    let expected = Value::Map(vec![(Value::String("".to_string()), Value::Nil)]);
+    assert_eq!(expected, to_value_with(&map, opts).unwrap());
+}
+
+#[test]
+fn pass_empty_as_nil_round_trip() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions { empty_as_nil: true, ..SerializerOptions::default() };
+    let expected = (String::new(), Vec::<u32>::new(), BTreeMap::<String, u32>::new(), Some(Vec::<u32>::new()));
+
+    let value = to_value_with(&expected, opts).unwrap();
+    assert_eq!(Value::Array(vec![Value::Nil, Value::Nil, Value::Nil, Value::Nil]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_nil_as_empty(true);
+    let actual: (String, Vec<u32>, BTreeMap<String, u32>, Option<Vec<u32>>) =
+        Deserialize::deserialize(&mut de).unwrap();
+
+    // An empty `Some` can not be told apart from `None` anymore.
+    assert_eq!((String::new(), Vec::new(), BTreeMap::new(), None), actual);
+}
+
+#[test]
+fn fail_nil_as_empty_when_disabled() {
+    let res: Result<Vec<u32>> = Deserialize::deserialize(&mut Deserializer::new(Value::Nil));
+
+    assert!(res.is_err());
+}
+
+/// Zero-field struct, implemented the way `#[derive]` does for `struct Empty {}`.
+#[derive(Debug, PartialEq)]
+struct Empty {}
+
+impl serde::Serialize for Empty {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        struct Visitor;
+
+        impl serde::ser::MapVisitor for Visitor {
+            fn visit<S>(&mut self, _ser: &mut S) -> result::Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                Ok(None)
+            }
+
+            fn len(&self) -> Option<usize> {
+                Some(0)
+            }
+        }
+
+        ser.serialize_struct("Empty", Visitor)
+    }
+}
+
+impl Deserialize for Empty {
+    fn deserialize<D>(de: &mut D) -> result::Result<Empty, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = Empty;
+
+            fn visit_seq<V>(&mut self, mut visitor: V) -> result::Result<Empty, V::Error>
+                where V: serde::de::SeqVisitor
+            {
+                try!(visitor.end());
+                Ok(Empty {})
+            }
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Empty, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                try!(visitor.end());
+                Ok(Empty {})
+            }
+        }
+
+        de.deserialize_struct("Empty", &[], Visitor)
+    }
+}
+
+#[test]
+fn pass_zero_field_struct_round_trip() {
+    use std::io::Cursor;
+    use serde::Serialize;
+    use rmp_serde::value::{SerializerOptions, from_value, to_value, to_value_with};
+
+    assert_eq!(Value::Map(vec![]), to_value(&Empty {}));
+    assert_eq!(Empty {}, from_value(Value::Map(vec![])).unwrap());
+
+    let opts = SerializerOptions { compact_structs: true, ..SerializerOptions::default() };
+    assert_eq!(Value::Array(vec![]), to_value_with(&Empty {}, opts).unwrap());
+    assert_eq!(Empty {}, from_value(Value::Array(vec![])).unwrap());
+
+    // Nested empty structs must not disturb the state of their container.
+    let expected = Value::Array(vec![Value::Map(vec![]), Value::Integer(Integer::U64(1)), Value::Map(vec![])]);
+    assert_eq!(expected, to_value(&(Empty {}, 1u8, Empty {})));
+
+    let mut buf = Vec::new();
+    Empty {}.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+    assert_eq!(vec![0x90], buf);
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    assert_eq!(Empty {}, Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_phantom_data_round_trip() {
+    use std::io::Cursor;
+    use std::marker::PhantomData;
+    use serde::Serialize;
+    use rmp_serde::value::{from_value, to_value};
+
+    let value = to_value(&(42u8, PhantomData::<String>));
+    assert_eq!(Value::Array(vec![Value::Integer(Integer::U64(42)), Value::Nil]), value);
+
+    let actual: (u8, PhantomData<String>) = from_value(value).unwrap();
+    assert_eq!((42, PhantomData), actual);
+
+    let mut buf = Vec::new();
+    (42u8, PhantomData::<String>).serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+    assert_eq!(vec![0x92, 0x2a, 0x90], buf);
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    let actual: (u8, PhantomData<String>) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!((42, PhantomData), actual);
+}
+
+fn path_fixture() -> Value {
+    // {"a": {"b": [{"c": 42}, "x"]}, "7": "seven", 5: "five", -1: "minus one"}
+    Value::Map(vec![
+        (Value::String("a".to_string()), Value::Map(vec![
+            (Value::String("b".to_string()), Value::Array(vec![
+                Value::Map(vec![(Value::String("c".to_string()), Value::Integer(Integer::U64(42)))]),
+                Value::String("x".to_string()),
+            ])),
+        ])),
+        (Value::String("7".to_string()), Value::String("seven".to_string())),
+        (Value::Integer(Integer::U64(5)), Value::String("five".to_string())),
+        (Value::Integer(Integer::I64(-1)), Value::String("minus one".to_string())),
+    ])
+}
+
+#[test]
+fn pass_get_path() {
+    use rmp_serde::value::get_path;
+
+    let value = path_fixture();
+
+    assert_eq!(Some(&Value::Integer(Integer::U64(42))), get_path(&value, "a.b[0].c"));
+    assert_eq!(Some(&Value::Integer(Integer::U64(42))), get_path(&value, ".a.b[0].c"));
+    assert_eq!(Some(&Value::String("x".to_string())), get_path(&value, "a.b[1]"));
+    assert_eq!(Some(&value), get_path(&value, ""));
+}
+
+#[test]
+fn pass_get_path_numeric_map_keys() {
+    use rmp_serde::value::get_path;
+
+    let value = path_fixture();
+
+    assert_eq!(Some(&Value::String("seven".to_string())), get_path(&value, "7"));
+    assert_eq!(Some(&Value::String("five".to_string())), get_path(&value, "[5]"));
+    assert_eq!(Some(&Value::String("minus one".to_string())), get_path(&value, "[-1]"));
+    assert_eq!(None, get_path(&value, "5"));
+    assert_eq!(None, get_path(&value, "[7]"));
+}
+
+#[test]
+fn fail_get_path_missing() {
+    use rmp_serde::value::get_path;
+
+    let value = path_fixture();
+
+    assert_eq!(None, get_path(&value, "a.b[2]"));
+    assert_eq!(None, get_path(&value, "a.b[-1]"));
+    assert_eq!(None, get_path(&value, "a.missing"));
+    assert_eq!(None, get_path(&value, "a.b[0].c.d"));
+    assert_eq!(None, get_path(&value, "a..b"));
+    assert_eq!(None, get_path(&value, "a.b[0"));
+    assert_eq!(None, get_path(&value, "a."));
+}
+
+#[test]
+fn pass_i64_from_either_integer_variant() {
+    use std::i64;
+    use rmp_serde::value::from_value;
+
+    assert_eq!(i64::MAX, from_value::<i64>(Value::Integer(Integer::U64(i64::MAX as u64))).unwrap());
+    assert_eq!(i64::MIN, from_value::<i64>(Value::Integer(Integer::I64(i64::MIN))).unwrap());
+    assert_eq!(0, from_value::<i64>(Value::Integer(Integer::U64(0))).unwrap());
+    assert_eq!(-1, from_value::<i8>(Value::Integer(Integer::I64(-1))).unwrap());
+    assert_eq!(127, from_value::<i8>(Value::Integer(Integer::U64(127))).unwrap());
+}
+
+#[test]
+fn fail_i64_from_too_large_u64() {
+    use rmp_serde::value::from_value;
+
+    match from_value::<i64>(Value::Integer(Integer::U64(i64::max_value() as u64 + 1))).err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("out of range for i64") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_u64_from_either_integer_variant() {
+    use std::{i64, u64};
+    use rmp_serde::value::from_value;
+
+    assert_eq!(u64::MAX, from_value::<u64>(Value::Integer(Integer::U64(u64::MAX))).unwrap());
+    assert_eq!(i64::MAX as u64, from_value::<u64>(Value::Integer(Integer::I64(i64::MAX))).unwrap());
+    assert_eq!(0, from_value::<u64>(Value::Integer(Integer::I64(0))).unwrap());
+    assert_eq!(255, from_value::<u8>(Value::Integer(Integer::I64(255))).unwrap());
+}
+
+#[test]
+fn fail_u64_from_negative_i64() {
+    use rmp_serde::value::from_value;
+
+    match from_value::<u64>(Value::Integer(Integer::I64(-1))).err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("out of range for u64") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_streaming_serializer_flushes_each_record() {
+    use serde::Serialize;
+    use rmp_serde::value::StreamingSerializer;
+
+    let mut count = 0;
+    let mut last = None;
+
+    {
+        let mut ser = StreamingSerializer::new(|value| {
+            count += 1;
+            last = Some(value);
+        });
+
+        for id in 0..1000u32 {
+            (id, "record").serialize(&mut ser).unwrap();
+        }
+    }
+
+    let expected = Value::Array(vec![Value::Integer(Integer::U64(999)), Value::String("record".to_string())]);
+
+    assert_eq!(1000, count);
+    assert_eq!(Some(expected), last);
+}
+
+#[test]
+fn pass_streaming_serializer_mixed_values() {
+    use std::collections::BTreeMap;
+    use serde::Serialize;
+    use rmp_serde::value::StreamingSerializer;
+
+    let mut values = Vec::new();
+
+    {
+        let mut map = BTreeMap::new();
+        map.insert("id", 1);
+
+        let mut ser = StreamingSerializer::new(|value| values.push(value));
+        42u8.serialize(&mut ser).unwrap();
+        None::<u8>.serialize(&mut ser).unwrap();
+        Some("x").serialize(&mut ser).unwrap();
+        map.serialize(&mut ser).unwrap();
+        Point { x: 5, label: None }.serialize(&mut ser).unwrap();
+    }
+
+    assert_eq!(5, values.len());
+    assert_eq!(Value::Integer(Integer::U64(42)), values[0]);
+    assert_eq!(Value::Nil, values[1]);
+    assert_eq!(Value::String("x".to_string()), values[2]);
+    assert_eq!(Value::Map(vec![(Value::String("id".to_string()), Value::Integer(Integer::I64(1)))]), values[3]);
+    assert_eq!(Value::Map(vec![
+        (Value::String("x".to_string()), Value::Integer(Integer::I64(5))),
+        (Value::String("label".to_string()), Value::Nil),
+    ]), values[4]);
+}
+
+#[test]
+fn pass_streaming_serializer_after_error() {
+    use serde::Serialize;
+    use rmp_serde::value::StreamingSerializer;
+
+    let mut values = Vec::new();
+
+    {
+        let mut ser = StreamingSerializer::new(|value| values.push(value));
+        assert!(vec![Failing(false), Failing(true)].serialize(&mut ser).is_err());
+        1u8.serialize(&mut ser).unwrap();
+    }
+
+    assert_eq!(vec![Value::Integer(Integer::U64(1))], values);
+}
+
+#[test]
+fn pass_streaming_serializer_enum_same_as_plain() {
+    use serde::Serialize;
+    use rmp_serde::value::{SerializerOptions, StreamingSerializer, to_value_with};
+
+    let opts = SerializerOptions { enum_as_index_map: true, ..SerializerOptions::default() };
+
+    let shapes = [
+        Geometry::Point,
+        Geometry::Circle(5),
+        Geometry::Line(1, 2),
+        Geometry::Rect { w: 3, h: 4 },
+    ];
+
+    let mut values = Vec::new();
+
+    {
+        let mut ser = StreamingSerializer::with_options(|value| values.push(value), opts.clone());
+        for shape in &shapes {
+            shape.serialize(&mut ser).unwrap();
+        }
+    }
+
+    let expected: Vec<Value> = shapes.iter().map(|shape| to_value_with(shape, opts.clone()).unwrap()).collect();
+    assert_eq!(expected, values);
+    assert_eq!(Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(5)))]), values[1]);
+}
+
+#[test]
+fn pass_string_from_utf8_binary() {
+    use rmp_serde::value::from_value;
+
+    let value = Value::Binary("le message".as_bytes().to_vec());
+
+    assert_eq!("le message".to_string(), from_value::<String>(value).unwrap());
+}
+
+#[test]
+fn fail_string_from_invalid_utf8_binary() {
+    use rmp_serde::value::from_value;
+
+    let value = Value::Binary(vec![0x6c, 0x65, 0xff, 0x6d]);
+
+    match from_value::<String>(value).err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("invalid UTF-8 in binary at byte 2") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn pass_bytes_from_binary_unchanged() {
+    use serde::bytes::ByteBuf;
+    use rmp_serde::value::from_value;
+
+    let buf: ByteBuf = from_value(Value::Binary(vec![0xff, 0x00])).unwrap();
+
+    assert_eq!(&[0xff, 0x00][..], &buf[..]);
+}
+
+#[test]
+fn pass_btree_map_key_order_preserved() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::{from_value, to_value};
+
+    let map: BTreeMap<String, u32> = vec![("b", 2), ("a", 1), ("c", 3), ("B", 0)].into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+    let value = to_value(&map);
+    let keys: Vec<Value> = match value {
+        Value::Map(ref map) => map.iter().map(|&(ref k, _)| k.clone()).collect(),
+        ref other => panic!("unexpected value: {:?}", other),
+    };
+
+    let expected: Vec<Value> = map.keys().map(|k| Value::String(k.clone())).collect();
+    assert_eq!(expected, keys);
+
+    let actual: BTreeMap<String, u32> = from_value(value).unwrap();
+    assert_eq!(map.keys().collect::<Vec<_>>(), actual.keys().collect::<Vec<_>>());
+    assert_eq!(map, actual);
+}
+
+/// Map keeping its entries in insertion order, standing in for `IndexMap`, which serde 0.7 has no
+/// impls for.
+#[derive(Debug, PartialEq)]
+struct InsertionOrderMap(Vec<(String, u32)>);
+
+impl Deserialize for InsertionOrderMap {
+    fn deserialize<D>(de: &mut D) -> result::Result<InsertionOrderMap, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = InsertionOrderMap;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<InsertionOrderMap, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                let mut entries = Vec::new();
+                while let Some(entry) = try!(visitor.visit()) {
+                    entries.push(entry);
+                }
+                try!(visitor.end());
+
+                Ok(InsertionOrderMap(entries))
+            }
+        }
+
+        de.deserialize_map(Visitor)
+    }
+}
+
+#[test]
+fn pass_map_keys_visited_in_stored_order() {
+    use rmp_serde::value::from_value;
+
+    let value = Value::Map(vec![
+        (Value::String("zeta".to_string()), Value::Integer(Integer::U64(1))),
+        (Value::String("alpha".to_string()), Value::Integer(Integer::U64(2))),
+        (Value::String("mid".to_string()), Value::Integer(Integer::U64(3))),
+    ]);
+
+    let actual: InsertionOrderMap = from_value(value).unwrap();
+    assert_eq!(InsertionOrderMap(vec![
+        ("zeta".to_string(), 1),
+        ("alpha".to_string(), 2),
+        ("mid".to_string(), 3),
+    ]), actual);
+}
+
+#[test]
+fn pass_btree_map_key_order_preserved_with_options() {
+    use std::collections::BTreeMap;
+    use rmp_serde::value::{SerializerOptions, from_value, to_value_with};
+
+    // Negative keys stay `I64` while the others are narrowed to `U64`, which must not reorder
+    // them when sorting.
+    let map: BTreeMap<i64, bool> = vec![(-5, true), (-1, false), (0, true), (3, false), (42, true)]
+        .into_iter()
+        .collect();
+
+    let opts = SerializerOptions { canonical: true, narrow_ints: true, ..SerializerOptions::default() };
+    let value = to_value_with(&map, opts).unwrap();
+
+    let keys: Vec<Value> = match value {
+        Value::Map(ref map) => map.iter().map(|&(ref k, _)| k.clone()).collect(),
+        ref other => panic!("unexpected value: {:?}", other),
+    };
+
+    assert_eq!(vec![
+        Value::Integer(Integer::I64(-5)),
+        Value::Integer(Integer::I64(-1)),
+        Value::Integer(Integer::U64(0)),
+        Value::Integer(Integer::U64(3)),
+        Value::Integer(Integer::U64(42)),
+    ], keys);
+
+    let actual: BTreeMap<i64, bool> = from_value(value).unwrap();
+    assert_eq!(map, actual);
+}
+
+#[test]
+fn pass_from_value_partial_returns_tail() {
+    use rmp_serde::value::from_value_partial;
+
+    let value = Value::Array(vec![
+        Value::Integer(Integer::U64(1)),
+        Value::String("le message".to_string()),
+        Value::Boolean(true),
+        Value::Nil,
+    ]);
+
+    let (head, tail): ((u32, String), Option<Value>) = from_value_partial(value).unwrap();
+
+    assert_eq!((1, "le message".to_string()), head);
+    assert_eq!(Some(Value::Array(vec![Value::Boolean(true), Value::Nil])), tail);
+}
+
+#[test]
+fn pass_from_value_partial_consumed_entirely() {
+    use rmp_serde::value::from_value_partial;
+
+    let value = Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(2))]);
+
+    let (head, tail): ((u32, u32), Option<Value>) = from_value_partial(value.clone()).unwrap();
+    assert_eq!(((1, 2), None), (head, tail));
+
+    let (head, tail): (Vec<u32>, Option<Value>) = from_value_partial(value).unwrap();
+    assert_eq!((vec![1, 2], None), (head, tail));
+
+    let (head, tail): (String, Option<Value>) = from_value_partial(Value::String("x".to_string())).unwrap();
+    assert_eq!(("x".to_string(), None), (head, tail));
+}
+
+#[test]
+fn fail_from_value_partial_too_short() {
+    use rmp_serde::value::from_value_partial;
+
+    let value = Value::Array(vec![Value::Integer(Integer::U64(1))]);
+
+    assert!(from_value_partial::<(u32, u32)>(value).is_err());
+}
+
+#[test]
+fn pass_enum_from_int() {
+    use rmp_serde::value::to_value;
+
+    for &color in &[Color::Red, Color::Green] {
+        let mut de = Deserializer::new(to_value(&(color as u32)));
+        de.set_enum_from_int(true);
+
+        assert_eq!(color, Deserialize::deserialize(&mut de).unwrap());
+    }
+
+    // Variant names are still accepted.
+    let mut de = Deserializer::new(Value::String("Green".to_string()));
+    de.set_enum_from_int(true);
+    assert_eq!(Color::Green, Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn fail_enum_from_int_out_of_range() {
+    let mut de = Deserializer::new(Value::Integer(Integer::U64(2)));
+    de.set_enum_from_int(true);
+
+    let res: Result<Color> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("variant index 2 is out of range for enum Color") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let mut de = Deserializer::new(Value::Integer(Integer::I64(-1)));
+    de.set_enum_from_int(true);
+
+    let res: Result<Color> = Deserialize::deserialize(&mut de);
+    assert!(res.is_err());
+}
+
+#[test]
+fn fail_enum_from_int_when_disabled() {
+    let res: Result<Color> = Deserialize::deserialize(&mut Deserializer::new(Value::Integer(Integer::U64(0))));
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_enum_as_int_round_trip() {
+    use std::collections::HashMap;
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions { enum_as_int: true, ..SerializerOptions::default() };
+
+    let value = to_value_with(&vec![Color::Green, Color::Red], opts.clone()).unwrap();
+    assert_eq!(Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(0))]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_enum_from_int(true);
+    let actual: Vec<Color> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(vec![Color::Green, Color::Red], actual);
+
+    let mut map = HashMap::new();
+    map.insert(Color::Green, 2u32);
+
+    let value = to_value_with(&map, opts).unwrap();
+    assert_eq!(Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(2)))]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_enum_from_int(true);
+    let actual: HashMap<Color, u32> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(map, actual);
+}
+
+#[test]
+fn pass_array_as_bytes() {
+    let mut de = Deserializer::new(Value::Binary(vec![0xcc, 0x80, 0x00]));
+    de.set_array_as_bytes(true);
+
+    let actual: Vec<u8> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(vec![0xcc, 0x80, 0x00], actual);
+
+    let value = Value::Array(vec![
+        Value::Integer(Integer::U64(0xcc)),
+        Value::Integer(Integer::U64(0x80)),
+        Value::Integer(Integer::U64(0x00)),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.set_array_as_bytes(true);
+
+    let actual: Vec<u8> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(vec![0xcc, 0x80, 0x00], actual);
+}
+
+#[test]
+fn fail_array_as_bytes_out_of_range() {
+    let value = Value::Array(vec![
+        Value::Integer(Integer::U64(0xcc)),
+        Value::Integer(Integer::U64(256)),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.set_array_as_bytes(true);
+
+    let res: Result<Vec<u8>> = Deserialize::deserialize(&mut de);
+    assert!(res.is_err());
+
+    let mut de = Deserializer::new(Value::Array(vec![Value::Integer(Integer::I64(-1))]));
+    de.set_array_as_bytes(true);
+
+    let res: Result<Vec<u8>> = Deserialize::deserialize(&mut de);
+    assert!(res.is_err());
+}
+
+#[test]
+fn fail_binary_into_vec_without_array_as_bytes() {
+    let mut de = Deserializer::new(Value::Binary(vec![0xcc, 0x80]));
+
+    let res: Result<Vec<u8>> = Deserialize::deserialize(&mut de);
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_tagged_elements_round_trip() {
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions { tagged_elements: true, ..SerializerOptions::default() };
+
+    let elements = (42u32, "le message".to_string(), true);
+
+    let value = to_value_with(&elements, opts.clone()).unwrap();
+    assert_eq!(Value::Array(vec![
+        Value::Array(vec![Value::Integer(Integer::U64(2)), Value::Integer(Integer::U64(42))]),
+        Value::Array(vec![Value::Integer(Integer::U64(4)), Value::String("le message".to_string())]),
+        Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Boolean(true)]),
+    ]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_tagged_elements(true);
+
+    let actual: (u32, String, bool) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(elements, actual);
+
+    // Nested sequences are tagged as well.
+    let nested = vec![vec![Some(1u8)], vec![None]];
+
+    let value = to_value_with(&nested, opts).unwrap();
+    assert_eq!(Value::Array(vec![
+        Value::Array(vec![Value::Integer(Integer::U64(6)), Value::Array(vec![
+            Value::Array(vec![Value::Integer(Integer::U64(2)), Value::Integer(Integer::U64(1))]),
+        ])]),
+        Value::Array(vec![Value::Integer(Integer::U64(6)), Value::Array(vec![
+            Value::Array(vec![Value::Integer(Integer::U64(0)), Value::Nil]),
+        ])]),
+    ]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_tagged_elements(true);
+
+    let actual: Vec<Vec<Option<u8>>> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(nested, actual);
+}
+
+#[test]
+fn fail_tagged_elements_mismatch() {
+    let value = Value::Array(vec![
+        Value::Array(vec![Value::Integer(Integer::U64(4)), Value::Integer(Integer::U64(42))]),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.set_tagged_elements(true);
+
+    let res: Result<Vec<u32>> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("tag 4 does not match integer 42") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let mut de = Deserializer::new(Value::Array(vec![Value::Integer(Integer::U64(42))]));
+    de.set_tagged_elements(true);
+
+    let res: Result<Vec<u32>> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("expected a [tag, element] pair, found integer 42") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+/// An enum with variants of every kind.
+#[derive(Debug, PartialEq)]
+enum Geometry {
+    Point,
+    Circle(u32),
+    Line(u32, u32),
+    Rect { w: u32, h: u32 },
+}
+
+impl serde::Serialize for Geometry {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        struct Line<'a>(&'a u32, &'a u32, u8);
+
+        impl<'a> serde::ser::SeqVisitor for Line<'a> {
+            fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                self.2 += 1;
+                match self.2 {
+                    1 => ser.serialize_tuple_variant_elt(self.0).map(Some),
+                    2 => ser.serialize_tuple_variant_elt(self.1).map(Some),
+                    _ => Ok(None),
+                }
+            }
+
+            fn len(&self) -> Option<usize> {
+                Some(2)
+            }
+        }
+
+        struct Rect<'a>(&'a u32, &'a u32, u8);
+
+        impl<'a> serde::ser::MapVisitor for Rect<'a> {
+            fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                self.2 += 1;
+                match self.2 {
+                    1 => ser.serialize_struct_variant_elt("w", self.0).map(Some),
+                    2 => ser.serialize_struct_variant_elt("h", self.1).map(Some),
+                    _ => Ok(None),
+                }
+            }
+
+            fn len(&self) -> Option<usize> {
+                Some(2)
+            }
+        }
+
+        match *self {
+            Geometry::Point => ser.serialize_unit_variant("Geometry", 0, "Point"),
+            Geometry::Circle(ref r) => ser.serialize_newtype_variant("Geometry", 1, "Circle", r),
+            Geometry::Line(ref a, ref b) => ser.serialize_tuple_variant("Geometry", 2, "Line", Line(a, b, 0)),
+            Geometry::Rect { ref w, ref h } => ser.serialize_struct_variant("Geometry", 3, "Rect", Rect(w, h, 0)),
+        }
+    }
+}
+
+impl Deserialize for Geometry {
+    fn deserialize<D>(de: &mut D) -> result::Result<Geometry, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Variant(usize);
+
+        impl Deserialize for Variant {
+            fn deserialize<D>(de: &mut D) -> result::Result<Variant, D::Error>
+                where D: serde::Deserializer
+            {
+                struct Visitor;
+
+                impl serde::de::Visitor for Visitor {
+                    type Value = Variant;
+
+                    fn visit_usize<E>(&mut self, value: usize) -> result::Result<Variant, E>
+                        where E: serde::de::Error
+                    {
+                        Ok(Variant(value))
+                    }
+                }
+
+                de.deserialize(Visitor)
+            }
+        }
+
+        struct LineVisitor;
+
+        impl serde::de::Visitor for LineVisitor {
+            type Value = Geometry;
+
+            fn visit_seq<V>(&mut self, mut visitor: V) -> result::Result<Geometry, V::Error>
+                where V: serde::de::SeqVisitor
+            {
+                let a = try!(visitor.visit()).unwrap_or(0);
+                let b = try!(visitor.visit()).unwrap_or(0);
+                try!(visitor.end());
+
+                Ok(Geometry::Line(a, b))
+            }
+        }
+
+        struct RectVisitor;
+
+        impl serde::de::Visitor for RectVisitor {
+            type Value = Geometry;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Geometry, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                let (mut w, mut h) = (0, 0);
+                while let Some(key) = try!(visitor.visit_key::<String>()) {
+                    match &key[..] {
+                        "w" => w = try!(visitor.visit_value()),
+                        "h" => h = try!(visitor.visit_value()),
+                        _ => return Err(serde::de::Error::unknown_field("unknown field")),
+                    }
+                }
+                try!(visitor.end());
+
+                Ok(Geometry::Rect { w: w, h: h })
+            }
+        }
+
+        struct Visitor;
+
+        impl serde::de::EnumVisitor for Visitor {
+            type Value = Geometry;
+
+            fn visit<V>(&mut self, mut visitor: V) -> result::Result<Geometry, V::Error>
+                where V: serde::de::VariantVisitor
+            {
+                let Variant(idx) = try!(visitor.visit_variant());
+
+                match idx {
+                    0 => visitor.visit_unit().map(|()| Geometry::Point),
+                    1 => visitor.visit_newtype().map(Geometry::Circle),
+                    2 => visitor.visit_tuple(2, LineVisitor),
+                    3 => visitor.visit_struct(&["w", "h"], RectVisitor),
+                    _ => Err(serde::de::Error::invalid_value("unknown variant")),
+                }
+            }
+        }
+
+        de.deserialize_enum("Geometry", &["Point", "Circle", "Line", "Rect"], Visitor)
+    }
+}
+
+#[test]
+fn pass_enum_as_index_map_round_trip() {
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions { enum_as_index_map: true, ..SerializerOptions::default() };
+
+    let shapes = vec![
+        Geometry::Point,
+        Geometry::Circle(5),
+        Geometry::Line(1, 2),
+        Geometry::Rect { w: 3, h: 4 },
+    ];
+
+    let value = to_value_with(&shapes, opts).unwrap();
+    assert_eq!(Value::Array(vec![
+        Value::Map(vec![(Value::Integer(Integer::U64(0)), Value::Nil)]),
+        Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(5)))]),
+        Value::Map(vec![(Value::Integer(Integer::U64(2)), Value::Array(vec![
+            Value::Integer(Integer::U64(1)),
+            Value::Integer(Integer::U64(2)),
+        ]))]),
+        Value::Map(vec![(Value::Integer(Integer::U64(3)), Value::Map(vec![
+            (Value::String("w".to_string()), Value::Integer(Integer::U64(3))),
+            (Value::String("h".to_string()), Value::Integer(Integer::U64(4))),
+        ]))]),
+    ]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_enum_from_index_map(true);
+
+    let actual: Vec<Geometry> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(shapes, actual);
+}
+
+#[test]
+fn pass_enum_as_index_map_keys() {
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+
+    let opts = SerializerOptions { enum_as_index_map: true, ..SerializerOptions::default() };
+
+    let mut map = HashMap::new();
+    map.insert(Color::Green, 1u32);
+
+    let value = to_value_with(&map, opts).unwrap();
+    assert_eq!(Value::Map(vec![
+        (Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(1))),
+    ]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_enum_from_index_map(true);
+
+    let actual: HashMap<Color, u32> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(map, actual);
+}
+
+#[test]
+fn fail_enum_from_index_map_out_of_range() {
+    let value = Value::Map(vec![(Value::Integer(Integer::U64(4)), Value::Nil)]);
+
+    let mut de = Deserializer::new(value.clone());
+    de.set_enum_from_index_map(true);
+
+    let res: Result<Geometry> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("variant index 4 is out of range for enum Geometry") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let res: Result<Geometry> = Deserialize::deserialize(&mut Deserializer::new(value));
+    assert!(res.is_err());
+}
+
+/// An internally tagged enum, with the index of the variant under `kind`.
+#[derive(Debug, PartialEq)]
+enum Figure {
+    Circle { r: u32 },
+    Empty,
+    Rect { w: u32, h: u32 },
+}
+
+impl serde::Serialize for Figure {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        use std::collections::BTreeMap;
+
+        let mut map = BTreeMap::new();
+        match *self {
+            Figure::Circle { r } => {
+                map.insert("kind", 0);
+                map.insert("r", r);
+            }
+            Figure::Empty => {
+                map.insert("kind", 1);
+            }
+            Figure::Rect { w, h } => {
+                map.insert("kind", 2);
+                map.insert("w", w);
+                map.insert("h", h);
+            }
+        }
+
+        map.serialize(ser)
+    }
+}
+
+impl Deserialize for Figure {
+    fn deserialize<D>(de: &mut D) -> result::Result<Figure, D::Error>
+        where D: serde::Deserializer
+    {
+        use std::collections::BTreeMap;
+
+        struct Variant(usize);
+
+        impl Deserialize for Variant {
+            fn deserialize<D>(de: &mut D) -> result::Result<Variant, D::Error>
+                where D: serde::Deserializer
+            {
+                struct Visitor;
+
+                impl serde::de::Visitor for Visitor {
+                    type Value = Variant;
+
+                    fn visit_usize<E>(&mut self, value: usize) -> result::Result<Variant, E>
+                        where E: serde::de::Error
+                    {
+                        Ok(Variant(value))
+                    }
+                }
+
+                de.deserialize(Visitor)
+            }
+        }
+
+        struct Visitor;
+
+        impl serde::de::EnumVisitor for Visitor {
+            type Value = Figure;
+
+            fn visit<V>(&mut self, mut visitor: V) -> result::Result<Figure, V::Error>
+                where V: serde::de::VariantVisitor
+            {
+                let Variant(idx) = try!(visitor.visit_variant());
+
+                if idx == 1 {
+                    try!(visitor.visit_unit());
+                    return Ok(Figure::Empty);
+                }
+
+                let fields: BTreeMap<String, u32> = try!(visitor.visit_newtype());
+                let field = |name: &'static str| match fields.get(name) {
+                    Some(&value) => Ok(value),
+                    None => Err(serde::de::Error::missing_field(name)),
+                };
+
+                match idx {
+                    0 => Ok(Figure::Circle { r: try!(field("r")) }),
+                    2 => Ok(Figure::Rect { w: try!(field("w")), h: try!(field("h")) }),
+                    _ => Err(serde::de::Error::invalid_value("unknown variant")),
+                }
+            }
+        }
+
+        de.deserialize_enum("Figure", &["Circle", "Empty", "Rect"], Visitor)
+    }
+}
+
+#[test]
+fn pass_enum_tag_round_trip() {
+    use rmp_serde::value::to_value;
+
+    let figures = vec![Figure::Rect { w: 3, h: 4 }, Figure::Empty, Figure::Circle { r: 5 }];
+
+    let value = to_value(&figures);
+    let expected = Value::Array(vec![
+        Value::Map(vec![
+            (Value::String("h".into()), Value::Integer(Integer::U64(4))),
+            (Value::String("kind".into()), Value::Integer(Integer::U64(2))),
+            (Value::String("w".into()), Value::Integer(Integer::U64(3))),
+        ]),
+        Value::Map(vec![
+            (Value::String("kind".into()), Value::Integer(Integer::U64(1))),
+        ]),
+        Value::Map(vec![
+            (Value::String("kind".into()), Value::Integer(Integer::U64(0))),
+            (Value::String("r".into()), Value::Integer(Integer::U64(5))),
+        ]),
+    ]);
+    assert_eq!(expected, value);
+
+    let mut de = Deserializer::new(value);
+    de.set_enum_tag("kind");
+
+    let actual: Vec<Figure> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(figures, actual);
+}
+
+#[test]
+fn fail_enum_tag_out_of_range() {
+    let value = Value::Map(vec![
+        (Value::String("kind".into()), Value::Integer(Integer::U64(3))),
+    ]);
+
+    let mut de = Deserializer::new(value);
+    de.set_enum_tag("kind");
+
+    let res: Result<Figure> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("discriminant 3 is out of range for enum Figure") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn fail_enum_tag_missing() {
+    let value = Value::Map(vec![
+        (Value::String("r".into()), Value::Integer(Integer::U64(5))),
+    ]);
+
+    let mut de = Deserializer::new(value.clone());
+    de.set_enum_tag("kind");
+
+    let res: Result<Figure> = Deserialize::deserialize(&mut de);
+    match res.err() {
+        Some(Error::Syntax(ref msg)) if msg.contains("missing discriminant field `kind` for enum Figure") => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let res: Result<Figure> = Deserialize::deserialize(&mut Deserializer::new(value));
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_recording_replayed_into_value_serializer() {
+    use std::collections::BTreeMap;
+    use serde::Serialize;
+    use rmp_serde::value::{RecordingSerializer, to_value};
+    use rmp_serde::value::encode::Serializer;
+
+    let mut map = BTreeMap::new();
+    map.insert("point", vec![Some(Point { x: -5, label: Some("origin".to_string()) }), None]);
+
+    let expected = (map, Color::Green, 'x', 0.5f32, [1u8, 2], (Msg::Echo("le message".to_string()),));
+
+    let mut rec = RecordingSerializer::new();
+    expected.serialize(&mut rec).unwrap();
+    let recording = rec.unwrap();
+
+    let mut ser = Serializer::new();
+    recording.replay(&mut ser).unwrap();
+
+    assert_eq!(to_value(&expected), ser.unwrap());
+
+    // Recordings can be replayed any number of times.
+    assert_eq!(to_value(&expected), to_value(&recording));
+}
+
+#[test]
+fn pass_recording_replayed_into_msgpack_serializer() {
+    use serde::Serialize;
+    use rmp_serde::value::RecordingSerializer;
+
+    let value = (42u8, vec![Point { x: 1, label: None }], Some("le message"));
+
+    let mut rec = RecordingSerializer::new();
+    value.serialize(&mut rec).unwrap();
+
+    let mut expected = Vec::new();
+    value.serialize(&mut rmp_serde::Serializer::new(&mut expected)).unwrap();
+
+    let mut actual = Vec::new();
+    rec.unwrap().replay(&mut rmp_serde::Serializer::new(&mut actual)).unwrap();
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn pass_recording_matches_trace() {
+    use serde::Serialize;
+    use rmp_serde::value::{RecordingSerializer, Trace, TracingSerializer};
+    use rmp_serde::value::encode::Serializer;
+
+    let value = (Point { x: 1, label: None }, vec![Color::Red]);
+
+    let expected = Trace::new();
+    value.serialize(&mut TracingSerializer::new(&mut Serializer::new(), &expected)).unwrap();
+
+    let mut rec = RecordingSerializer::new();
+    value.serialize(&mut rec).unwrap();
+
+    let actual = Trace::new();
+    rec.unwrap().replay(&mut TracingSerializer::new(&mut Serializer::new(), &actual)).unwrap();
+
+    assert_eq!(expected.ops(), actual.ops());
+}
+
+/// Decodes like derived code does, asking the map visitor for fields that are not present.
+#[derive(Debug, PartialEq)]
+struct Profile {
+    name: String,
+    nickname: Option<String>,
+}
+
+impl Deserialize for Profile {
+    fn deserialize<D>(de: &mut D) -> result::Result<Profile, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor for Visitor {
+            type Value = Profile;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Profile, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                let mut name = None;
+                let mut nickname = None;
+
+                while let Some(key) = try!(visitor.visit_key::<String>()) {
+                    match &key[..] {
+                        "name" => name = Some(try!(visitor.visit_value())),
+                        "nickname" => nickname = Some(try!(visitor.visit_value())),
+                        _ => return Err(serde::de::Error::unknown_field(&key)),
+                    }
+                }
+
+                let name = match name {
+                    Some(name) => name,
+                    None => try!(visitor.missing_field("name")),
+                };
+                let nickname = match nickname {
+                    Some(nickname) => nickname,
+                    None => try!(visitor.missing_field("nickname")),
+                };
+
+                try!(visitor.end());
+                Ok(Profile { name: name, nickname: nickname })
+            }
+        }
+
+        de.deserialize_struct("Profile", &["name", "nickname"], Visitor)
+    }
+}
+
+#[test]
+fn pass_option_field_from_explicit_nil() {
+    use rmp_serde::value::from_value;
+
+    let value = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+        (Value::String("nickname".to_string()), Value::Nil),
+    ]);
+
+    assert_eq!(Profile { name: "John".to_string(), nickname: None }, from_value(value).unwrap());
+}
+
+#[test]
+fn pass_option_field_from_omitted_key() {
+    use std::io::Cursor;
+    use rmp_serde::value::from_value;
+
+    let value = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+    ]);
+
+    let expected = Profile { name: "John".to_string(), nickname: None };
+    assert_eq!(expected, from_value(value).unwrap());
+
+    // {"name": "John"}
+    let buf = [0x81, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa4, 0x4a, 0x6f, 0x68, 0x6e];
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    assert_eq!(expected, Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn fail_non_option_field_from_omitted_key() {
+    use rmp_serde::value::from_value;
+    use rmp_serde::value::decode::Error;
+
+    let value = Value::Map(vec![
+        (Value::String("nickname".to_string()), Value::String("Johnny".to_string())),
+    ]);
+
+    match from_value::<Profile>(value) {
+        Err(Error::Uncategorized(ref msg)) if msg == "missing field" => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+/// Struct with a single `value` field, decoded through `missing_field` when the key is omitted.
+#[derive(Debug, PartialEq)]
+struct Holder<T>(T);
+
+impl<T: Deserialize> Deserialize for Holder<T> {
+    fn deserialize<D>(de: &mut D) -> result::Result<Holder<T>, D::Error>
+        where D: serde::Deserializer
+    {
+        struct Visitor<T>(::std::marker::PhantomData<T>);
+
+        impl<T: Deserialize> serde::de::Visitor for Visitor<T> {
+            type Value = Holder<T>;
+
+            fn visit_map<V>(&mut self, mut visitor: V) -> result::Result<Holder<T>, V::Error>
+                where V: serde::de::MapVisitor
+            {
+                let mut value = None;
+
+                while let Some(key) = try!(visitor.visit_key::<String>()) {
+                    match &key[..] {
+                        "value" => value = Some(try!(visitor.visit_value())),
+                        _ => return Err(serde::de::Error::unknown_field(&key)),
+                    }
+                }
+
+                let value = match value {
+                    Some(value) => value,
+                    None => try!(visitor.missing_field("value")),
+                };
+
+                try!(visitor.end());
+                Ok(Holder(value))
+            }
+        }
+
+        de.deserialize_struct("Holder", &["value"], Visitor(::std::marker::PhantomData))
+    }
+}
+
+#[test]
+fn fail_nil_like_field_from_omitted_key() {
+    use std::io::Cursor;
+    use rmp_serde::decode;
+    use rmp_serde::value::{RawValue, from_value};
+
+    // Unit and raw values decode from nil, but an omitted key is still a missing field.
+    match from_value::<Holder<()>>(Value::Map(vec![])) {
+        Err(Error::Uncategorized(ref msg)) if msg == "missing field" => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match from_value::<Holder<RawValue>>(Value::Map(vec![])) {
+        Err(Error::Uncategorized(ref msg)) if msg == "missing field" => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(Holder(None::<u8>), from_value(Value::Map(vec![])).unwrap());
+
+    // {}
+    let buf = [0x80];
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    match Holder::<()>::deserialize(&mut de).err() {
+        Some(decode::Error::Uncategorized(ref msg)) if msg == "missing field" => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    assert_eq!(Holder(None::<u8>), Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_raw_value_vec_round_trip() {
+    use std::io::Cursor;
+    use serde::Serialize;
+    use rmp::value::Float;
+    use rmp_serde::value::{RawValue, from_value, to_value};
+
+    let vec = vec![
+        Value::Nil,
+        Value::Boolean(true),
+        Value::Integer(Integer::I64(-42)),
+        Value::Integer(Integer::U64(42)),
+        Value::Float(Float::F64(3.1415)),
+        Value::String("le message".to_string()),
+        Value::Binary(vec![0xcc, 0x80]),
+        Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Nil]),
+        Value::Map(vec![(Value::String("key".to_string()), Value::Boolean(false))]),
+    ];
+    let raw: Vec<RawValue> = vec.iter().cloned().map(RawValue).collect();
+
+    assert_eq!(Value::Array(vec.clone()), to_value(&raw));
+    assert_eq!(raw, from_value::<Vec<RawValue>>(to_value(&raw)).unwrap());
+
+    let mut buf = Vec::new();
+    raw.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+
+    let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+    let actual: Vec<RawValue> = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(raw, actual);
+}
+
+#[test]
+fn pass_raw_value_map_round_trip() {
+    use std::collections::HashMap;
+    use rmp_serde::value::{RawValue, from_value, to_value};
+
+    let mut map = HashMap::new();
+    map.insert("id".to_string(), RawValue(Value::Integer(Integer::U64(42))));
+    map.insert("tags".to_string(), RawValue(Value::Array(vec![Value::String("a".to_string())])));
+    map.insert("parent".to_string(), RawValue(Value::Nil));
+
+    let value = to_value(&map);
+    match value {
+        Value::Map(ref entries) => assert_eq!(3, entries.len()),
+        ref value => panic!("expected map, found {:?}", value),
+    }
+
+    assert_eq!(map, from_value::<HashMap<String, RawValue>>(value).unwrap());
+}
+
+#[derive(Debug)]
+struct ChainedError {
+    message: &'static str,
+    cause: Option<Box<ChainedError>>,
+}
+
+impl std::fmt::Display for ChainedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+impl std::error::Error for ChainedError {
+    fn description(&self) -> &str {
+        self.message
+    }
+
+    fn cause(&self) -> Option<&std::error::Error> {
+        self.cause.as_ref().map(|err| &**err as &std::error::Error)
+    }
+}
+
+#[test]
+fn pass_from_error() {
+    use rmp_serde::value::from_error;
+
+    let err = ChainedError { message: "connection refused", cause: None };
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("message".to_string()), Value::String("connection refused".to_string())),
+        (Value::String("source_chain".to_string()), Value::Array(vec![])),
+    ]), from_error(&err));
+}
+
+#[test]
+fn pass_from_error_with_source_chain() {
+    use rmp_serde::value::from_error;
+
+    let err = ChainedError {
+        message: "failed to load config",
+        cause: Some(Box::new(ChainedError {
+            message: "failed to read file",
+            cause: Some(Box::new(ChainedError { message: "permission denied", cause: None })),
+        })),
+    };
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("message".to_string()), Value::String("failed to load config".to_string())),
+        (Value::String("source_chain".to_string()), Value::Array(vec![
+            Value::String("failed to read file".to_string()),
+            Value::String("permission denied".to_string()),
+        ])),
+    ]), from_error(&err));
+}
+
+#[test]
+fn pass_option_vec_from_nil_empty_and_populated() {
+    use std::io::Cursor;
+    use serde::Serialize;
+    use rmp_serde::value::{from_value, to_value};
+
+    let cases = vec![
+        None,
+        Some(vec![]),
+        Some(vec!["le".to_string(), "message".to_string()]),
+    ];
+
+    for expected in cases {
+        let value = to_value(&expected);
+        assert_eq!(expected, from_value::<Option<Vec<String>>>(value).unwrap());
+
+        let mut buf = Vec::new();
+        expected.serialize(&mut rmp_serde::Serializer::new(&mut buf)).unwrap();
+
+        let mut de = rmp_serde::Deserializer::new(Cursor::new(&buf[..]));
+        let actual: Option<Vec<String>> = Deserialize::deserialize(&mut de).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    assert_eq!(None, from_value::<Option<Vec<String>>>(Value::Nil).unwrap());
+    assert_eq!(Some(vec![]), from_value::<Option<Vec<String>>>(Value::Array(vec![])).unwrap());
+}
+
+fn systemtime_round_trip(time: std::time::SystemTime) -> Value {
+    use rmp_serde::value::encode::Serializer;
+    use rmp_serde::value::systemtime;
+
+    let mut ser = Serializer::new();
+    systemtime::serialize(&time, &mut ser).unwrap();
+    let value = ser.unwrap();
+
+    let actual = systemtime::deserialize(&mut Deserializer::new(value.clone())).unwrap();
+    assert_eq!(time, actual);
+
+    value
+}
+
+#[test]
+fn pass_systemtime_epoch_round_trip() {
+    use std::time::UNIX_EPOCH;
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("secs".to_string()), Value::Integer(Integer::I64(0))),
+        (Value::String("nanos".to_string()), Value::Integer(Integer::U64(0))),
+    ]), systemtime_round_trip(UNIX_EPOCH));
+}
+
+#[test]
+fn pass_systemtime_future_round_trip() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("secs".to_string()), Value::Integer(Integer::I64(1500000000))),
+        (Value::String("nanos".to_string()), Value::Integer(Integer::U64(42))),
+    ]), systemtime_round_trip(UNIX_EPOCH + Duration::new(1500000000, 42)));
+}
+
+#[test]
+fn pass_systemtime_pre_epoch_round_trip() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("secs".to_string()), Value::Integer(Integer::I64(-2))),
+        (Value::String("nanos".to_string()), Value::Integer(Integer::U64(500000000))),
+    ]), systemtime_round_trip(UNIX_EPOCH - Duration::new(1, 500000000)));
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("secs".to_string()), Value::Integer(Integer::I64(-86400))),
+        (Value::String("nanos".to_string()), Value::Integer(Integer::U64(0))),
+    ]), systemtime_round_trip(UNIX_EPOCH - Duration::new(86400, 0)));
+}
+
+#[test]
+fn fail_systemtime_nanos_out_of_range() {
+    use rmp_serde::value::systemtime;
+
+    let value = Value::Map(vec![
+        (Value::String("secs".to_string()), Value::Integer(Integer::I64(0))),
+        (Value::String("nanos".to_string()), Value::Integer(Integer::U64(1000000000))),
+    ]);
+
+    assert!(systemtime::deserialize(&mut Deserializer::new(value)).is_err());
+}
+
+#[test]
+fn pass_canonical_vec_ignores_map_order() {
+    use rmp::value::Float;
+    use rmp_serde::value::to_canonical_vec;
+
+    let lhs = Value::Map(vec![
+        (Value::String("b".to_string()), Value::Integer(Integer::I64(5))),
+        (Value::String("a".to_string()), Value::Map(vec![
+            (Value::Integer(Integer::U64(2)), Value::Float(Float::F64(0.5))),
+            (Value::Integer(Integer::U64(1)), Value::Nil),
+        ])),
+    ]);
+    let rhs = Value::Map(vec![
+        (Value::String("a".to_string()), Value::Map(vec![
+            (Value::Integer(Integer::I64(1)), Value::Nil),
+            (Value::Integer(Integer::U64(2)), Value::Float(Float::F32(0.5))),
+        ])),
+        (Value::String("b".to_string()), Value::Integer(Integer::U64(5))),
+    ]);
+
+    let expected = vec![
+        0x82,
+        0xa1, 0x61, 0x82, 0x01, 0xc0, 0x02, 0xca, 0x3f, 0x00, 0x00, 0x00,
+        0xa1, 0x62, 0x05,
+    ];
+
+    assert_eq!(expected, to_canonical_vec(&lhs));
+    assert_eq!(expected, to_canonical_vec(&rhs));
+}
+
+#[test]
+fn pass_canonical_vec_keeps_inexact_floats() {
+    use rmp::value::Float;
+    use rmp_serde::value::to_canonical_vec;
+
+    let buf = to_canonical_vec(&Value::Float(Float::F64(0.1)));
+
+    assert_eq!(0xcb, buf[0]);
+    assert_eq!(9, buf.len());
+}
+
+#[test]
+fn pass_value_into_deserializer() {
+    use serde::de::value::ValueDeserializer;
+
+    let value = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+        (Value::String("nickname".to_string()), Value::String("Johnny".to_string())),
+    ]);
+
+    let mut de = value.into_deserializer();
+    let actual: Profile = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(Profile { name: "John".to_string(), nickname: Some("Johnny".to_string()) }, actual);
+}
+
+impl serde::Serialize for Profile {
+    fn serialize<S>(&self, ser: &mut S) -> result::Result<(), S::Error>
+        where S: serde::Serializer
+    {
+        struct Visitor<'a>(&'a Profile, usize);
+
+        impl<'a> serde::ser::MapVisitor for Visitor<'a> {
+            fn visit<S>(&mut self, ser: &mut S) -> result::Result<Option<()>, S::Error>
+                where S: serde::Serializer
+            {
+                self.1 += 1;
+                match self.1 {
+                    1 => ser.serialize_struct_elt("name", &self.0.name).map(Some),
+                    2 => ser.serialize_struct_elt("nickname", &self.0.nickname).map(Some),
+                    _ => Ok(None),
+                }
+            }
+
+            fn len(&self) -> Option<usize> {
+                Some(2)
+            }
+        }
+
+        ser.serialize_struct("Profile", Visitor(self, 0))
+    }
+}
+
+#[test]
+fn pass_field_map_round_trip() {
+    use std::collections::HashMap;
+    use serde::Serialize;
+    use rmp_serde::value::encode::Serializer;
+
+    let mut fields = HashMap::new();
+    fields.insert("name", 0);
+    fields.insert("nickname", 1);
+
+    let expected = vec![Profile { name: "John".to_string(), nickname: Some("Johnny".to_string()) }];
+
+    let mut ser = Serializer::new();
+    ser.set_field_map(fields);
+    expected.serialize(&mut ser).unwrap();
+    let value = ser.unwrap();
+
+    assert_eq!(Value::Array(vec![Value::Map(vec![
+        (Value::Integer(Integer::U64(0)), Value::String("John".to_string())),
+        (Value::Integer(Integer::U64(1)), Value::String("Johnny".to_string())),
+    ])]), value);
+
+    let mut indices = HashMap::new();
+    indices.insert(0, "name");
+    indices.insert(1, "nickname");
+
+    let mut de = Deserializer::new(value);
+    de.set_field_map(indices);
+    let actual: Vec<Profile> = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn fail_field_map_missing_field() {
+    use std::collections::HashMap;
+    use serde::Serialize;
+    use rmp_serde::value::encode::{Error, Serializer};
+
+    let mut fields = HashMap::new();
+    fields.insert("name", 0);
+
+    let mut ser = Serializer::new();
+    ser.set_field_map(fields);
+
+    let profile = Profile { name: "John".to_string(), nickname: None };
+
+    match profile.serialize(&mut ser) {
+        Err(Error::Custom(ref msg)) => assert_eq!("field `nickname` is missing from the field map", msg),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_field_map_missing_index() {
+    use std::collections::HashMap;
+
+    let value = Value::Map(vec![
+        (Value::Integer(Integer::U64(0)), Value::String("John".to_string())),
+        (Value::Integer(Integer::U64(7)), Value::Nil),
+    ]);
+
+    let mut indices = HashMap::new();
+    indices.insert(0, "name");
+    indices.insert(1, "nickname");
+
+    let mut de = Deserializer::new(value);
+    de.set_field_map(indices);
+
+    match Profile::deserialize(&mut de) {
+        Err(Error::Syntax(ref msg)) => {
+            assert_eq!("syntax error: field index 7 is missing from the field map", msg)
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_value_ext_mutate_nested_map() {
+    let mut value = Value::Map(vec![
+        (Value::String("id".to_string()), Value::Integer(Integer::U64(42))),
+        (Value::String("config".to_string()), Value::Map(vec![
+            (Value::String("timeout".to_string()), Value::Integer(Integer::U64(5))),
+            (Value::String("hosts".to_string()), Value::Array(vec![Value::String("a".to_string())])),
+        ])),
+    ]);
+
+    {
+        let config = value.get_mut("config").unwrap();
+        *config.get_mut("timeout").unwrap() = Value::Integer(Integer::U64(7));
+        config.get_mut("hosts").unwrap().as_array_mut().unwrap().push(Value::String("b".to_string()));
+    }
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("id".to_string()), Value::Integer(Integer::U64(42))),
+        (Value::String("config".to_string()), Value::Map(vec![
+            (Value::String("timeout".to_string()), Value::Integer(Integer::U64(7))),
+            (Value::String("hosts".to_string()), Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+            ])),
+        ])),
+    ]), value);
+
+    assert!(value.get_mut("missing").is_none());
+    assert!(value.as_array_mut().is_none());
+}
+
+#[test]
+fn pass_value_ext_take_and_reinsert() {
+    let mut value = Value::Map(vec![
+        (Value::String("id".to_string()), Value::Integer(Integer::U64(42))),
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+    ]);
+
+    let id = value.get_mut("id").unwrap().take();
+    assert_eq!(Value::Integer(Integer::U64(42)), id);
+
+    // Existing keys keep their position, new ones are appended.
+    assert_eq!(Some(Value::Nil), value.insert(Value::String("id".to_string()), Value::Integer(Integer::U64(43))).unwrap());
+    assert_eq!(None, value.insert(Value::String("admin".to_string()), Value::Boolean(true)).unwrap());
+
+    assert_eq!(Value::Map(vec![
+        (Value::String("id".to_string()), Value::Integer(Integer::U64(43))),
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+        (Value::String("admin".to_string()), Value::Boolean(true)),
+    ]), value);
+}
+
+#[test]
+fn fail_value_ext_insert_into_non_map() {
+    match Value::Nil.insert(Value::Nil, Value::Nil) {
+        Err(Error::TypeMismatch(Marker::Map32)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,350 @@
+#![cfg_attr(feature = "serde_macros", feature(custom_derive, plugin))]
+#![cfg_attr(feature = "serde_macros", plugin(serde_macros))]
+
+#![cfg(feature = "serde_macros")]
+
+extern crate serde;
+extern crate rmp;
+extern crate rmp_serde;
+
+use rmp::Value;
+use rmp::value::Integer;
+use rmp_serde::value::{from_value, to_value};
+use rmp_serde::value::decode::Result;
+
+#[test]
+fn pass_single_field_tuple_struct_from_bare_value() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Decoded(u32);
+
+    let actual: Decoded = from_value(Value::Integer(Integer::U64(42))).unwrap();
+
+    assert_eq!(Decoded(42), actual);
+}
+
+#[test]
+fn pass_single_field_tuple_struct_from_array() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Decoded(u32);
+
+    let actual: Decoded = from_value(Value::Array(vec![Value::Integer(Integer::U64(42))])).unwrap();
+
+    assert_eq!(Decoded(42), actual);
+}
+
+#[test]
+fn pass_tuple_struct_from_array() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Decoded(u32, String);
+
+    let expected = Decoded(42, "le message".to_string());
+
+    assert_eq!(expected, from_value(to_value(&expected)).unwrap());
+}
+
+#[test]
+fn fail_tuple_struct_from_bare_value() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Decoded(u32, u32);
+
+    let res: Result<Decoded> = from_value(Value::Integer(Integer::U64(42)));
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn pass_unit_struct_from_nil_and_empty_array() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Unit;
+
+    assert_eq!(Unit, from_value(Value::Nil).unwrap());
+    assert_eq!(Unit, from_value(Value::Array(vec![])).unwrap());
+}
+
+#[test]
+fn pass_result_field_with_compact_encoding() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Response {
+        #[serde(serialize_with = "rmp_serde::value::result::serialize",
+                deserialize_with = "rmp_serde::value::result::deserialize")]
+        result: ::std::result::Result<Vec<u8>, String>,
+    }
+
+    let ok = Response { result: Ok(vec![1, 2]) };
+    let err = Response { result: Err("le message".to_string()) };
+
+    assert_eq!(ok, from_value(to_value(&ok)).unwrap());
+    assert_eq!(err, from_value(to_value(&err)).unwrap());
+}
+
+#[test]
+fn pass_cow_bytes_field_round_trip() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Chunk<'a> {
+        #[serde(serialize_with = "rmp_serde::value::cow_bytes::serialize",
+                deserialize_with = "rmp_serde::value::cow_bytes::deserialize")]
+        data: Cow<'a, [u8]>,
+    }
+
+    let bytes = [1, 2, 3];
+    let borrowed = Chunk { data: Cow::Borrowed(&bytes[..]) };
+    let owned = Chunk { data: Cow::Owned(bytes.to_vec()) };
+
+    let expected = Value::Map(vec![
+        (Value::String("data".into()), Value::Binary(bytes.to_vec())),
+    ]);
+    assert_eq!(expected, to_value(&borrowed));
+    assert_eq!(expected, to_value(&owned));
+
+    let actual: Chunk = from_value(expected).unwrap();
+    assert_eq!(owned, actual);
+    assert_eq!(borrowed, actual);
+}
+
+#[test]
+fn pass_trace_struct() {
+    use serde::Serialize;
+    use rmp_serde::value::{Trace, TracingSerializer};
+    use rmp_serde::value::encode::Serializer;
+
+    #[derive(Serialize)]
+    struct Struct {
+        id: u32,
+        name: String,
+    }
+
+    let trace = Trace::new();
+    let mut ser = Serializer::new();
+    Struct { id: 42, name: "x".to_string() }
+        .serialize(&mut TracingSerializer::new(&mut ser, &trace)).unwrap();
+
+    assert_eq!(vec!["serialize_struct(Struct, 2)",
+                    "serialize_struct_elt(id)",
+                    "serialize_u32(42)",
+                    "serialize_struct_elt(name)",
+                    "serialize_str(\"x\")"], trace.ops());
+}
+
+#[test]
+fn pass_raw_value_field() {
+    use rmp_serde::value::RawValue;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Request {
+        method: String,
+        params: RawValue,
+    }
+
+    let params = Value::Array(vec![
+        Value::Integer(Integer::U64(42)),
+        Value::Map(vec![(Value::String("le".to_string()), Value::Nil)]),
+    ]);
+
+    let value = Value::Map(vec![
+        (Value::String("method".to_string()), Value::String("call".to_string())),
+        (Value::String("params".to_string()), params.clone()),
+    ]);
+
+    let actual: Request = from_value(value).unwrap();
+
+    assert_eq!(Request { method: "call".to_string(), params: RawValue(params) }, actual);
+}
+
+#[test]
+fn pass_struct_with_field_aliases() {
+    use std::collections::HashMap;
+    use serde::Deserialize;
+    use rmp_serde::value::decode::Deserializer;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Event {
+        id: u32,
+        timestamp: u64,
+    }
+
+    let value = Value::Map(vec![
+        (Value::String("id".to_string()), Value::Integer(Integer::U64(1))),
+        (Value::String("ts".to_string()), Value::Integer(Integer::U64(42))),
+    ]);
+
+    let mut aliases = HashMap::new();
+    aliases.insert("ts".to_string(), "timestamp".to_string());
+
+    let mut de = Deserializer::new(value);
+    de.set_field_aliases(aliases);
+
+    let actual: Event = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(Event { id: 1, timestamp: 42 }, actual);
+}
+
+#[test]
+fn pass_enum_keyed_map() {
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert(Color::Red, 1u32);
+    map.insert(Color::Green, 2u32);
+
+    let value = to_value(&map);
+    let expected = Value::Map(vec![
+        (Value::String("Red".to_string()), Value::Integer(Integer::U64(1))),
+        (Value::String("Green".to_string()), Value::Integer(Integer::U64(2))),
+    ]);
+    assert_eq!(expected, value);
+
+    let actual: Result<BTreeMap<Color, u32>> = from_value(value);
+    assert_eq!(map, actual.unwrap());
+}
+
+#[test]
+fn pass_struct_skipping_version_prefix() {
+    use rmp_serde::value::from_value_skipping_prefix;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Payload {
+        id: u32,
+        name: String,
+    }
+
+    let frame = Value::Array(vec![
+        Value::Integer(Integer::U64(2)),
+        Value::Map(vec![
+            (Value::String("id".to_string()), Value::Integer(Integer::U64(42))),
+            (Value::String("name".to_string()), Value::String("John".to_string())),
+        ]),
+    ]);
+
+    let actual: Result<Payload> = from_value_skipping_prefix(frame, 1);
+
+    assert_eq!(Payload { id: 42, name: "John".to_string() }, actual.unwrap());
+}
+
+#[test]
+fn pass_enum_from_int() {
+    use serde::Deserialize;
+    use rmp_serde::value::decode::Deserializer;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Level {
+        Low,
+        Mid,
+        High,
+    }
+
+    let mut de = Deserializer::new(Value::Integer(Integer::U64(2)));
+    de.set_enum_from_int(true);
+
+    let actual: Level = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(Level::High, actual);
+}
+
+#[test]
+fn pass_enum_as_int_round_trip() {
+    use serde::Deserialize;
+    use rmp_serde::value::{SerializerOptions, to_value_with};
+    use rmp_serde::value::decode::Deserializer;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Level {
+        Low,
+        Mid,
+        High,
+    }
+
+    let opts = SerializerOptions { enum_as_int: true, ..SerializerOptions::default() };
+    let expected = vec![Level::High, Level::Low, Level::Mid];
+
+    let value = to_value_with(&expected, opts).unwrap();
+    assert_eq!(Value::Array(vec![
+        Value::Integer(Integer::U64(2)),
+        Value::Integer(Integer::U64(0)),
+        Value::Integer(Integer::U64(1)),
+    ]), value);
+
+    let mut de = Deserializer::new(value);
+    de.set_enum_from_int(true);
+    let actual: Vec<Level> = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn pass_option_field_from_nil_or_omitted_key() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Profile {
+        name: String,
+        nickname: Option<String>,
+    }
+
+    let expected = Profile { name: "John".to_string(), nickname: None };
+
+    let explicit = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+        (Value::String("nickname".to_string()), Value::Nil),
+    ]);
+    let omitted = Value::Map(vec![
+        (Value::String("name".to_string()), Value::String("John".to_string())),
+    ]);
+
+    assert_eq!(expected, from_value(explicit).unwrap());
+    assert_eq!(expected, from_value(omitted).unwrap());
+}
+
+#[test]
+fn pass_renamed_fields_round_trip() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Session {
+        #[serde(rename = "userId")]
+        user_id: u32,
+        #[serde(rename = "lastSeenAt")]
+        last_seen_at: u64,
+    }
+
+    let session = Session { user_id: 42, last_seen_at: 1000 };
+
+    let value = to_value(&session);
+    assert_eq!(Value::Map(vec![
+        (Value::String("userId".to_string()), Value::Integer(Integer::U64(42))),
+        (Value::String("lastSeenAt".to_string()), Value::Integer(Integer::U64(1000))),
+    ]), value);
+
+    assert_eq!(session, from_value(value).unwrap());
+
+    // The Rust field names are not recognized in place of the renamed ones.
+    let raw = Value::Map(vec![
+        (Value::String("user_id".to_string()), Value::Integer(Integer::U64(42))),
+        (Value::String("last_seen_at".to_string()), Value::Integer(Integer::U64(1000))),
+    ]);
+    assert!(from_value::<Session>(raw).is_err());
+}
+
+#[test]
+fn pass_binary_str_field_round_trip() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        topic: String,
+        #[serde(serialize_with = "rmp_serde::value::binary_str::serialize",
+                deserialize_with = "rmp_serde::value::binary_str::deserialize")]
+        payload: String,
+    }
+
+    let message = Message { topic: "news".to_string(), payload: "le message".to_string() };
+
+    let value = to_value(&message);
+    assert_eq!(Value::Map(vec![
+        (Value::String("topic".to_string()), Value::String("news".to_string())),
+        (Value::String("payload".to_string()), Value::Binary(b"le message".to_vec())),
+    ]), value);
+
+    assert_eq!(message, from_value(value).unwrap());
+}
@@ -1,5 +1,7 @@
 //! Contains Value and ValueRef structs and its conversion traits.
 
+use std::cmp::Ordering;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Integer {
     /// Every non-negative integer is treated as u64, even if it fits in i64.
@@ -8,7 +10,7 @@ pub enum Integer {
     I64(i64),
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug)]
 pub enum Float {
     F32(f32),
     F64(f64),
@@ -84,6 +86,134 @@ impl ::std::fmt::Display for Value {
     }
 }
 
+/// Integers are ordered by their numeric value. Equal numbers of different representations are
+/// ordered `I64` first to stay consistent with equality.
+impl Eq for Integer {}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Integer) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Integer) -> Ordering {
+        match (*self, *other) {
+            (Integer::U64(lhs), Integer::U64(rhs)) => lhs.cmp(&rhs),
+            (Integer::I64(lhs), Integer::I64(rhs)) => lhs.cmp(&rhs),
+            (Integer::I64(lhs), Integer::U64(rhs)) => {
+                if lhs < 0 || (lhs as u64) <= rhs {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+            (Integer::U64(..), Integer::I64(..)) => other.cmp(self).reverse(),
+        }
+    }
+}
+
+/// Floats are equal if they have the same width and bits, so that NaN equals itself and `0.0`
+/// differs from `-0.0`, consistently with their order.
+impl PartialEq for Float {
+    fn eq(&self, other: &Float) -> bool {
+        self.width() == other.width() && self.to_bits() == other.to_bits()
+    }
+}
+
+/// Floats are ordered by their numeric value, with NaN greater than any other number. Equal
+/// numbers of different widths are ordered `F32` first, `-0.0` is ordered before `0.0` and NaNs
+/// are ordered by their bits.
+impl Eq for Float {}
+
+impl PartialOrd for Float {
+    fn partial_cmp(&self, other: &Float) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float {
+    fn cmp(&self, other: &Float) -> Ordering {
+        let (lhs, rhs) = (self.as_f64(), other.as_f64());
+
+        match lhs.partial_cmp(&rhs) {
+            Some(Ordering::Equal) | None => {
+                let lhs = (lhs.is_nan(), self.width(), lhs.is_sign_positive(), self.to_bits());
+                let rhs = (rhs.is_nan(), other.width(), rhs.is_sign_positive(), other.to_bits());
+                lhs.cmp(&rhs)
+            }
+            Some(ord) => ord,
+        }
+    }
+}
+
+impl Float {
+    fn as_f64(&self) -> f64 {
+        match *self {
+            Float::F32(val) => val as f64,
+            Float::F64(val) => val,
+        }
+    }
+
+    fn to_bits(&self) -> u64 {
+        match *self {
+            Float::F32(val) => val.to_bits() as u64,
+            Float::F64(val) => val.to_bits(),
+        }
+    }
+
+    fn width(&self) -> u8 {
+        match *self {
+            Float::F32(..) => 32,
+            Float::F64(..) => 64,
+        }
+    }
+}
+
+/// Values of different types are ordered as the variants are declared, from `Nil` to `Ext`.
+/// Values of the same type are ordered by their content, arrays and maps lexicographically.
+///
+/// This allows to use values as keys of ordered collections, like `BTreeMap`.
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (&Value::Boolean(lhs), &Value::Boolean(rhs)) => lhs.cmp(&rhs),
+            (&Value::Integer(ref lhs), &Value::Integer(ref rhs)) => lhs.cmp(rhs),
+            (&Value::Float(ref lhs), &Value::Float(ref rhs)) => lhs.cmp(rhs),
+            (&Value::String(ref lhs), &Value::String(ref rhs)) => lhs.cmp(rhs),
+            (&Value::Binary(ref lhs), &Value::Binary(ref rhs)) => lhs.cmp(rhs),
+            (&Value::Array(ref lhs), &Value::Array(ref rhs)) => lhs.cmp(rhs),
+            (&Value::Map(ref lhs), &Value::Map(ref rhs)) => lhs.cmp(rhs),
+            (&Value::Ext(lty, ref lhs), &Value::Ext(rty, ref rhs)) => (lty, lhs).cmp(&(rty, rhs)),
+            (lhs, rhs) => lhs.rank().cmp(&rhs.rank()),
+        }
+    }
+}
+
+impl Value {
+    fn rank(&self) -> u8 {
+        match *self {
+            Value::Nil => 0,
+            Value::Boolean(..) => 1,
+            Value::Integer(..) => 2,
+            Value::Float(..) => 3,
+            Value::String(..) => 4,
+            Value::Binary(..) => 5,
+            Value::Array(..) => 6,
+            Value::Map(..) => 7,
+            Value::Ext(..) => 8,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ValueRef<'a> {
     /// Nil represents nil.
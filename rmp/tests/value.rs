@@ -58,3 +58,77 @@ fn display_ext() {
     assert_eq!("[1, [100]]", format!("{}", Value::Ext(1, vec![100])));
     assert_eq!("[1, [100, 42]]", format!("{}", Value::Ext(1, vec![100, 42])));
 }
+
+#[test]
+fn ord_between_types() {
+    let values = vec![
+        Value::Nil,
+        Value::Boolean(false),
+        Value::Integer(Integer::U64(0)),
+        Value::Float(Float::F64(0.0)),
+        Value::String("".to_string()),
+        Value::Binary(vec![]),
+        Value::Array(vec![]),
+        Value::Map(vec![]),
+        Value::Ext(0, vec![]),
+    ];
+
+    for (idx, lhs) in values.iter().enumerate() {
+        for rhs in &values[idx + 1..] {
+            assert!(lhs < rhs, "{} < {}", lhs, rhs);
+        }
+    }
+}
+
+#[test]
+fn ord_int() {
+    assert!(Integer::I64(-1) < Integer::U64(0));
+    assert!(Integer::I64(42) < Integer::U64(42));
+    assert!(Integer::U64(42) < Integer::I64(43));
+    assert!(Integer::I64(i64::max_value()) < Integer::U64(u64::max_value()));
+}
+
+#[test]
+fn ord_float() {
+    use std::f64;
+
+    assert!(Float::F64(-1.0) < Float::F32(0.5));
+    assert!(Float::F32(1.5) < Float::F64(1.5));
+    assert!(Float::F64(f64::INFINITY) < Float::F64(f64::NAN));
+    assert_eq!(::std::cmp::Ordering::Equal, Float::F64(f64::NAN).cmp(&Float::F64(f64::NAN)));
+    assert!(Float::F64(-0.0) < Float::F64(0.0));
+}
+
+#[test]
+fn eq_float_consistent_with_ord() {
+    use std::cmp::Ordering;
+    use std::f64;
+
+    let floats = [
+        Float::F64(f64::NAN),
+        Float::F32(::std::f32::NAN),
+        Float::F64(0.0),
+        Float::F64(-0.0),
+        Float::F32(1.5),
+        Float::F64(1.5),
+        Float::F64(f64::INFINITY),
+    ];
+
+    for lhs in &floats {
+        for rhs in &floats {
+            assert_eq!(lhs == rhs, lhs.cmp(rhs) == Ordering::Equal, "{:?} and {:?}", lhs, rhs);
+        }
+    }
+
+    assert_eq!(Value::Float(Float::F64(f64::NAN)), Value::Float(Float::F64(f64::NAN)));
+    assert!(Value::Float(Float::F64(0.0)) != Value::Float(Float::F64(-0.0)));
+}
+
+#[test]
+fn ord_array_lexicographically() {
+    let lhs = Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(2))]);
+    let rhs = Value::Array(vec![Value::Integer(Integer::U64(1)), Value::Integer(Integer::U64(3))]);
+
+    assert!(lhs < rhs);
+    assert!(Value::Array(vec![Value::Nil]) < Value::Array(vec![Value::Nil, Value::Nil]));
+}